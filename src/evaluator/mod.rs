@@ -8,16 +8,46 @@ use crate::operators::*;
 use crate::parser::expression::Expression;
 use anyhow::{Result, anyhow};
 use serde_yaml::Value;
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// A user-defined function, with the functions visible where it was defined
+#[derive(Debug)]
+pub struct Function {
+    /// Names of the filter parameters
+    pub params: Vec<String>,
+    /// The function body
+    pub body: Expression,
+    /// Functions in scope at the definition
+    pub env: Rc<Functions>,
+    /// Whether the body sees the function itself (false for the filters
+    /// passed as arguments, which run in the caller's scope)
+    pub recursive: bool,
+}
+
+/// User-defined functions in scope, keyed by `name/arity`
+pub type Functions = std::collections::HashMap<String, Rc<Function>>;
+
+/// Variables in scope, keyed by name without the `$`; the values are shared
+/// so copying the table doesn't copy them
+pub type Variables = std::collections::HashMap<String, Rc<Value>>;
+
+/// Outputs of an expression, produced as they are pulled
+pub type ValueStream<'a> = Box<dyn Iterator<Item = Result<Value>> + 'a>;
 
 /// Context for expression evaluation
+///
+/// Variables and functions are shared between a context and its children
+/// and only copied when a binding or definition changes them, so creating
+/// a child for every value in a pipeline costs no more than the value.
 #[derive(Debug, Clone)]
 pub struct Context {
     /// The current value being processed
     pub value: Value,
-    /// Parent context (for relative lookups)
-    pub parent: Option<Box<Context>>,
     /// Variables in scope
-    pub variables: std::collections::HashMap<String, Value>,
+    pub variables: Rc<Variables>,
+    /// User-defined functions in scope
+    pub functions: Rc<Functions>,
 }
 
 impl Context {
@@ -25,51 +55,138 @@ impl Context {
     pub fn new(value: Value) -> Self {
         Self {
             value,
-            parent: None,
-            variables: std::collections::HashMap::new(),
+            variables: Rc::default(),
+            functions: Rc::default(),
         }
     }
 
-    /// Create a child context
+    /// Create a child context: the same variables and functions with a new
+    /// value
     pub fn child(&self, value: Value) -> Self {
         Self {
             value,
-            parent: Some(Box::new(self.clone())),
-            variables: self.variables.clone(),
+            variables: Rc::clone(&self.variables),
+            functions: Rc::clone(&self.functions),
         }
     }
 
-    /// Set a variable
+    /// A context to create children from later, without keeping a copy of
+    /// this one's value
+    pub fn scope(&self) -> Self {
+        self.child(Value::Null)
+    }
+
+    /// Set a variable, copying the variables first if they are shared
     pub fn set_variable(&mut self, name: String, value: Value) {
-        self.variables.insert(name, value);
+        Rc::make_mut(&mut self.variables).insert(name, Rc::new(value));
     }
 
     /// Get a variable
     pub fn get_variable(&self, name: &str) -> Option<&Value> {
-        self.variables.get(name)
+        self.variables.get(name).map(|value| value.as_ref())
     }
 }
 
+/// Default limit on nested evaluation, and on how deeply `..` descends
+pub const DEFAULT_MAX_DEPTH: usize = 512;
+
 /// Expression evaluator
-pub struct Evaluator;
+pub struct Evaluator {
+    /// Global variables (e.g. from `--arg`) visible to every evaluation
+    variables: Rc<Variables>,
+    /// Maximum nesting of evaluation calls before failing
+    max_depth: usize,
+    /// Current nesting of evaluation calls
+    depth: Cell<usize>,
+}
+
+/// One level of evaluation depth, released when dropped
+struct DepthGuard<'a>(&'a Cell<usize>);
+
+impl Drop for DepthGuard<'_> {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() - 1);
+    }
+}
 
 impl Evaluator {
     /// Create a new evaluator
     pub fn new() -> Self {
-        Self
+        Self::with_max_depth(DEFAULT_MAX_DEPTH)
+    }
+
+    /// Create an evaluator that fails cleanly instead of overflowing the
+    /// stack once evaluation (or `..` over a document) nests deeper than
+    /// `max_depth`
+    pub fn with_max_depth(max_depth: usize) -> Self {
+        Self {
+            variables: Rc::default(),
+            max_depth,
+            depth: Cell::new(0),
+        }
+    }
+
+    /// The maximum nesting depth
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Enter one level of evaluation, failing past the maximum depth
+    fn enter(&self) -> Result<DepthGuard<'_>> {
+        let depth = self.depth.get() + 1;
+        if depth > self.max_depth {
+            return Err(anyhow!(
+                "Maximum evaluation depth of {} exceeded",
+                self.max_depth
+            ));
+        }
+        self.depth.set(depth);
+        Ok(DepthGuard(&self.depth))
+    }
+
+    /// Define a global variable available as `$name`
+    pub fn set_variable(&mut self, name: String, value: Value) {
+        Rc::make_mut(&mut self.variables).insert(name, Rc::new(value));
     }
 
     /// Evaluate an expression against input data
     pub fn evaluate(&self, expr: &Expression, input: Option<&Value>) -> Result<Value> {
-        let ctx = match input {
+        let mut ctx = match input {
             Some(v) => Context::new(v.clone()),
             None => Context::new(Value::Null),
         };
+        ctx.variables = Rc::clone(&self.variables);
         self.eval(expr, &ctx)
     }
 
+    /// Evaluate an expression against input data, collecting every output
+    ///
+    /// Generators such as `.[]` produce one value per output; `empty`
+    /// produces none.
+    pub fn evaluate_multi(&self, expr: &Expression, input: Option<&Value>) -> Result<Vec<Value>> {
+        let mut ctx = Context::new(input.cloned().unwrap_or(Value::Null));
+        ctx.variables = Rc::clone(&self.variables);
+        self.eval_multi(expr, &ctx)
+    }
+
+    /// Evaluate an expression against input data, producing each output as
+    /// it is pulled
+    ///
+    /// An error ends the stream as its last item, after every output that
+    /// came before it.
+    pub fn evaluate_lazy<'a>(
+        &'a self,
+        expr: &'a Expression,
+        input: Option<&Value>,
+    ) -> ValueStream<'a> {
+        let mut ctx = Context::new(input.cloned().unwrap_or(Value::Null));
+        ctx.variables = Rc::clone(&self.variables);
+        self.stream(expr, &ctx)
+    }
+
     /// Evaluate an expression in a context
     pub fn eval(&self, expr: &Expression, ctx: &Context) -> Result<Value> {
+        let _depth = self.enter()?;
         match expr {
             Expression::Identity => Ok(ctx.value.clone()),
             Expression::Literal(v) => Ok(v.clone()),
@@ -80,11 +197,17 @@ impl Evaluator {
             Expression::IndexAccess { target, index } => {
                 index_access::eval(self, target, *index, ctx)
             }
+            Expression::DynamicIndex { target, index } => {
+                index_access::eval_dynamic(self, target, index, ctx)
+            }
             Expression::Iterator { target } => iterator::eval(self, target, ctx),
             Expression::Pipe { left, right } => pipe::eval(self, left, right, ctx),
             Expression::Comma { left, right } => comma::eval(self, left, right, ctx),
             Expression::Assign { target, value } => assign::eval(self, target, value, ctx),
             Expression::Update { target, value } => update::eval(self, target, value, ctx),
+            Expression::ArithmeticUpdate { target, op, value } => {
+                update::eval_arithmetic(self, target, op, value, ctx)
+            }
             Expression::Add { left, right } => arithmetic::add(self, left, right, ctx),
             Expression::Subtract { left, right } => arithmetic::sub(self, left, right, ctx),
             Expression::Multiply { left, right } => arithmetic::mul(self, left, right, ctx),
@@ -106,81 +229,450 @@ impl Evaluator {
             Expression::Or { left, right } => logical::or(self, left, right, ctx),
             Expression::Not { expr } => logical::not(self, expr, ctx),
             Expression::Select { condition } => select::eval(self, condition, ctx),
+            Expression::TypeFilter { name } => Ok(type_filters::eval(name, ctx)),
             Expression::Keys { target } => keys::eval(self, target, ctx),
+            Expression::KeysUnsorted { target } => keys::eval_unsorted(self, target, ctx),
             Expression::Length { target } => length::eval(self, target, ctx),
             Expression::Type { target } => crate::operators::type_op::eval(self, target, ctx),
             Expression::Has { target, key } => has::eval(self, target, key, ctx),
+            Expression::Contains { target, value } => contains::eval(self, target, value, ctx),
+            Expression::Split { target, separator } => split::eval(self, target, separator, ctx),
+            Expression::StartsWith { target, prefix } => {
+                contains::eval_affix(self, target, prefix, false, ctx)
+            }
+            Expression::EndsWith { target, suffix } => {
+                contains::eval_affix(self, target, suffix, true, ctx)
+            }
+            Expression::In { container } => in_op::eval(self, container, ctx),
             Expression::Sort { target } => sort::eval(self, target, ctx),
             Expression::Reverse { target } => reverse::eval(self, target, ctx),
             Expression::Unique { target } => unique::eval(self, target, ctx),
+            Expression::UniqueBy { target, key_expr } => {
+                unique::eval_by(self, target, key_expr, ctx)
+            }
             Expression::Flatten { target } => flatten::eval(self, target, ctx),
+            Expression::SortBy { target, key_expr } => sort_by::eval(self, target, key_expr, ctx),
             Expression::GroupBy { target, key_expr } => group_by::eval(self, target, key_expr, ctx),
+            Expression::CountBy { target, key_expr } => count_by::eval(self, target, key_expr, ctx),
             Expression::Map { target, expr } => map::eval(self, target, expr, ctx),
+            Expression::MapValues { target, expr } => map_values::eval(self, target, expr, ctx),
             Expression::Filter { target, expr } => filter::eval(self, target, expr, ctx),
+            Expression::IfThenElse {
+                condition,
+                then_branch,
+                else_branch,
+            } => if_then_else::eval(self, condition, then_branch, else_branch, ctx),
+            Expression::Walk { f } => walk::eval(self, f, ctx),
             Expression::Recurse => recurse::eval(self, ctx),
+            Expression::RecurseF { f, cond } => recurse::eval_with(self, f, cond.as_deref(), ctx),
+            Expression::ToStream => stream::eval_tostream(self, ctx),
+            Expression::Path { expr } => path::eval(self, expr, ctx),
+            Expression::Format { target, fmt } => format::eval(self, target, fmt, ctx),
+            Expression::Splits {
+                target,
+                pattern,
+                flags,
+            } => regex::eval_splits(self, target, pattern, flags.as_deref(), ctx),
+            Expression::Test {
+                target,
+                pattern,
+                flags,
+            } => regex::eval_test(self, target, pattern, flags.as_deref(), ctx),
+            Expression::Match {
+                target,
+                pattern,
+                flags,
+            } => regex::eval_match(self, target, pattern, flags.as_deref(), ctx),
+            Expression::Sub {
+                target,
+                pattern,
+                replacement,
+                flags,
+            } => regex::eval_sub(self, target, pattern, replacement, flags.as_deref(), ctx),
+            Expression::Gsub {
+                target,
+                pattern,
+                replacement,
+                flags,
+            } => regex::eval_gsub(self, target, pattern, replacement, flags.as_deref(), ctx),
+            Expression::Interpolation { parts } => interpolation::eval(self, parts, ctx),
+            Expression::FromStream { expr } => stream::eval_fromstream(self, expr, ctx),
             Expression::Group { expr } => self.eval(expr, ctx),
-            Expression::Variable { name } => ctx
-                .get_variable(name)
-                .cloned()
-                .ok_or_else(|| anyhow!("Undefined variable: {}", name)),
+            Expression::Variable { name } => match ctx.get_variable(name) {
+                Some(value) => Ok(value.clone()),
+                None if name == "ENV" => Ok(env::env_object()),
+                None => Err(anyhow!("Undefined variable: {}", name)),
+            },
+            Expression::FunctionDef {
+                name,
+                params,
+                body,
+                rest,
+            } => self.eval(rest, &function::define(name, params, body, ctx)),
+            Expression::FunctionCall { name, args } => function::eval_call(self, name, args, ctx),
+            Expression::Bind {
+                source,
+                pattern,
+                body,
+            } => bind::eval(self, source, pattern, body, ctx),
+            Expression::Loc { file, line } => {
+                let mut loc = serde_yaml::Mapping::new();
+                loc.insert("file".into(), Value::String(file.clone()));
+                loc.insert("line".into(), Value::Number((*line as u64).into()));
+                Ok(Value::Mapping(loc))
+            }
             Expression::Array { elements } => array::eval(self, elements, ctx),
             Expression::Object { fields } => object::eval(self, fields, ctx),
+            Expression::Range { start, end, by } => {
+                range::eval(self, start, end, by.as_deref(), ctx)
+            }
             Expression::Slice { target, start, end } => {
                 slice::eval(self, target, *start, *end, ctx)
             }
             Expression::Alternative { left, right } => alternative::eval(self, left, right, ctx),
             Expression::First { expr } => first::eval(self, expr, ctx),
             Expression::Last { expr } => last::eval(self, expr, ctx),
+            Expression::Limit { n, expr } => limit::eval(self, n, expr, ctx),
+            Expression::Nth { n, expr } => nth::eval(self, n, expr, ctx),
+            Expression::Min { target } => min_max::eval(self, target, None, false, ctx),
+            Expression::Max { target } => min_max::eval(self, target, None, true, ctx),
+            Expression::MinBy { target, key } => min_max::eval(self, target, Some(key), false, ctx),
+            Expression::MaxBy { target, key } => min_max::eval(self, target, Some(key), true, ctx),
             Expression::AddOp => add::eval(self, ctx),
-            Expression::Env { name } => env::eval(self, name, ctx),
+            Expression::Explode => codepoints::eval_explode(self, ctx),
+            Expression::Implode => codepoints::eval_implode(self, ctx),
+            Expression::Ascii => codepoints::eval_ascii(self, ctx),
+            Expression::Abs => math::eval_abs(self, ctx),
+            Expression::Signum => math::eval_signum(self, ctx),
+            Expression::MathFn { name } => math::eval_unary(self, name, ctx),
+            Expression::MathFn2 { name, left, right } => {
+                math::eval_binary(self, name, left, right, ctx)
+            }
+            Expression::Now => datetime::eval_now(),
+            Expression::ToDate => datetime::eval_todate(ctx),
+            Expression::FromDate => datetime::eval_fromdate(ctx),
+            Expression::Strftime { format } => datetime::eval_strftime(self, format, ctx),
+            Expression::Gmtime => datetime::eval_gmtime(ctx),
+            Expression::Mktime => datetime::eval_mktime(ctx),
+            Expression::Strptime { format } => datetime::eval_strptime(self, format, ctx),
+            Expression::Env { name } => env::eval(self, name.as_deref(), ctx),
             Expression::ToString { target } => tostring::eval(self, target, ctx),
             Expression::ToNumber { target } => tonumber::eval(self, target, ctx),
+            Expression::Try { expr, catch } => try_catch::eval(self, expr, catch.as_deref(), ctx),
             _ => Err(anyhow!("Unsupported expression: {:?}", expr)),
         }
     }
 
-    /// Evaluate an expression and return multiple results (for iterators)
+    /// Evaluate an expression and return every output
+    ///
+    /// The outputs come from [`Evaluator::eval_lazy`]; this is where they
+    /// are collected.
     pub fn eval_multi(&self, expr: &Expression, ctx: &Context) -> Result<Vec<Value>> {
+        self.eval_lazy(expr, ctx)?.collect()
+    }
+
+    /// Evaluate an expression lazily, computing each output as it is pulled
+    ///
+    /// This is the core of generator evaluation. `.[]`, `|`, `,`, postfix
+    /// access, `select`, `as` bindings and `?` stream one value at a time,
+    /// so `.[] | select(.ok) | .name` over a large array never holds more
+    /// than the input, the current element and the results; `first`,
+    /// `limit` and `nth` stop pulling once they have what they need, even
+    /// over `range(1e9)`. Other generators (and calls of user-defined
+    /// functions) produce all their outputs at once.
+    pub fn eval_lazy<'a>(&'a self, expr: &'a Expression, ctx: &Context) -> Result<ValueStream<'a>> {
+        let _depth = self.enter()?;
         match expr {
+            // Postfix access applies to every output of its target, so
+            // `.[].name` and `.[][]` stream like `.[] | .name`
             Expression::Iterator { target } => {
-                let target_val = self.eval(target, ctx)?;
-                let mut results = vec![];
-
-                match &target_val {
-                    Value::Sequence(arr) => {
-                        for item in arr {
-                            results.push(item.clone());
-                        }
+                let targets = self.eval_lazy(target, ctx)?;
+                let scope = ctx.scope();
+                Ok(Box::new(targets.flat_map(move |val| -> ValueStream<'a> {
+                    match val {
+                        // Owned containers give up their items without a copy
+                        Ok(Value::Sequence(items)) => Box::new(items.into_iter().map(Ok)),
+                        Ok(Value::Mapping(map)) => Box::new(map.into_iter().map(|(_, v)| Ok(v))),
+                        Ok(other) => Box::new(std::iter::once(iterator::eval(
+                            self,
+                            &Expression::Identity,
+                            &scope.child(other),
+                        ))),
+                        Err(err) => error_stream(err),
                     }
-                    Value::Mapping(map) => {
-                        for (_, v) in map {
-                            results.push(v.clone());
-                        }
-                    }
-                    _ => {}
-                }
-
-                Ok(results)
+                })))
+            }
+            Expression::FieldAccess { target, field } => self.each(target, ctx, move |item_ctx| {
+                field_access::eval(self, &Expression::Identity, field, item_ctx)
+            }),
+            Expression::IndexAccess { target, index } => self.each(target, ctx, move |item_ctx| {
+                index_access::eval(self, &Expression::Identity, *index, item_ctx)
+            }),
+            Expression::Slice { target, start, end } => self.each(target, ctx, move |item_ctx| {
+                slice::eval(self, &Expression::Identity, *start, *end, item_ctx)
+            }),
+            Expression::DynamicIndex { target, index } => {
+                // The index is computed from the input, not from the target
+                let keys = self.eval_multi(index, ctx)?;
+                let ctx = ctx.clone();
+                Ok(Box::new(keys.into_iter().flat_map(move |key| {
+                    let key = Expression::Literal(key);
+                    self.each(target, &ctx, move |item_ctx| {
+                        index_access::eval_dynamic(self, &Expression::Identity, &key, item_ctx)
+                    })
+                    .unwrap_or_else(error_stream)
+                })))
+            }
+            Expression::Group { expr } => self.eval_lazy(expr, ctx),
+            Expression::Empty => Ok(Box::new(std::iter::empty())),
+            Expression::Range { start, end, by } => Ok(Box::new(
+                range::iter(self, start, end, by.as_deref(), ctx)?.map(Ok),
+            )),
+            Expression::Comma { left, right } => {
+                let left = self.eval_lazy(left, ctx)?;
+                let ctx = ctx.clone();
+                // The right side isn't evaluated until the left is used up
+                let right = std::iter::once(()).flat_map(move |()| self.stream(right, &ctx));
+                Ok(Box::new(left.chain(right)))
             }
             Expression::Pipe { left, right } => {
-                let left_results = self.eval_multi(left, ctx)?;
-                let mut results = vec![];
-                for val in left_results {
-                    let child_ctx = ctx.child(val);
-                    match self.eval_multi(right, &child_ctx) {
-                        Ok(mut vals) => results.append(&mut vals),
-                        Err(_) => {
-                            if let Ok(single) = self.eval(right, &child_ctx) {
-                                results.push(single);
-                            }
+                let left = self.eval_lazy(left, ctx)?;
+                let scope = ctx.scope();
+                Ok(Box::new(left.flat_map(move |val| match val {
+                    Ok(val) => self.stream(right, &scope.child(val)),
+                    Err(err) => error_stream(err),
+                })))
+            }
+            Expression::IfThenElse {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                if helpers::is_truthy(&self.eval(condition, ctx)?) {
+                    self.eval_lazy(then_branch, ctx)
+                } else {
+                    self.eval_lazy(else_branch, ctx)
+                }
+            }
+            Expression::Select { condition } => {
+                // Filtered-out values produce no output rather than null;
+                // a null would otherwise flow on, and since field access on
+                // null yields null, `select(.a) | .b` would emit it
+                let keep = helpers::is_truthy(&self.eval(condition, ctx)?);
+                Ok(Box::new(keep.then(|| Ok(ctx.value.clone())).into_iter()))
+            }
+            Expression::FunctionDef {
+                name,
+                params,
+                body,
+                rest,
+            } => self.eval_lazy(rest, &function::define(name, params, body, ctx)),
+            Expression::Bind {
+                source,
+                pattern,
+                body,
+            } => bind::eval_lazy(self, source, pattern, body, ctx),
+            Expression::First { expr } => first::eval_lazy(self, expr, ctx),
+            Expression::Limit { n, expr } => limit::eval_lazy(self, n, expr, ctx),
+            Expression::Nth { n, expr } => nth::eval_lazy(self, n, expr, ctx),
+            Expression::Try { expr, catch } => {
+                // `?` on a postfix access only covers the final step, applied
+                // to each output of the target: `.[].tags[]?` skips the items
+                // without tags instead of discarding every result
+                if let (None, Some((target, step))) = (catch, Self::postfix_parts(expr)) {
+                    let targets = self.eval_lazy(target, ctx)?;
+                    let scope = ctx.scope();
+                    return Ok(Box::new(targets.flat_map(move |val| {
+                        match val {
+                            Ok(val) => self
+                                .eval_multi(&step, &scope.child(val))
+                                .unwrap_or_default()
+                                .into_iter()
+                                .map(Ok)
+                                .collect(),
+                            Err(err) => vec![Err(err)],
                         }
-                    }
+                    })));
                 }
-                Ok(results)
+
+                // An error ends the outputs, replaced by those of the
+                // handler if there is one; earlier outputs are kept
+                let outputs = self.stream(expr, ctx);
+                let scope = ctx.scope();
+                let handled = outputs.scan(false, move |failed, output| {
+                    if *failed {
+                        return None;
+                    }
+                    let err = match output {
+                        Ok(value) => return Some(vec![Ok(value)]),
+                        Err(err) => err,
+                    };
+                    *failed = true;
+                    let Some(handler) = catch else {
+                        return Some(vec![]);
+                    };
+                    let err_ctx = scope.child(Value::String(err.to_string()));
+                    Some(match self.eval_multi(handler, &err_ctx) {
+                        Ok(values) => values.into_iter().map(Ok).collect(),
+                        Err(err) => vec![Err(err)],
+                    })
+                });
+                Ok(Box::new(handled.flatten()))
+            }
+            Expression::Object { fields } => eager(object::eval_multi(self, fields, ctx)),
+            Expression::FunctionCall { name, args } => {
+                eager(function::eval_multi_call(self, name, args, ctx))
+            }
+            Expression::Walk { f } => eager(walk::eval_multi(self, f, ctx)),
+            Expression::Recurse => eager(recurse::eval_multi(self, ctx)),
+            Expression::RecurseF { f, cond } => {
+                eager(recurse::eval_multi_with(self, f, cond.as_deref(), ctx))
             }
-            _ => self.eval(expr, ctx).map(|v| vec![v]),
+            Expression::ToStream => eager(Ok(stream::eval_multi_tostream(ctx))),
+            Expression::Path { expr } => eager(path::eval_multi(self, expr, ctx)),
+            Expression::Splits {
+                target,
+                pattern,
+                flags,
+            } => eager(regex::eval_multi_splits(
+                self,
+                target,
+                pattern,
+                flags.as_deref(),
+                ctx,
+            )),
+            Expression::Match {
+                target,
+                pattern,
+                flags,
+            } => eager(regex::eval_multi_match(
+                self,
+                target,
+                pattern,
+                flags.as_deref(),
+                ctx,
+            )),
+            Expression::Sub {
+                target,
+                pattern,
+                replacement,
+                flags,
+            } => eager(regex::eval_multi_sub(
+                self,
+                target,
+                pattern,
+                replacement,
+                flags.as_deref(),
+                ctx,
+            )),
+            Expression::Gsub {
+                target,
+                pattern,
+                replacement,
+                flags,
+            } => eager(regex::eval_multi_gsub(
+                self,
+                target,
+                pattern,
+                replacement,
+                flags.as_deref(),
+                ctx,
+            )),
+            Expression::FromStream { expr } => {
+                eager(stream::eval_multi_fromstream(self, expr, ctx))
+            }
+            Expression::TypeFilter { name } => eager(Ok(type_filters::eval_multi(name, ctx))),
+            Expression::Last { expr } => eager(last::eval_multi(self, expr, ctx)),
+            Expression::StartsWith { target, prefix } => {
+                eager(contains::eval_multi_affix(self, target, prefix, false, ctx))
+            }
+            Expression::EndsWith { target, suffix } => {
+                eager(contains::eval_multi_affix(self, target, suffix, true, ctx))
+            }
+            _ => eager(self.eval(expr, ctx).map(|v| vec![v])),
+        }
+    }
+
+    /// Evaluate an expression lazily as part of another stream
+    ///
+    /// An error starting it becomes its only output, and pulling from it
+    /// counts as one level of evaluation depth, so deeply nested or
+    /// recursive generators fail cleanly instead of overflowing the stack.
+    pub fn stream<'a>(&'a self, expr: &'a Expression, ctx: &Context) -> ValueStream<'a> {
+        match self.eval_lazy(expr, ctx) {
+            Ok(outputs) => Box::new(Nested {
+                evaluator: self,
+                outputs,
+            }),
+            Err(err) => error_stream(err),
         }
     }
+
+    /// Split a postfix access into its target and the final step applied to
+    /// the identity, e.g. `.a.b[]` into `.a.b` and `.[]`
+    fn postfix_parts(expr: &Expression) -> Option<(&Expression, Expression)> {
+        let identity = || Box::new(Expression::Identity);
+        match expr {
+            Expression::FieldAccess { target, field } => Some((
+                target,
+                Expression::FieldAccess {
+                    target: identity(),
+                    field: field.clone(),
+                },
+            )),
+            Expression::IndexAccess { target, index } => Some((
+                target,
+                Expression::IndexAccess {
+                    target: identity(),
+                    index: *index,
+                },
+            )),
+            Expression::Iterator { target } => {
+                Some((target, Expression::Iterator { target: identity() }))
+            }
+            _ => None,
+        }
+    }
+
+    /// Apply a single-valued operation to every output of `target`
+    fn each<'a>(
+        &'a self,
+        target: &'a Expression,
+        ctx: &Context,
+        op: impl Fn(&Context) -> Result<Value> + 'a,
+    ) -> Result<ValueStream<'a>> {
+        let targets = self.eval_lazy(target, ctx)?;
+        let scope = ctx.scope();
+        Ok(Box::new(targets.map(move |value| op(&scope.child(value?)))))
+    }
+}
+
+/// A stream of outputs computed up front
+fn eager<'a>(values: Result<Vec<Value>>) -> Result<ValueStream<'a>> {
+    Ok(Box::new(values?.into_iter().map(Ok)))
+}
+
+/// A stream whose only output is an error
+fn error_stream<'a>(err: anyhow::Error) -> ValueStream<'a> {
+    Box::new(std::iter::once(Err(err)))
+}
+
+/// Outputs pulled one evaluation level deeper than their consumer
+struct Nested<'a> {
+    evaluator: &'a Evaluator,
+    outputs: ValueStream<'a>,
+}
+
+impl Iterator for Nested<'_> {
+    type Item = Result<Value>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let _depth = match self.evaluator.enter() {
+            Ok(depth) => depth,
+            Err(err) => return Some(Err(err)),
+        };
+        self.outputs.next()
+    }
 }
 
 impl Default for Evaluator {
@@ -192,6 +684,21 @@ impl Default for Evaluator {
 /// Helper functions for operators
 pub mod helpers {
     use serde_yaml::Value;
+    use std::cmp::Ordering;
+
+    /// Error for an array function such as `sort` applied to a non-array
+    ///
+    /// The usual cause is applying it to each output of a generator
+    /// (`.[] | sort`), so the message shows how to collect them instead.
+    pub fn not_an_array(action: &str, func: &str, value: &Value) -> anyhow::Error {
+        anyhow::anyhow!(
+            "Cannot {} {}: {} works on a single array; to apply it to the outputs of a generator, collect them first, as in [.[]] | {}",
+            action,
+            value_type(value),
+            func,
+            func
+        )
+    }
 
     /// Check if a value is "truthy"
     pub fn is_truthy(value: &Value) -> bool {
@@ -214,22 +721,71 @@ pub mod helpers {
         }
     }
 
-    /// Compare two values
-    pub fn compare_values(a: &Value, b: &Value) -> Option<std::cmp::Ordering> {
+    /// Compare two values using jq's total ordering
+    ///
+    /// Values of different types are ordered by [`type_order`]. Arrays compare
+    /// element by element; objects compare their sorted key sets first and
+    /// then their values in sorted key order.
+    pub fn compare_values(a: &Value, b: &Value) -> Ordering {
         match (a, b) {
-            (Value::Null, Value::Null) => Some(std::cmp::Ordering::Equal),
-            (Value::Bool(a), Value::Bool(b)) => a.partial_cmp(b),
+            (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
             (Value::Number(a), Value::Number(b)) => {
                 if let (Some(ai), Some(bi)) = (a.as_i64(), b.as_i64()) {
-                    ai.partial_cmp(&bi)
-                } else if let (Some(af), Some(bf)) = (a.as_f64(), b.as_f64()) {
-                    af.partial_cmp(&bf)
+                    ai.cmp(&bi)
+                } else if let (Some(au), Some(bu)) = (a.as_u64(), b.as_u64()) {
+                    au.cmp(&bu)
                 } else {
-                    None
+                    let af = a.as_f64().unwrap_or(f64::NAN);
+                    let bf = b.as_f64().unwrap_or(f64::NAN);
+                    af.partial_cmp(&bf).unwrap_or(Ordering::Equal)
                 }
             }
-            (Value::String(a), Value::String(b)) => a.partial_cmp(b),
-            _ => None,
+            (Value::String(a), Value::String(b)) => a.cmp(b),
+            (Value::Sequence(a), Value::Sequence(b)) => a
+                .iter()
+                .zip(b)
+                .map(|(x, y)| compare_values(x, y))
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or_else(|| a.len().cmp(&b.len())),
+            (Value::Mapping(a), Value::Mapping(b)) => {
+                let mut a_keys: Vec<&Value> = a.keys().collect();
+                let mut b_keys: Vec<&Value> = b.keys().collect();
+                a_keys.sort_by(|x, y| compare_values(x, y));
+                b_keys.sort_by(|x, y| compare_values(x, y));
+
+                let key_ordering = a_keys
+                    .iter()
+                    .zip(&b_keys)
+                    .map(|(x, y)| compare_values(x, y))
+                    .find(|ordering| ordering.is_ne())
+                    .unwrap_or_else(|| a_keys.len().cmp(&b_keys.len()));
+                if key_ordering.is_ne() {
+                    return key_ordering;
+                }
+
+                a_keys
+                    .iter()
+                    .map(|k| compare_values(&a[*k], &b[*k]))
+                    .find(|ordering| ordering.is_ne())
+                    .unwrap_or(Ordering::Equal)
+            }
+            (Value::Tagged(a), Value::Tagged(b)) => compare_values(&a.value, &b.value),
+            _ => type_order(a).cmp(&type_order(b)),
+        }
+    }
+
+    /// Rank of a value's type in jq's cross-type ordering:
+    /// null < false < true < numbers < strings < arrays < objects
+    pub fn type_order(value: &Value) -> u8 {
+        match value {
+            Value::Null => 0,
+            Value::Bool(false) => 1,
+            Value::Bool(true) => 2,
+            Value::Number(_) => 3,
+            Value::String(_) => 4,
+            Value::Sequence(_) => 5,
+            Value::Mapping(_) => 6,
+            _ => 7,
         }
     }
 
@@ -242,7 +798,7 @@ pub mod helpers {
             Value::String(_) => "string",
             Value::Sequence(_) => "array",
             Value::Mapping(_) => "object",
-            _ => "unknown",
+            Value::Tagged(tagged) => value_type(&tagged.value),
         }
     }
 
@@ -253,6 +809,7 @@ pub mod helpers {
             Value::Bool(b) => b.to_string(),
             Value::Number(n) => n.to_string(),
             Value::String(s) => s.clone(),
+            Value::Tagged(tagged) => value_to_string(&tagged.value),
             _ => serde_json::to_string(value).unwrap_or_default(),
         }
     }
@@ -410,6 +967,33 @@ items:
         assert_eq!(arr.len(), 3);
     }
 
+    #[test]
+    fn test_eval_global_variable() {
+        let parser = ExpressionParser::new();
+        let mut evaluator = Evaluator::new();
+        evaluator.set_variable("n".to_string(), Value::Number(5.into()));
+
+        let expr = parser.parse("$n + 1").unwrap();
+        let result = evaluator.evaluate(&expr, None).unwrap();
+
+        assert_eq!(result, 6);
+    }
+
+    #[test]
+    fn test_eval_loc() {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+
+        let expr = parser.parse("$__loc__").unwrap();
+        let result = evaluator.evaluate(&expr, None).unwrap();
+        let expected: Value = serde_yaml::from_str("{file: <stdin>, line: 1}").unwrap();
+        assert_eq!(result, expected);
+
+        let expr = parser.parse("1,\n$__loc__.line").unwrap();
+        let result = evaluator.evaluate_multi(&expr, None).unwrap();
+        assert_eq!(result, vec![Value::from(1), Value::from(2)]);
+    }
+
     #[test]
     fn test_eval_length() {
         let parser = ExpressionParser::new();
@@ -421,4 +1005,235 @@ items:
 
         assert_eq!(result, 5);
     }
+
+    #[test]
+    fn test_sort_mixed_types_in_jq_order() {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let input: Value =
+            serde_yaml::from_str(r#"[{}, [1], "a", 3, true, null, false, [0], {"a": 1}, 1.5]"#)
+                .unwrap();
+        let expr = parser.parse("sort").unwrap();
+        let result = evaluator.evaluate(&expr, Some(&input)).unwrap();
+        let expected: Value =
+            serde_yaml::from_str(r#"[null, false, true, 1.5, 3, "a", [0], [1], {}, {"a": 1}]"#)
+                .unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_array_functions_on_collected_generator() {
+        let input = "[{x: 3}, {x: 1}, {x: 2}, {x: 1}]";
+        assert_eq!(
+            eval_multi_str("[.[] | .x] | sort", input).unwrap(),
+            vec![serde_yaml::from_str::<Value>("[1, 1, 2, 3]").unwrap()]
+        );
+        assert_eq!(
+            eval_multi_str("[.[].x] | unique | reverse", input).unwrap(),
+            vec![serde_yaml::from_str::<Value>("[3, 2, 1]").unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_array_functions_on_generator_explain_collecting() {
+        for func in ["sort", "reverse", "flatten", "unique"] {
+            let expr = format!(".[] | {}", func);
+            let err = eval_multi_str(&expr, "[3, 1, 2]").unwrap_err().to_string();
+            assert!(err.contains("works on a single array"), "{}", err);
+            assert!(err.contains(&format!("[.[]] | {}", func)), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_eval_multi_select_drops_missing_fields() {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let input: Value =
+            serde_yaml::from_str("[{n: 1, active: true}, {n: 2, active: false}, {n: 3}]").unwrap();
+        let expr = parser.parse(".[] | select(.active) | .n").unwrap();
+        let ctx = Context::new(input);
+        let results = evaluator.eval_multi(&expr, &ctx).unwrap();
+        assert_eq!(results, vec![Value::from(1)]);
+    }
+
+    #[test]
+    fn test_eval_multi_empty_produces_nothing() {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(".[] | empty").unwrap();
+        let ctx = Context::new(serde_yaml::from_str("[1, 2, 3]").unwrap());
+        let results = evaluator.eval_multi(&expr, &ctx).unwrap();
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn test_empty_in_array_constructor() {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse("[1, 2, empty, 3]").unwrap();
+        let result = evaluator.evaluate(&expr, None).unwrap();
+        let expected: Value = serde_yaml::from_str("[1, 2, 3]").unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_evaluate_multi_streams_outputs() {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let input: Value = serde_yaml::from_str("[1, 2, 3]").unwrap();
+
+        let expr = parser.parse(".[]").unwrap();
+        let results = evaluator.evaluate_multi(&expr, Some(&input)).unwrap();
+        assert_eq!(
+            results,
+            vec![Value::from(1), Value::from(2), Value::from(3)]
+        );
+
+        let expr = parser.parse(".").unwrap();
+        let results = evaluator.evaluate_multi(&expr, Some(&input)).unwrap();
+        assert_eq!(results, vec![input]);
+    }
+
+    #[test]
+    fn test_eval_depth_limit() {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::with_max_depth(32);
+        let expr = parser.parse(&vec!["."; 1_000].join(" | ")).unwrap();
+        let err = evaluator.evaluate(&expr, None).unwrap_err().to_string();
+        assert!(err.contains("Maximum evaluation depth"), "{}", err);
+        assert!(evaluator.evaluate_multi(&expr, None).is_err());
+
+        // The evaluator is reusable after hitting the limit
+        let expr = parser.parse(". | .").unwrap();
+        assert!(evaluator.evaluate(&expr, None).is_ok());
+    }
+
+    fn eval_multi_str(expr: &str, input: &str) -> Result<Vec<Value>> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let input: Value = serde_yaml::from_str(input).unwrap();
+        evaluator.evaluate_multi(&parser.parse(expr)?, Some(&input))
+    }
+
+    #[test]
+    fn test_recurse_pipe_type_filter_counts() {
+        let results = eval_multi_str(".. | numbers", "{a: 1, b: [2, {c: 3}], d: x}").unwrap();
+        assert_eq!(
+            results,
+            vec![Value::from(1), Value::from(2), Value::from(3)]
+        );
+    }
+
+    #[test]
+    fn test_pipe_select_then_field_counts() {
+        let results = eval_multi_str(
+            ".[] | select(.) | .name",
+            "[{name: a}, null, false, {name: b}]",
+        )
+        .unwrap();
+        assert_eq!(results, vec![Value::from("a"), Value::from("b")]);
+    }
+
+    #[test]
+    fn test_pipe_propagates_errors() {
+        assert!(eval_multi_str(".[] | .name", "[{name: a}, 1]").is_err());
+    }
+
+    #[test]
+    fn test_postfix_access_over_generator() {
+        let input = "[{name: a, tags: [1, 2]}, {name: b}]";
+        assert_eq!(
+            eval_multi_str(".[].name", input).unwrap(),
+            vec![Value::from("a"), Value::from("b")]
+        );
+        assert_eq!(
+            eval_multi_str("(.[]) | .name", input).unwrap(),
+            vec![Value::from("a"), Value::from("b")]
+        );
+        assert_eq!(
+            eval_multi_str(".[0].tags[]", input).unwrap(),
+            vec![Value::from(1), Value::from(2)]
+        );
+    }
+
+    #[test]
+    fn test_optional_applies_per_item() {
+        let input = "[{tags: [1, 2]}, {tags: null}, {tags: 3}]";
+        assert_eq!(
+            eval_multi_str(".[].tags[]?", input).unwrap(),
+            vec![Value::from(1), Value::from(2)]
+        );
+        assert_eq!(
+            eval_multi_str(".. | .x?", "[{x: 1}, 2]").unwrap(),
+            vec![Value::from(1)]
+        );
+    }
+
+    fn large_array(len: i64) -> Value {
+        Value::Sequence((0..len).map(Value::from).collect())
+    }
+
+    #[test]
+    fn test_large_array_streams_through_pipeline() {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let input = large_array(1_000_000);
+        let start = std::time::Instant::now();
+
+        // Each stage handles one element at a time, so this is linear in
+        // the input rather than copying it per element
+        let expr = parser
+            .parse("[.[] | select(. % 100000 == 0) | . / 100000] | length")
+            .unwrap();
+        let result = evaluator.evaluate(&expr, Some(&input)).unwrap();
+        assert_eq!(result, 10);
+
+        let expr = parser.parse("first(.[] | select(. > 10) | . * 2)").unwrap();
+        let result = evaluator.evaluate_multi(&expr, Some(&input)).unwrap();
+        assert_eq!(result, vec![Value::from(22)]);
+
+        assert!(start.elapsed() < std::time::Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_pipeline_shares_variables_and_functions() {
+        let parser = ExpressionParser::new();
+        let mut evaluator = Evaluator::new();
+        // A large variable and a function are in scope for every element;
+        // copying them per pipe step would make this take minutes
+        evaluator.set_variable("big".to_string(), large_array(100_000));
+        let input = large_array(200_000);
+        let start = std::time::Instant::now();
+
+        let expr = parser
+            .parse(
+                "def inc: . + 1; . as $all \
+                 | [.[] | inc | . * 2 | select(. % 4 == 0) | . as $x | $x / 4] | length",
+            )
+            .unwrap();
+        let result = evaluator.evaluate(&expr, Some(&input)).unwrap();
+        assert_eq!(result, 100_000);
+
+        assert!(start.elapsed() < std::time::Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_eval_lazy_pulls_only_what_is_needed() {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        // The error is never reached when only the first output is pulled
+        let expr = parser.parse(".[] | tonumber").unwrap();
+        let ctx = Context::new(serde_yaml::from_str("['1', x]").unwrap());
+        let mut outputs = evaluator.eval_lazy(&expr, &ctx).unwrap();
+        assert_eq!(outputs.next().unwrap().unwrap(), Value::from(1));
+        assert!(outputs.next().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_try_keeps_outputs_before_an_error() {
+        assert_eq!(
+            eval_multi_str("[(.[] | tonumber)?]", "['1', x, '2']").unwrap(),
+            vec![serde_yaml::from_str::<Value>("[1]").unwrap()]
+        );
+    }
 }
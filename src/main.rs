@@ -3,19 +3,19 @@
 //! rq uses jq-like syntax but works with YAML, JSON, and TOML files.
 //! It supports reading, querying, updating, and converting between formats.
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, anyhow, bail};
 use clap::{CommandFactory, Parser, ValueEnum};
 use colored::Colorize;
-use std::io::{self, Read};
-use std::path::PathBuf;
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
 
 mod evaluator;
 mod operators;
 mod output;
 mod parser;
 
-use evaluator::Evaluator;
-use parser::expression::ExpressionParser;
+use evaluator::{Evaluator, ValueStream};
+use parser::expression::{Expression, ExpressionParser};
 use parser::input::InputParser;
 
 /// rq - A lightweight command-line YAML, JSON, and TOML processor
@@ -36,10 +36,19 @@ struct Cli {
     #[arg(short = 'p', long = "input-format", value_enum)]
     input_format: Option<InputFormat>,
 
+    /// Read every YAML input scalar as a string instead of typing plain
+    /// ones by the YAML 1.2 core schema (`1.10`, `true` and `null` stay text)
+    #[arg(long = "yaml-strict-strings")]
+    yaml_strict_strings: bool,
+
     /// Output format
     #[arg(short = 'o', long = "output-format", value_enum)]
     output_format: Option<OutputFormat>,
 
+    /// Parse each input into [path, leaf] stream events
+    #[arg(long = "stream")]
+    stream: bool,
+
     /// Update the file in place
     #[arg(short = 'i', long = "inplace")]
     inplace: bool,
@@ -48,11 +57,28 @@ struct Cli {
     #[arg(short = 'n', long = "null-input")]
     null_input: bool,
 
+    /// Read each line of input as a string instead of parsing it; with
+    /// --slurp the whole input is one string
+    #[arg(short = 'R', long = "raw-input", conflicts_with = "inplace")]
+    raw_input: bool,
+
+    /// Read every input into one array and run the expression once
+    #[arg(short = 's', long = "slurp", conflicts_with = "inplace")]
+    slurp: bool,
+
     /// Pretty print output
     #[arg(short = 'P', long = "pretty-print")]
     pretty_print: bool,
 
-    /// Force print with colors
+    /// Print each result on a single line (JSON compact, YAML flow style)
+    #[arg(short = 'c', long = "compact-output")]
+    compact_output: bool,
+
+    /// Render YAML output in flow style ({a: 1, b: [1, 2]})
+    #[arg(long = "flow", visible_alias = "yaml-flow")]
+    flow: bool,
+
+    /// Force print with colors (default: only when stdout is a terminal and NO_COLOR is unset)
     #[arg(short = 'C', long = "colors")]
     colors: bool,
 
@@ -60,30 +86,90 @@ struct Cli {
     #[arg(short = 'M', long = "no-colors")]
     no_colors: bool,
 
-    /// Set indent level for output
+    /// Set indent level for output (0 for single-line output; for TOML,
+    /// the indent of nested tables)
     #[arg(short = 'I', long = "indent", default_value = "2")]
     indent: usize,
 
+    /// Indent JSON output with tabs instead of spaces (overrides --indent)
+    #[arg(long = "tab")]
+    tab: bool,
+
     /// Unwrap scalar values (no quotes for strings)
     #[arg(short = 'r', long = "unwrap-scalar", default_value = "true")]
     unwrap_scalar: bool,
 
-    /// Expression file to load
-    #[arg(long = "from-file")]
-    from_file: Option<PathBuf>,
+    /// Double-quote every string in YAML output, so none can be read back
+    /// as another type (`"no"`, `"0123"`)
+    #[arg(long = "yaml-quote-strings")]
+    yaml_quote_strings: bool,
+
+    /// Start CSV/TSV output from objects with a header row of every key
+    #[arg(
+        long = "csv-headers",
+        value_name = "BOOL",
+        num_args = 0..=1,
+        default_value_t = true,
+        default_missing_value = "true",
+        require_equals = true,
+        action = clap::ArgAction::Set
+    )]
+    csv_headers: bool,
+
+    /// Set $NAME to the string VALUE (repeatable)
+    #[arg(long = "arg", num_args = 2, value_names = ["NAME", "VALUE"])]
+    arg: Vec<String>,
+
+    /// Set $NAME to the parsed JSON VALUE (repeatable)
+    #[arg(long = "argjson", num_args = 2, value_names = ["NAME", "JSON"])]
+    argjson: Vec<String>,
+
+    /// Treat the remaining arguments as strings in $ARGS.positional
+    #[arg(long = "args", num_args = 0.., value_name = "ARGS")]
+    args: Vec<String>,
+
+    /// Treat the remaining arguments as JSON values in $ARGS.positional
+    #[arg(long = "jsonargs", num_args = 0.., value_name = "JSON")]
+    jsonargs: Vec<String>,
+
+    /// Expression file to load; repeat to load definitions from several
+    /// files, read in order, with the main expression in the last
+    #[arg(short = 'f', long = "from-file")]
+    from_file: Vec<PathBuf>,
 
     /// Don't print document separators
     #[arg(short = 'N', long = "no-doc")]
     no_doc: bool,
 
+    /// Sort object keys in output
+    #[arg(short = 'S', long = "sort-keys")]
+    sort_keys: bool,
+
     /// Use NUL char to separate values
     #[arg(short = '0', long = "nul-output")]
     nul_output: bool,
 
-    /// Set exit status if no matches or null/false returned
+    /// Print strings unquoted in any output format and end every output
+    /// with a NUL char, for `xargs -0`
+    #[arg(long = "raw-output0")]
+    raw_output0: bool,
+
+    /// Escape non-ASCII characters in JSON output as \uXXXX
+    #[arg(short = 'a', long = "ascii-output")]
+    ascii_output: bool,
+
+    /// Prefix each JSON output with an RS character (RFC 7464)
+    #[arg(long = "seq")]
+    seq: bool,
+
+    /// Exit with 1 if the last output is null/false, 4 if there is no output
     #[arg(short = 'e', long = "exit-status")]
     exit_status: bool,
 
+    /// On a per-document error, report it and continue; exit with 5 at the end
+    #[arg(short = 'k', long = "continue")]
+    keep_going: bool,
+
     /// Verbose mode
     #[arg(short = 'v', long = "verbose")]
     verbose: bool,
@@ -103,25 +189,38 @@ enum OutputFormat {
     Yaml,
     Json,
     Toml,
+    Csv,
+    Tsv,
 }
 
 fn main() -> Result<()> {
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
 
-    // Setup colors
-    if cli.no_colors {
-        colored::control::set_override(false);
-    } else if cli.colors {
-        colored::control::set_override(true);
+    // With an expression file, the first positional argument is an input
+    if !cli.from_file.is_empty()
+        && let Some(file) = cli.expression.take()
+    {
+        cli.files.insert(0, PathBuf::from(file));
+    }
+
+    // --raw-output0 implies the NUL separators of -0
+    if cli.raw_output0 {
+        cli.nul_output = true;
     }
 
+    // Decide on colors once; `colored` is pinned to the same decision so its
+    // own terminal heuristics can't disagree with OutputOptions.colors
+    let colors = use_colors(&cli);
+    colored::control::set_override(colors);
+
     // Get expression
-    let expression = if let Some(file) = cli.from_file {
-        std::fs::read_to_string(file).context("Failed to read expression file")?
+    let expression = if !cli.from_file.is_empty() {
+        read_expression_files(&cli.from_file)?
     } else if let Some(expr) = cli.expression {
         expr
     } else if cli.null_input {
-        String::new()
+        // `rq -n` on its own outputs null, like `jq -n`
+        ".".to_string()
     } else {
         // No expression provided - show help
         Cli::command().print_help()?;
@@ -143,97 +242,394 @@ fn main() -> Result<()> {
         eprintln!("{} {:?}", "Parsed:".dimmed(), expr);
     }
 
-    // Read input
-    let input_data = if cli.null_input {
-        None
-    } else if cli.files.is_empty() {
-        // Read from stdin
-        let mut buffer = String::new();
-        io::stdin()
-            .read_to_string(&mut buffer)
-            .context("Failed to read from stdin")?;
-        if buffer.trim().is_empty() {
-            None
-        } else {
-            Some(buffer)
+    // Define variables from --arg/--argjson; $ARGS collects them by name
+    // along with the --args/--jsonargs positional values
+    let mut evaluator = Evaluator::new();
+    let mut named = serde_yaml::Mapping::new();
+    for pair in cli.arg.chunks(2) {
+        if let [name, value] = pair {
+            let value = serde_yaml::Value::String(value.clone());
+            evaluator.set_variable(name.clone(), value.clone());
+            named.insert(serde_yaml::Value::String(name.clone()), value);
         }
-    } else {
-        // Read from file(s)
-        let mut buffer = String::new();
-        for file in &cli.files {
-            let content = std::fs::read_to_string(file)
-                .with_context(|| format!("Failed to read file: {}", file.display()))?;
-            buffer.push_str(&content);
-            buffer.push('\n');
+    }
+    for pair in cli.argjson.chunks(2) {
+        if let [name, json] = pair {
+            let value = InputParser::parse(json, parser::input::InputFormat::Json)
+                .with_context(|| format!("Invalid JSON text passed to --argjson {}", name))?;
+            evaluator.set_variable(name.clone(), value.clone());
+            named.insert(serde_yaml::Value::String(name.clone()), value);
         }
-        Some(buffer)
-    };
+    }
+    let mut positional: Vec<serde_yaml::Value> = cli
+        .args
+        .iter()
+        .map(|arg| serde_yaml::Value::String(arg.clone()))
+        .collect();
+    for json in &cli.jsonargs {
+        let value = InputParser::parse(json, parser::input::InputFormat::Json)
+            .with_context(|| format!("Invalid JSON text passed to --jsonargs: {}", json))?;
+        positional.push(value);
+    }
+    let mut args = serde_yaml::Mapping::new();
+    args.insert("positional".into(), serde_yaml::Value::Sequence(positional));
+    args.insert("named".into(), serde_yaml::Value::Mapping(named));
+    evaluator.set_variable("ARGS".to_string(), serde_yaml::Value::Mapping(args));
 
-    // Parse input
     let input_format = cli.input_format.unwrap_or(InputFormat::Auto);
-    let parsed_input = if let Some(data) = input_data {
-        let format = detect_format(&data, input_format, cli.files.first())?;
-        Some(InputParser::parse(&data, format)?)
-    } else {
-        None
+    let options = output::OutputOptions {
+        indent: if cli.tab {
+            output::Indent::Tab
+        } else {
+            output::Indent::Spaces(cli.indent)
+        },
+        pretty_print: cli.pretty_print,
+        unwrap_scalar: cli.unwrap_scalar,
+        no_doc: cli.no_doc,
+        colors,
+        sort_keys: cli.sort_keys,
+        compact: cli.compact_output,
+        flow: cli.flow,
+        seq: cli.seq,
+        ascii_output: cli.ascii_output,
+        csv_headers: cli.csv_headers,
+        quote_strings: cli.yaml_quote_strings,
     };
 
-    // Evaluate expression
-    let evaluator = Evaluator::new();
-    let result = evaluator.evaluate(&expr, parsed_input.as_ref())?;
-
-    // Determine output format
-    let output_format = cli.output_format.unwrap_or({
-        if cli.pretty_print {
-            OutputFormat::Yaml
+    // With -k a failing document is reported on stderr and skipped,
+    // otherwise the error aborts the run
+    let mut failed = false;
+    let mut recover = |err: anyhow::Error| -> Result<()> {
+        if cli.keep_going {
+            eprintln!("Error: {:#}", err);
+            failed = true;
+            Ok(())
         } else {
-            match input_format {
-                InputFormat::Json => OutputFormat::Json,
-                InputFormat::Toml => OutputFormat::Toml,
-                _ => OutputFormat::Yaml,
-            }
+            Err(err)
         }
-    });
-
-    // Output result
-    let output = output::format_output(
-        &result,
-        output_format,
-        output::OutputOptions {
-            indent: cli.indent,
-            pretty_print: cli.pretty_print,
-            unwrap_scalar: cli.unwrap_scalar,
-            no_doc: cli.no_doc,
-            colors: cli.colors && !cli.no_colors,
-        },
-    )?;
+    };
 
-    // Handle in-place editing
-    if cli.inplace && !cli.files.is_empty() {
-        let file = &cli.files[0];
-        std::fs::write(file, output)
-            .with_context(|| format!("Failed to write to file: {}", file.display()))?;
+    // The last output produced, which decides the `-e` exit status
+    let last = if cli.inplace && !cli.files.is_empty() {
+        // Each file is evaluated on its own contents and written back in its
+        // own format. Nothing is written until every file has evaluated.
+        let mut result = serde_yaml::Value::Null;
+        let mut edits = Vec::with_capacity(cli.files.len());
+        for file in &cli.files {
+            let edit = read_file(file, input_format, cli.yaml_strict_strings).and_then(
+                |(input, format)| {
+                    let output_format = cli
+                        .output_format
+                        .unwrap_or_else(|| output_format_for(format));
+                    // The file is replaced by the output, so there must be
+                    // exactly one
+                    let results = evaluate_input(&evaluator, &expr, Some(&input), output_format)
+                        .collect::<Result<Vec<_>>>()?;
+                    let [output] = <[_; 1]>::try_from(results).map_err(|results| {
+                        anyhow!(
+                            "In-place editing of {} needs exactly one output, got {}",
+                            file.display(),
+                            results.len()
+                        )
+                    })?;
+                    result = output;
+                    output::format_output(&result, output_format, options.clone())
+                },
+            );
+            match edit {
+                Ok(output) => edits.push((file, output)),
+                Err(err) => recover(err)?,
+            }
+        }
+        for (file, output) in edits {
+            write_atomically(file, &output)
+                .with_context(|| format!("Failed to write to file: {}", file.display()))?;
+        }
+        Some(result)
     } else {
-        print!("{}", output);
-        if !output.ends_with('\n') {
-            println!();
+        // Read input: each file is parsed on its own into a separate input
+        let inputs = if cli.null_input {
+            vec![None]
+        } else if cli.raw_input {
+            read_raw_inputs(&cli.files, cli.slurp)?
+        } else if cli.files.is_empty() {
+            // Read from stdin
+            let mut buffer = String::new();
+            io::stdin()
+                .read_to_string(&mut buffer)
+                .context("Failed to read from stdin")?;
+            if buffer.trim().is_empty() {
+                vec![None]
+            } else {
+                let input = detect_format(&buffer, input_format, None).and_then(|format| {
+                    InputParser::parse_with(&buffer, format, cli.yaml_strict_strings)
+                });
+                match input {
+                    Ok(input) => vec![Some(input)],
+                    Err(err) => {
+                        recover(err)?;
+                        vec![]
+                    }
+                }
+            }
+        } else {
+            let mut inputs = Vec::with_capacity(cli.files.len());
+            for file in &cli.files {
+                match read_file(file, input_format, cli.yaml_strict_strings) {
+                    Ok((input, _)) => inputs.push(Some(input)),
+                    Err(err) => recover(err)?,
+                }
+            }
+            inputs
+        };
+
+        // With --stream each input is replaced by its [path, leaf] events
+        let inputs: Vec<Option<serde_yaml::Value>> = if cli.stream {
+            inputs
+                .into_iter()
+                .flat_map(|input| match input {
+                    Some(value) => operators::stream::events(&value)
+                        .into_iter()
+                        .map(Some)
+                        .collect(),
+                    None => vec![None],
+                })
+                .collect()
+        } else {
+            inputs
+        };
+
+        // With --slurp every parsed input becomes an element of one array
+        let inputs = if cli.slurp && !cli.raw_input && !cli.null_input {
+            vec![Some(serde_yaml::Value::Sequence(
+                inputs.into_iter().flatten().collect(),
+            ))]
+        } else {
+            inputs
+        };
+
+        // Determine output format
+        let output_format = cli.output_format.unwrap_or({
+            if cli.pretty_print {
+                OutputFormat::Yaml
+            } else {
+                match input_format {
+                    InputFormat::Json => OutputFormat::Json,
+                    InputFormat::Toml => OutputFormat::Toml,
+                    _ => OutputFormat::Yaml,
+                }
+            }
+        });
+
+        // Evaluate expression against each input and output every result
+        let mut last = None;
+        for input in &inputs {
+            // Each output is printed as soon as it is produced; an error
+            // ends the document after the outputs before it
+            for result in evaluate_input(&evaluator, &expr, input.as_ref(), output_format) {
+                let output = result.and_then(|result| {
+                    let output = match &result {
+                        serde_yaml::Value::String(s) if cli.raw_output0 => {
+                            // The NUL would read as the end of the string
+                            if s.contains('\0') {
+                                bail!("Cannot output a string containing NUL with --raw-output0");
+                            }
+                            s.clone()
+                        }
+                        _ => {
+                            let output =
+                                output::format_output(&result, output_format, options.clone())?;
+                            // The newline is replaced by the separator
+                            match output.strip_suffix('\n') {
+                                Some(output) => output.to_string(),
+                                None => output,
+                            }
+                        }
+                    };
+                    Ok((result, output))
+                });
+                let (result, output) = match output {
+                    Ok(output) => output,
+                    Err(err) => {
+                        recover(err)?;
+                        break;
+                    }
+                };
+                // Each output ends with a newline, or a NUL byte with -0
+                print!("{}{}", output, if cli.nul_output { '\0' } else { '\n' });
+                last = Some(result);
+            }
         }
+        last
+    };
+
+    if failed {
+        std::process::exit(5);
     }
 
-    // Handle exit status
+    // Handle exit status: 1 if the last output was null or false, 4 if
+    // there was no output at all
     if cli.exit_status {
-        let is_empty = match &result {
-            serde_yaml::Value::Null => true,
-            serde_yaml::Value::Bool(b) => !b,
-            serde_yaml::Value::Sequence(arr) => arr.is_empty(),
-            serde_yaml::Value::Mapping(map) => map.is_empty(),
-            _ => false,
-        };
-        if is_empty {
-            std::process::exit(1);
+        match last {
+            None => std::process::exit(4),
+            Some(serde_yaml::Value::Null | serde_yaml::Value::Bool(false)) => std::process::exit(1),
+            Some(_) => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Read expression files in order, joined into one expression so the
+/// definitions of earlier files are visible in later ones
+fn read_expression_files(files: &[PathBuf]) -> Result<String> {
+    let mut sources = Vec::with_capacity(files.len());
+    for file in files {
+        sources.push(
+            std::fs::read_to_string(file)
+                .with_context(|| format!("Failed to read expression file {}", file.display()))?,
+        );
+    }
+    Ok(sources.join("\n"))
+}
+
+/// Evaluate the expression on one input, producing each output as it is
+/// pulled
+///
+/// Expressions see TOML datetimes as plain strings; for TOML output the
+/// datetimes still in their original place get their type back.
+fn evaluate_input<'a>(
+    evaluator: &'a Evaluator,
+    expr: &'a Expression,
+    input: Option<&'a serde_yaml::Value>,
+    output_format: OutputFormat,
+) -> ValueStream<'a> {
+    let Some(original) = input.filter(|input| output::has_datetimes(input)) else {
+        return evaluator.evaluate_lazy(expr, input);
+    };
+    let results = evaluator.evaluate_lazy(expr, Some(&output::untag_datetimes(original.clone())));
+    if !matches!(output_format, OutputFormat::Toml) {
+        return results;
+    }
+    Box::new(results.map(|result| result.map(|result| output::restore_datetimes(result, original))))
+}
+
+/// Whether a document reads as TOML: its first significant line after any
+/// `[table]` or `[[array]]` headers is a `key = value` pair whose key has no
+/// YAML-only characters such as `:` (quoted keys may contain anything)
+///
+/// A header alone is not enough, since `[a]` is also a YAML flow sequence.
+fn looks_like_toml(data: &str) -> bool {
+    let mut lines = data
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+    let Some(line) = lines.find(|line| !line.starts_with('[')) else {
+        return false;
+    };
+    let Some((key, _)) = line.split_once('=') else {
+        return false;
+    };
+    let key = key.trim();
+    !key.is_empty()
+        && (key.starts_with(['"', '\''])
+            || key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | ' ')))
+}
+
+/// Read stdin, or every file in turn, as text for --raw-input: one string
+/// per line (without its newline), or with --slurp the whole text as one
+/// string
+fn read_raw_inputs(files: &[PathBuf], slurp: bool) -> Result<Vec<Option<serde_yaml::Value>>> {
+    let mut text = String::new();
+    if files.is_empty() {
+        io::stdin()
+            .read_to_string(&mut text)
+            .context("Failed to read from stdin")?;
+    } else {
+        for file in files {
+            text.push_str(
+                &std::fs::read_to_string(file)
+                    .with_context(|| format!("Failed to read file: {}", file.display()))?,
+            );
         }
     }
 
+    if slurp {
+        return Ok(vec![Some(serde_yaml::Value::String(text))]);
+    }
+    let mut lines: Vec<&str> = text.split('\n').collect();
+    if lines.last() == Some(&"") {
+        lines.pop();
+    }
+    Ok(lines
+        .into_iter()
+        .map(|line| Some(serde_yaml::Value::String(line.to_string())))
+        .collect())
+}
+
+/// Read and parse an input file, returning its contents and detected format
+fn read_file(
+    file: &PathBuf,
+    input_format: InputFormat,
+    yaml_strict_strings: bool,
+) -> Result<(serde_yaml::Value, parser::input::InputFormat)> {
+    let data = std::fs::read_to_string(file)
+        .with_context(|| format!("Failed to read file: {}", file.display()))?;
+    let format = detect_format(&data, input_format, Some(file))?;
+    let input = InputParser::parse_with(&data, format, yaml_strict_strings)
+        .with_context(|| format!("Failed to parse file: {}", file.display()))?;
+    Ok((input, format))
+}
+
+/// Whether output should be colorized: on when stdout is a terminal and
+/// `NO_COLOR` is unset or empty, with `-C` forcing and `-M` disabling colors
+/// (`-M` wins). Files written by `--inplace` are never colorized.
+fn use_colors(cli: &Cli) -> bool {
+    if cli.no_colors || (cli.inplace && !cli.files.is_empty()) {
+        false
+    } else if cli.colors {
+        true
+    } else if std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+        false
+    } else {
+        io::stdout().is_terminal()
+    }
+}
+
+/// Output format matching a detected input format
+fn output_format_for(format: parser::input::InputFormat) -> OutputFormat {
+    match format {
+        parser::input::InputFormat::Yaml => OutputFormat::Yaml,
+        parser::input::InputFormat::Json => OutputFormat::Json,
+        parser::input::InputFormat::Toml => OutputFormat::Toml,
+    }
+}
+
+/// Replace `path` with `contents` without ever leaving it half-written
+///
+/// A symlink is resolved first, so its target is replaced and the link
+/// itself kept. The data goes to a uniquely named temp file next to the
+/// target, created readable only by its owner and given the original's
+/// permissions before anything is written, which is then renamed over it.
+fn write_atomically(path: &Path, contents: &str) -> Result<()> {
+    let path = std::fs::canonicalize(path)?;
+    let dir = path.parent().context("Path has no parent directory")?;
+    let name = path
+        .file_name()
+        .context("Path has no file name")?
+        .to_string_lossy();
+    let permissions = std::fs::metadata(&path)?.permissions();
+
+    // The temp file is removed when dropped unless it has been persisted
+    let mut tmp = tempfile::Builder::new()
+        .prefix(&format!(".{}.rq-", name))
+        .suffix(".tmp")
+        .tempfile_in(dir)?;
+    tmp.as_file().set_permissions(permissions)?;
+    tmp.write_all(contents.as_bytes())?;
+    tmp.persist(&path)?;
     Ok(())
 }
 
@@ -257,20 +653,17 @@ fn detect_format(
                 }
             }
 
-            // Try to detect from content
+            // Try to detect from content, cheapest checks first: JSON is
+            // parsed only if the first character can start a JSON value,
+            // TOML only if the first line is a table header or `key = value`
             let trimmed = data.trim_start();
-            if trimmed.starts_with('{') || trimmed.starts_with('[') {
-                // Try JSON first
-                if serde_json::from_str::<serde_json::Value>(data).is_ok() {
-                    return Ok(parser::input::InputFormat::Json);
-                }
+            let json_start = trimmed.starts_with(['{', '[', '"', '-', 't', 'f', 'n'])
+                || trimmed.starts_with(|c: char| c.is_ascii_digit());
+            if json_start && serde_json::from_str::<serde_json::Value>(data).is_ok() {
+                return Ok(parser::input::InputFormat::Json);
             }
 
-            // Check for TOML
-            if trimmed.contains('=')
-                && !trimmed.starts_with('-')
-                && toml::from_str::<toml::Value>(data).is_ok()
-            {
+            if looks_like_toml(trimmed) && toml::from_str::<toml::Value>(data).is_ok() {
                 return Ok(parser::input::InputFormat::Toml);
             }
 
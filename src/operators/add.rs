@@ -1,56 +1,150 @@
 //! Add function (sum all elements)
 
-use crate::evaluator::{Context, Evaluator};
+use crate::evaluator::{Context, Evaluator, helpers};
 use anyhow::{Result, anyhow};
-use serde_yaml::Value;
+use serde_yaml::{Mapping, Value};
 
-/// Evaluate add function - sum all elements in the array
+/// Evaluate add function - combine all elements in the array
+///
+/// The element type decides how values are combined: numbers are summed,
+/// strings concatenated, arrays concatenated and objects merged with later
+/// keys winning. Null elements are skipped and an empty array yields null.
 pub fn eval(_evaluator: &Evaluator, ctx: &Context) -> Result<Value> {
-    match &ctx.value {
-        Value::Sequence(arr) => {
-            let mut sum: f64 = 0.0;
-            let mut all_integers = true;
+    let arr = match &ctx.value {
+        Value::Sequence(arr) => arr,
+        _ => {
+            return Err(anyhow!(
+                "Cannot add elements of {}",
+                helpers::value_type(&ctx.value)
+            ));
+        }
+    };
+
+    let items: Vec<&Value> = arr.iter().filter(|v| !v.is_null()).collect();
+    let Some(first) = items.first() else {
+        return Ok(Value::Null);
+    };
 
-            for item in arr {
+    match first {
+        Value::Number(_) => add_numbers(&items),
+        Value::String(_) => {
+            let mut result = String::new();
+            for item in &items {
                 match item {
-                    Value::Number(n) => {
-                        if let Some(i) = n.as_i64() {
-                            sum += i as f64;
-                        } else if let Some(f) = n.as_f64() {
-                            sum += f;
-                            all_integers = false;
-                        }
-                    }
-                    _ => return Err(anyhow!("Cannot add non-numeric value")),
+                    Value::String(s) => result.push_str(s),
+                    other => return Err(mismatch(first, other)),
                 }
             }
-
-            if all_integers && sum.fract() == 0.0 {
-                Ok(Value::Number((sum as i64).into()))
-            } else {
-                Ok(Value::Number(serde_yaml::Number::from(sum)))
+            Ok(Value::String(result))
+        }
+        Value::Sequence(_) => {
+            let mut result = Vec::new();
+            for item in &items {
+                match item {
+                    Value::Sequence(a) => result.extend(a.iter().cloned()),
+                    other => return Err(mismatch(first, other)),
+                }
             }
+            Ok(Value::Sequence(result))
         }
-        Value::String(s) => {
-            // Concatenate all strings in the array
-            match &ctx.value {
-                Value::Sequence(arr) => {
-                    let mut result = String::new();
-                    for item in arr {
-                        if let Value::String(s) = item {
-                            result.push_str(s);
-                        } else {
-                            return Err(anyhow!("Cannot add non-string value to string"));
+        Value::Mapping(_) => {
+            let mut result = Mapping::new();
+            for item in &items {
+                match item {
+                    Value::Mapping(m) => {
+                        for (k, v) in m {
+                            result.insert(k.clone(), v.clone());
                         }
                     }
-                    Ok(Value::String(result))
+                    other => return Err(mismatch(first, other)),
                 }
-                _ => Ok(Value::String(s.clone())),
             }
+            Ok(Value::Mapping(result))
         }
-        _ => Err(anyhow!(
-            "Cannot add elements of {}",
-            crate::evaluator::helpers::value_type(&ctx.value)
-        )),
+        other => Err(anyhow!("Cannot add {} values", helpers::value_type(other))),
+    }
+}
+
+/// Sum numbers, staying integral until a float or an overflow is seen
+fn add_numbers(items: &[&Value]) -> Result<Value> {
+    let mut int_sum: Option<i64> = Some(0);
+    let mut float_sum: f64 = 0.0;
+
+    for item in items {
+        let Value::Number(n) = item else {
+            return Err(mismatch(items[0], item));
+        };
+        int_sum = match (int_sum, n.as_i64()) {
+            (Some(sum), Some(i)) => sum.checked_add(i),
+            _ => None,
+        };
+        float_sum += n.as_f64().unwrap_or(0.0);
+    }
+
+    Ok(match int_sum {
+        Some(sum) => Value::Number(sum.into()),
+        None => Value::Number(serde_yaml::Number::from(float_sum)),
+    })
+}
+
+fn mismatch(first: &Value, other: &Value) -> anyhow::Error {
+    anyhow!(
+        "Cannot add {} and {}",
+        helpers::value_type(first),
+        helpers::value_type(other)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval(expr_str: &str, input: &str) -> Result<Value> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate(&expr, Some(&input_val))
+    }
+
+    #[test]
+    fn test_add_numbers() {
+        assert_eq!(parse_and_eval("add", "[1, 2, 3]").unwrap(), 6);
+        assert_eq!(parse_and_eval("add", "[1, 2.5]").unwrap(), 3.5);
+    }
+
+    #[test]
+    fn test_add_strings() {
+        assert_eq!(parse_and_eval("add", "[a, b, c]").unwrap(), "abc");
+    }
+
+    #[test]
+    fn test_add_arrays() {
+        let result = parse_and_eval("add", "[[1], [2, 3]]").unwrap();
+        let expected: Value = serde_yaml::from_str("[1, 2, 3]").unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_add_objects_later_keys_win() {
+        let result = parse_and_eval("add", "[{a: 1, b: 1}, {b: 2}]").unwrap();
+        let expected: Value = serde_yaml::from_str("{a: 1, b: 2}").unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_add_empty_is_null() {
+        assert_eq!(parse_and_eval("add", "[]").unwrap(), Value::Null);
+    }
+
+    #[test]
+    fn test_add_skips_nulls() {
+        assert_eq!(parse_and_eval("add", "[null, 1, null, 2]").unwrap(), 3);
+    }
+
+    #[test]
+    fn test_add_mixed_types_error() {
+        assert!(parse_and_eval("add", "[1, a]").is_err());
     }
 }
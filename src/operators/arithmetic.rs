@@ -4,6 +4,41 @@ use crate::evaluator::{Context, Evaluator, helpers};
 use crate::parser::expression::Expression;
 use anyhow::{Result, anyhow};
 use serde_yaml::Value;
+use std::collections::HashSet;
+
+/// The value of an integer, signed or unsigned, widened to `i128` so that
+/// sums, differences and quotients of 64-bit integers can't overflow
+/// (products still can, so `*` checks them)
+fn integer(n: &serde_yaml::Number) -> Option<i128> {
+    n.as_i64()
+        .map(i128::from)
+        .or_else(|| n.as_u64().map(i128::from))
+}
+
+/// Narrow the exact result of `a op b` back to a 64-bit integer
+///
+/// A result outside both `i64` and `u64`, or one that overflowed `i128`
+/// (`None`), is an error rather than a float that silently drops its low
+/// digits.
+fn integer_result(
+    result: Option<i128>,
+    a: &serde_yaml::Number,
+    op: &str,
+    b: &serde_yaml::Number,
+) -> Result<Value> {
+    if let Some(Ok(i)) = result.map(i64::try_from) {
+        Ok(Value::Number(i.into()))
+    } else if let Some(Ok(u)) = result.map(u64::try_from) {
+        Ok(Value::Number(u.into()))
+    } else {
+        Err(anyhow!(
+            "{} {} {} is outside the 64-bit integer range",
+            a,
+            op,
+            b
+        ))
+    }
+}
 
 /// Add two values
 pub fn add(
@@ -17,8 +52,8 @@ pub fn add(
 
     match (&left_val, &right_val) {
         (Value::Number(a), Value::Number(b)) => {
-            if let (Some(ai), Some(bi)) = (a.as_i64(), b.as_i64()) {
-                Ok(Value::Number((ai + bi).into()))
+            if let (Some(ai), Some(bi)) = (integer(a), integer(b)) {
+                integer_result(ai.checked_add(bi), a, "+", b)
             } else if let (Some(af), Some(bf)) = (a.as_f64(), b.as_f64()) {
                 Ok(Value::Number(serde_yaml::Number::from(af + bf)))
             } else {
@@ -31,6 +66,16 @@ pub fn add(
             result.extend(b.clone());
             Ok(Value::Sequence(result))
         }
+        (Value::Mapping(a), Value::Mapping(b)) => {
+            // Shallow merge, right-hand keys win
+            let mut result = a.clone();
+            for (k, v) in b {
+                result.insert(k.clone(), v.clone());
+            }
+            Ok(Value::Mapping(result))
+        }
+        (Value::Null, _) => Ok(right_val),
+        (_, Value::Null) => Ok(left_val),
         _ => Err(anyhow!(
             "Cannot add {:?} and {:?}",
             helpers::value_type(&left_val),
@@ -51,14 +96,28 @@ pub fn sub(
 
     match (&left_val, &right_val) {
         (Value::Number(a), Value::Number(b)) => {
-            if let (Some(ai), Some(bi)) = (a.as_i64(), b.as_i64()) {
-                Ok(Value::Number((ai - bi).into()))
+            if let (Some(ai), Some(bi)) = (integer(a), integer(b)) {
+                integer_result(ai.checked_sub(bi), a, "-", b)
             } else if let (Some(af), Some(bf)) = (a.as_f64(), b.as_f64()) {
                 Ok(Value::Number(serde_yaml::Number::from(af - bf)))
             } else {
                 Err(anyhow!("Cannot subtract numbers"))
             }
         }
+        (Value::Sequence(a), Value::Sequence(b)) => {
+            // Remove every element present in the right array, comparing
+            // by JSON representation like `unique`
+            let remove: HashSet<String> = b
+                .iter()
+                .map(|item| serde_json::to_string(item).unwrap_or_default())
+                .collect();
+            let result = a
+                .iter()
+                .filter(|item| !remove.contains(&serde_json::to_string(item).unwrap_or_default()))
+                .cloned()
+                .collect();
+            Ok(Value::Sequence(result))
+        }
         _ => Err(anyhow!(
             "Cannot subtract {:?} from {:?}",
             helpers::value_type(&right_val),
@@ -79,14 +138,15 @@ pub fn mul(
 
     match (&left_val, &right_val) {
         (Value::Number(a), Value::Number(b)) => {
-            if let (Some(ai), Some(bi)) = (a.as_i64(), b.as_i64()) {
-                Ok(Value::Number((ai * bi).into()))
+            if let (Some(ai), Some(bi)) = (integer(a), integer(b)) {
+                integer_result(ai.checked_mul(bi), a, "*", b)
             } else if let (Some(af), Some(bf)) = (a.as_f64(), b.as_f64()) {
                 Ok(Value::Number(serde_yaml::Number::from(af * bf)))
             } else {
                 Err(anyhow!("Cannot multiply numbers"))
             }
         }
+        (Value::Mapping(_), Value::Mapping(_)) => Ok(deep_merge(left_val, right_val)),
         _ => Err(anyhow!(
             "Cannot multiply {:?} and {:?}",
             helpers::value_type(&left_val),
@@ -95,6 +155,30 @@ pub fn mul(
     }
 }
 
+/// Recursively merge two objects
+///
+/// Nested objects present on both sides are merged; any other right-hand
+/// value, including arrays, replaces the left-hand one as in jq.
+fn deep_merge(left: Value, right: Value) -> Value {
+    match (left, right) {
+        (Value::Mapping(mut result), Value::Mapping(right)) => {
+            for (k, v) in right {
+                match result.get_mut(&k) {
+                    Some(existing) => {
+                        let left = std::mem::take(existing);
+                        *existing = deep_merge(left, v);
+                    }
+                    None => {
+                        result.insert(k, v);
+                    }
+                }
+            }
+            Value::Mapping(result)
+        }
+        (_, right) => right,
+    }
+}
+
 /// Divide two values
 pub fn div(
     evaluator: &Evaluator,
@@ -107,7 +191,12 @@ pub fn div(
 
     match (&left_val, &right_val) {
         (Value::Number(a), Value::Number(b)) => {
-            if let (Some(af), Some(bf)) = (a.as_f64(), b.as_f64()) {
+            if let (Some(ai), Some(bi)) = (integer(a), integer(b))
+                && bi != 0
+                && ai % bi == 0
+            {
+                integer_result(ai.checked_div(bi), a, "/", b)
+            } else if let (Some(af), Some(bf)) = (a.as_f64(), b.as_f64()) {
                 if bf == 0.0 {
                     return Err(anyhow!("Division by zero"));
                 }
@@ -136,11 +225,11 @@ pub fn modulo(
 
     match (&left_val, &right_val) {
         (Value::Number(a), Value::Number(b)) => {
-            if let (Some(ai), Some(bi)) = (a.as_i64(), b.as_i64()) {
+            if let (Some(ai), Some(bi)) = (integer(a), integer(b)) {
                 if bi == 0 {
                     return Err(anyhow!("Modulo by zero"));
                 }
-                Ok(Value::Number((ai % bi).into()))
+                integer_result(ai.checked_rem(bi), a, "%", b)
             } else if let (Some(af), Some(bf)) = (a.as_f64(), b.as_f64()) {
                 if bf == 0.0 {
                     return Err(anyhow!("Modulo by zero"));
@@ -198,6 +287,22 @@ mod tests {
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn test_add_objects_right_wins() {
+        let result = parse_and_eval(".[0] + .[1]", "[{a: 1, b: 2}, {b: 3, c: 4}]").unwrap();
+        let expected: Value = serde_yaml::from_str("{a: 1, b: 3, c: 4}").unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_add_null_identity() {
+        assert_eq!(parse_and_eval("null + 1", "null").unwrap(), 1);
+        assert_eq!(parse_and_eval("\"a\" + null", "null").unwrap(), "a");
+        let result = parse_and_eval(".a + .b", "{a: null, b: [1]}").unwrap();
+        let expected: Value = serde_yaml::from_str("[1]").unwrap();
+        assert_eq!(result, expected);
+    }
+
     #[test]
     fn test_add_mixed_types_error() {
         let result = parse_and_eval("1 + \"hello\"", "null");
@@ -223,6 +328,31 @@ mod tests {
         assert_eq!(result, -2);
     }
 
+    #[test]
+    fn test_sub_arrays() {
+        let result = parse_and_eval("[1, 2, 3, 4] - [2, 4]", "null").unwrap();
+        let expected: Value = serde_yaml::from_str("[1, 3]").unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_sub_arrays_removes_duplicates() {
+        let result = parse_and_eval("[1, 1, 2] - [1]", "null").unwrap();
+        let expected: Value = serde_yaml::from_str("[2]").unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_sub_arrays_mixed_elements() {
+        let result = parse_and_eval(
+            ".[0] - .[1]",
+            "[[a, {x: 1}, [1], null, b], [{x: 1}, [1], null, a]]",
+        )
+        .unwrap();
+        let expected: Value = serde_yaml::from_str("[b]").unwrap();
+        assert_eq!(result, expected);
+    }
+
     #[test]
     fn test_sub_strings_error() {
         let result = parse_and_eval("\"hello\" - \"world\"", "null");
@@ -248,6 +378,29 @@ mod tests {
         assert_eq!(result, 0);
     }
 
+    #[test]
+    fn test_mul_objects_deep_merge() {
+        let result = parse_and_eval(
+            ".[0] * .[1]",
+            "[{a: {x: 1, n: {p: 1}}, k: 1}, {a: {y: 2, n: {q: 2}}}]",
+        )
+        .unwrap();
+        let expected: Value =
+            serde_yaml::from_str("{a: {x: 1, n: {p: 1, q: 2}, y: 2}, k: 1}").unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_mul_objects_right_scalar_and_array_replace() {
+        let result = parse_and_eval(
+            ".[0] * .[1]",
+            "[{a: {x: 1}, b: 1, l: [1, 2]}, {a: 5, b: {y: 2}, l: [3]}]",
+        )
+        .unwrap();
+        let expected: Value = serde_yaml::from_str("{a: 5, b: {y: 2}, l: [3]}").unwrap();
+        assert_eq!(result, expected);
+    }
+
     // Division tests
     #[test]
     fn test_div_integers() {
@@ -313,4 +466,42 @@ mod tests {
         let result = parse_and_eval(".a + .b", "a: 5\nb: 3").unwrap();
         assert_eq!(result, 8);
     }
+
+    #[test]
+    fn test_large_integers_stay_exact() {
+        let result = parse_and_eval(". + 0", "9007199254740993").unwrap();
+        assert_eq!(result, 9007199254740993u64);
+        let result = parse_and_eval(". * 2 / 2", "9007199254740993").unwrap();
+        assert_eq!(result, 9007199254740993u64);
+        // Results between i64::MAX and u64::MAX are kept as unsigned
+        let result = parse_and_eval(". + 1", "9223372036854775807").unwrap();
+        assert_eq!(result, 9223372036854775808u64);
+        let result = parse_and_eval(". - 1", "18446744073709551615").unwrap();
+        assert_eq!(result, 18446744073709551614u64);
+        let result = parse_and_eval(". % 10", "18446744073709551615").unwrap();
+        assert_eq!(result, 5u64);
+    }
+
+    #[test]
+    fn test_integer_overflow_is_an_error() {
+        let err = parse_and_eval(". + 1", "18446744073709551615").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "18446744073709551615 + 1 is outside the 64-bit integer range"
+        );
+        assert!(parse_and_eval(". * .", "9223372036854775807").is_err());
+        // u64::MAX squared overflows even the i128 the product is taken in
+        let err = parse_and_eval(". * .", "18446744073709551615").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "18446744073709551615 * 18446744073709551615 is outside the 64-bit integer range"
+        );
+        assert!(parse_and_eval(". - 1", "-9223372036854775808").is_err());
+    }
+
+    #[test]
+    fn test_div_inexact_integers_is_float() {
+        let result = parse_and_eval(". / 2", "7").unwrap();
+        assert_eq!(result, 3.5);
+    }
 }
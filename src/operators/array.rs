@@ -6,11 +6,12 @@ use anyhow::Result;
 use serde_yaml::Value;
 
 /// Evaluate array constructor
+///
+/// Each element contributes all of its outputs, so `empty` adds nothing.
 pub fn eval(evaluator: &Evaluator, elements: &[Expression], ctx: &Context) -> Result<Value> {
     let mut result = Vec::new();
     for expr in elements {
-        let val = evaluator.eval(expr, ctx)?;
-        result.push(val);
+        result.extend(evaluator.eval_multi(expr, ctx)?);
     }
     Ok(Value::Sequence(result))
 }
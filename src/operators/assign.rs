@@ -1,28 +1,89 @@
 //! Assignment operator (=)
 
+use super::path;
 use crate::evaluator::{Context, Evaluator};
 use crate::parser::expression::Expression;
-use anyhow::{Result, anyhow};
+use anyhow::Result;
 use serde_yaml::Value;
 
 /// Evaluate assignment
+///
+/// The right-hand side is evaluated against the input, then stored at every
+/// path the target addresses. Missing objects and arrays along a path are
+/// created, so `.a.b.c = 1` works on `{}`.
 pub fn eval(
     evaluator: &Evaluator,
     target: &Expression,
     value: &Expression,
     ctx: &Context,
 ) -> Result<Value> {
-    // For now, simple field assignment
-    match target {
-        Expression::FieldAccess { target: _, field } => {
-            let new_value = evaluator.eval(value, ctx)?;
-            // Return a new object with the field set
-            let mut result = ctx.value.clone();
-            if let Value::Mapping(ref mut map) = result {
-                map.insert(Value::String(field.clone()), new_value);
-            }
-            Ok(result)
-        }
-        _ => Err(anyhow!("Assignment target must be a field access")),
+    let new_value = evaluator.eval(value, ctx)?;
+
+    let mut result = ctx.value.clone();
+    for p in path::paths(evaluator, target, ctx)? {
+        path::set_path(&mut result, &p, new_value.clone())?;
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval(expr_str: &str, input: &str) -> Result<Value> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate(&expr, Some(&input_val))
+    }
+
+    fn yaml(s: &str) -> Value {
+        serde_yaml::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_assign_top_level_field() {
+        let result = parse_and_eval(".a = 2", "{a: 1, b: 1}").unwrap();
+        assert_eq!(result, yaml("{a: 2, b: 1}"));
+    }
+
+    #[test]
+    fn test_assign_nested_creates_objects() {
+        let result = parse_and_eval(".a.b.c = 5", "{}").unwrap();
+        assert_eq!(result, yaml("{a: {b: {c: 5}}}"));
+    }
+
+    #[test]
+    fn test_assign_keeps_siblings() {
+        let result = parse_and_eval(".a.b = 2", "{a: {b: 1, c: 3}, d: 4}").unwrap();
+        assert_eq!(result, yaml("{a: {b: 2, c: 3}, d: 4}"));
+    }
+
+    #[test]
+    fn test_assign_array_index() {
+        let result = parse_and_eval(r#".items[1] = "x""#, "{items: [a, b, c]}").unwrap();
+        assert_eq!(result, yaml("{items: [a, x, c]}"));
+
+        let result = parse_and_eval(r#".items[2].name = "x""#, "{}").unwrap();
+        assert_eq!(result, yaml("{items: [null, null, {name: x}]}"));
+    }
+
+    #[test]
+    fn test_assign_every_iterated_element() {
+        let result = parse_and_eval(".[].done = true", "[{done: false}, {}]").unwrap();
+        assert_eq!(result, yaml("[{done: true}, {done: true}]"));
+    }
+
+    #[test]
+    fn test_assign_rhs_uses_input() {
+        let result = parse_and_eval(".a.b = .c", "{c: 3}").unwrap();
+        assert_eq!(result, yaml("{c: 3, a: {b: 3}}"));
+    }
+
+    #[test]
+    fn test_assign_through_scalar_error() {
+        assert!(parse_and_eval(".a.b = 1", "{a: 1}").is_err());
     }
 }
@@ -0,0 +1,274 @@
+//! Variable binding (`source as $name | body`) and destructuring
+//! (`. as {a: $a, b: [$first]} | body`)
+
+use crate::evaluator::{Context, Evaluator, ValueStream, helpers};
+use crate::parser::expression::{Expression, Pattern};
+use anyhow::{Result, anyhow};
+use serde_yaml::Value;
+
+/// Evaluate a binding - returns the first output of the body, or null
+pub fn eval(
+    evaluator: &Evaluator,
+    source: &Expression,
+    pattern: &Pattern,
+    body: &Expression,
+    ctx: &Context,
+) -> Result<Value> {
+    Ok(eval_multi(evaluator, source, pattern, body, ctx)?
+        .into_iter()
+        .next()
+        .unwrap_or(Value::Null))
+}
+
+/// Stream the outputs of the body with the pattern bound to each output of
+/// `source` in turn
+///
+/// The body runs against the original input, not the bound value.
+pub fn eval_multi(
+    evaluator: &Evaluator,
+    source: &Expression,
+    pattern: &Pattern,
+    body: &Expression,
+    ctx: &Context,
+) -> Result<Vec<Value>> {
+    eval_lazy(evaluator, source, pattern, body, ctx)?.collect()
+}
+
+/// Produce the outputs of the body lazily, binding the pattern to each
+/// output of `source` as it is pulled
+pub fn eval_lazy<'a>(
+    evaluator: &'a Evaluator,
+    source: &'a Expression,
+    pattern: &'a Pattern,
+    body: &'a Expression,
+    ctx: &Context,
+) -> Result<ValueStream<'a>> {
+    let values = evaluator.eval_lazy(source, ctx)?;
+    let ctx = ctx.clone();
+    Ok(Box::new(values.flat_map(move |value| {
+        match value.and_then(|value| bound(evaluator, pattern, &value, &ctx)) {
+            Ok(bound_ctx) => evaluator.stream(body, &bound_ctx),
+            Err(err) => Box::new(std::iter::once(Err(err))),
+        }
+    })))
+}
+
+/// Return a context in which the variables of `pattern` are bound to the
+/// matching parts of `value`
+pub fn bound(
+    evaluator: &Evaluator,
+    pattern: &Pattern,
+    value: &Value,
+    ctx: &Context,
+) -> Result<Context> {
+    let mut ctx = ctx.clone();
+    destructure(evaluator, pattern, value, &mut ctx)?;
+    Ok(ctx)
+}
+
+/// Walk `pattern` against `value`, binding its variables in `ctx`
+///
+/// Missing elements and keys (and null in place of a container) bind null,
+/// so `[1] as [$a, $b]` sets `$b` to null. Object pattern keys are
+/// evaluated against the input, with the variables bound so far.
+fn destructure(
+    evaluator: &Evaluator,
+    pattern: &Pattern,
+    value: &Value,
+    ctx: &mut Context,
+) -> Result<()> {
+    match pattern {
+        Pattern::Variable(name) => {
+            ctx.set_variable(name.clone(), value.clone());
+            Ok(())
+        }
+        Pattern::Array(elements) => {
+            let items = match value {
+                Value::Sequence(items) => items.as_slice(),
+                Value::Null => &[],
+                other => {
+                    return Err(anyhow!(
+                        "Cannot index {} with number",
+                        helpers::value_type(other)
+                    ));
+                }
+            };
+            for (i, element) in elements.iter().enumerate() {
+                let item = items.get(i).unwrap_or(&Value::Null);
+                destructure(evaluator, element, item, ctx)?;
+            }
+            Ok(())
+        }
+        Pattern::Object(entries) => {
+            if !matches!(value, Value::Mapping(_) | Value::Null) {
+                return Err(anyhow!(
+                    "Cannot index {} with a string",
+                    helpers::value_type(value)
+                ));
+            }
+            for (key, entry) in entries {
+                let key = match evaluator.eval(key, ctx)? {
+                    key @ Value::String(_) => key,
+                    other => {
+                        return Err(anyhow!(
+                            "Cannot index object with {}",
+                            helpers::value_type(&other)
+                        ));
+                    }
+                };
+                let item = value.get(&key).unwrap_or(&Value::Null);
+                destructure(evaluator, entry, item, ctx)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval(expr_str: &str, input: &str) -> Result<Value> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate(&expr, Some(&input_val))
+    }
+
+    fn parse_and_eval_multi(expr_str: &str, input: &str) -> Result<Vec<Value>> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate_multi(&expr, Some(&input_val))
+    }
+
+    fn yaml(s: &str) -> Value {
+        serde_yaml::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_bind_literal() {
+        assert_eq!(parse_and_eval("5 as $x | $x + 1", "null").unwrap(), 6);
+    }
+
+    #[test]
+    fn test_bind_generator() {
+        let result =
+            parse_and_eval_multi(".[] as $item | $item.name", "[{name: a}, {name: b}]").unwrap();
+        assert_eq!(result, vec![yaml("a"), yaml("b")]);
+    }
+
+    #[test]
+    fn test_bind_body_sees_original_input() {
+        let result = parse_and_eval(".a as $x | .b + $x", "{a: 1, b: 2}").unwrap();
+        assert_eq!(result, 3);
+    }
+
+    #[test]
+    fn test_bind_nested_and_shadowed() {
+        let result = parse_and_eval("1 as $x | 2 as $y | [$x, $y]", "null").unwrap();
+        assert_eq!(result, yaml("[1, 2]"));
+        let result = parse_and_eval("1 as $x | (2 as $x | $x), $x", "null").unwrap();
+        assert_eq!(result, 2);
+        let result = parse_and_eval_multi("1 as $x | (2 as $x | $x), $x", "null").unwrap();
+        assert_eq!(result, vec![yaml("2"), yaml("1")]);
+    }
+
+    #[test]
+    fn test_bind_inside_array() {
+        let result = parse_and_eval("[.[] as $n | $n * 2]", "[1, 2, 3]").unwrap();
+        assert_eq!(result, yaml("[2, 4, 6]"));
+    }
+
+    #[test]
+    fn test_bind_binds_tighter_than_arithmetic() {
+        assert_eq!(parse_and_eval("1 + 2 as $x | $x * 10", "null").unwrap(), 21);
+    }
+
+    #[test]
+    fn test_bind_empty_source() {
+        assert!(
+            parse_and_eval_multi("empty as $x | 1", "null")
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            parse_and_eval("empty as $x | 1", "null").unwrap(),
+            Value::Null
+        );
+    }
+
+    #[test]
+    fn test_bind_scoped_to_body() {
+        assert!(parse_and_eval("(1 as $x | $x) | $x", "null").is_err());
+    }
+
+    #[test]
+    fn test_bind_object_pattern() {
+        let result = parse_and_eval(r#"{"a":1,"b":2} as {a:$a,b:$b} | $a+$b"#, "null").unwrap();
+        assert_eq!(result, 3);
+    }
+
+    #[test]
+    fn test_bind_object_pattern_shorthands() {
+        let result = parse_and_eval(
+            r#". as {$a, "b": $b, ("c"): $c} | [$a, $b, $c]"#,
+            "{a: 1, b: 2, c: 3}",
+        )
+        .unwrap();
+        assert_eq!(result, yaml("[1, 2, 3]"));
+        let result = parse_and_eval(". as {$a: [$first]} | [$a, $first]", "{a: [1, 2]}").unwrap();
+        assert_eq!(result, yaml("[[1, 2], 1]"));
+    }
+
+    #[test]
+    fn test_bind_array_pattern() {
+        let result = parse_and_eval(". as [$first, $second] | $second", "[1, 2, 3]").unwrap();
+        assert_eq!(result, 2);
+    }
+
+    #[test]
+    fn test_bind_array_pattern_missing_elements() {
+        let result = parse_and_eval(". as [$a, $b, $c] | [$a, $b, $c]", "[1]").unwrap();
+        assert_eq!(result, yaml("[1, null, null]"));
+        let result = parse_and_eval(". as {a: $a, b: [$b]} | [$a, $b]", "{}").unwrap();
+        assert_eq!(result, yaml("[null, null]"));
+    }
+
+    #[test]
+    fn test_bind_nested_pattern_per_output() {
+        let result = parse_and_eval_multi(
+            r#".[] as {name: $n, tags: [$t]} | "\($n):\($t)""#,
+            "[{name: a, tags: [x, y]}, {name: b, tags: [z]}]",
+        )
+        .unwrap();
+        assert_eq!(result, vec![yaml("'a:x'"), yaml("'b:z'")]);
+    }
+
+    #[test]
+    fn test_bind_pattern_keys_see_earlier_variables() {
+        let result = parse_and_eval(". as {k: $k, ($k): $v} | $v", "{k: x, x: 5}").unwrap();
+        assert_eq!(result, 5);
+    }
+
+    #[test]
+    fn test_bind_pattern_type_errors() {
+        assert!(parse_and_eval(". as [$a] | $a", "{a: 1}").is_err());
+        assert!(parse_and_eval(". as {a: $a} | $a", "[1]").is_err());
+        assert!(parse_and_eval(". as {(1): $a} | $a", "{}").is_err());
+    }
+
+    #[test]
+    fn test_bind_syntax_errors() {
+        let parser = ExpressionParser::new();
+        assert!(parser.parse(". as x | x").is_err());
+        assert!(parser.parse(". as $x").is_err());
+        assert!(parser.parse(". as $x || 1").is_err());
+        assert!(parser.parse(". as [] | 1").is_err());
+        assert!(parser.parse(". as [$a | $a").is_err());
+        assert!(parser.parse(". as {a} | 1").is_err());
+    }
+}
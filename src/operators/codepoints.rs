@@ -0,0 +1,92 @@
+//! Codepoint functions (explode, implode, ascii)
+
+use crate::evaluator::{Context, Evaluator, helpers};
+use anyhow::{Result, anyhow};
+use serde_yaml::Value;
+
+/// Evaluate explode function - string to array of codepoints
+pub fn eval_explode(_evaluator: &Evaluator, ctx: &Context) -> Result<Value> {
+    match &ctx.value {
+        Value::String(s) => Ok(Value::Sequence(
+            s.chars()
+                .map(|c| Value::Number((c as u32).into()))
+                .collect(),
+        )),
+        other => Err(anyhow!("Cannot explode {}", helpers::value_type(other))),
+    }
+}
+
+/// Evaluate implode function - array of codepoints to string
+pub fn eval_implode(_evaluator: &Evaluator, ctx: &Context) -> Result<Value> {
+    let arr = match &ctx.value {
+        Value::Sequence(arr) => arr,
+        other => return Err(anyhow!("Cannot implode {}", helpers::value_type(other))),
+    };
+
+    arr.iter()
+        .map(to_char)
+        .collect::<Result<String>>()
+        .map(Value::String)
+}
+
+/// Evaluate ascii function - codepoint in 0..=127 to a one-character string
+pub fn eval_ascii(_evaluator: &Evaluator, ctx: &Context) -> Result<Value> {
+    let c = to_char(&ctx.value)?;
+    if !c.is_ascii() {
+        return Err(anyhow!("ascii expects a codepoint between 0 and 127"));
+    }
+    Ok(Value::String(c.to_string()))
+}
+
+fn to_char(value: &Value) -> Result<char> {
+    let Value::Number(n) = value else {
+        return Err(anyhow!(
+            "Cannot implode {}: codepoints must be numbers",
+            helpers::value_type(value)
+        ));
+    };
+    n.as_u64()
+        .and_then(|cp| u32::try_from(cp).ok())
+        .and_then(char::from_u32)
+        .ok_or_else(|| anyhow!("Invalid codepoint: {}", n))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval(expr_str: &str, input: &str) -> Result<Value> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate(&expr, Some(&input_val))
+    }
+
+    #[test]
+    fn test_explode() {
+        let result = parse_and_eval("explode", "\"aé\"").unwrap();
+        let expected: Value = serde_yaml::from_str("[97, 233]").unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_explode_implode_round_trip() {
+        assert_eq!(parse_and_eval("explode | implode", "abc").unwrap(), "abc");
+    }
+
+    #[test]
+    fn test_implode_invalid_codepoints() {
+        assert!(parse_and_eval("implode", "[1114112]").is_err());
+        assert!(parse_and_eval("implode", "[55296]").is_err());
+        assert!(parse_and_eval("implode", "[-1]").is_err());
+        assert!(parse_and_eval("implode", "[a]").is_err());
+    }
+
+    #[test]
+    fn test_ascii() {
+        assert_eq!(parse_and_eval("ascii", "65").unwrap(), "A");
+        assert!(parse_and_eval("ascii", "233").is_err());
+    }
+}
@@ -5,14 +5,81 @@ use crate::parser::expression::Expression;
 use anyhow::Result;
 use serde_yaml::Value;
 
-/// Evaluate comma - collect results into an array
+/// Evaluate comma - returns the first output, or null
 pub fn eval(
-    _evaluator: &Evaluator,
-    _left: &Expression,
-    _right: &Expression,
-    _ctx: &Context,
+    evaluator: &Evaluator,
+    left: &Expression,
+    right: &Expression,
+    ctx: &Context,
 ) -> Result<Value> {
-    // Comma is handled during parsing as array constructor
-    // This should not be called directly
-    Ok(Value::Null)
+    Ok(eval_multi(evaluator, left, right, ctx)?
+        .into_iter()
+        .next()
+        .unwrap_or(Value::Null))
+}
+
+/// Stream every output of `left`, then every output of `right`
+pub fn eval_multi(
+    evaluator: &Evaluator,
+    left: &Expression,
+    right: &Expression,
+    ctx: &Context,
+) -> Result<Vec<Value>> {
+    let mut results = evaluator.eval_multi(left, ctx)?;
+    results.extend(evaluator.eval_multi(right, ctx)?);
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval_multi(expr_str: &str, input: &str) -> Result<Vec<Value>> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate_multi(&expr, Some(&input_val))
+    }
+
+    fn yaml(s: &str) -> Value {
+        serde_yaml::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_comma_streams_both_sides() {
+        let result = parse_and_eval_multi(".a, .b", "{a: 1, b: 2}").unwrap();
+        assert_eq!(result, vec![yaml("1"), yaml("2")]);
+    }
+
+    #[test]
+    fn test_comma_inside_array_constructs_array() {
+        let result = parse_and_eval_multi("[.a, .b]", "{a: 1, b: 2}").unwrap();
+        assert_eq!(result, vec![yaml("[1, 2]")]);
+
+        let result = parse_and_eval_multi("[(1, 2), 3]", "null").unwrap();
+        assert_eq!(result, vec![yaml("[1, 2, 3]")]);
+    }
+
+    #[test]
+    fn test_comma_with_array_operand() {
+        let result = parse_and_eval_multi("[1, 2], 3", "null").unwrap();
+        assert_eq!(result, vec![yaml("[1, 2]"), yaml("3")]);
+    }
+
+    #[test]
+    fn test_comma_binds_tighter_than_pipe() {
+        let result = parse_and_eval_multi("1, 2 | . * 10", "null").unwrap();
+        assert_eq!(result, vec![yaml("10"), yaml("20")]);
+
+        let result = parse_and_eval_multi(".[] | .x, .y", "[{x: 1, y: 2}, {x: 3, y: 4}]").unwrap();
+        assert_eq!(result, vec![yaml("1"), yaml("2"), yaml("3"), yaml("4")]);
+    }
+
+    #[test]
+    fn test_comma_with_empty() {
+        let result = parse_and_eval_multi("empty, 1, empty", "null").unwrap();
+        assert_eq!(result, vec![yaml("1")]);
+    }
 }
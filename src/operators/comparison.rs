@@ -15,10 +15,7 @@ pub fn equal(
     let left_val = evaluator.eval(left, ctx)?;
     let right_val = evaluator.eval(right, ctx)?;
 
-    let result = match helpers::compare_values(&left_val, &right_val) {
-        Some(ordering) => ordering == std::cmp::Ordering::Equal,
-        None => false,
-    };
+    let result = helpers::compare_values(&left_val, &right_val).is_eq();
 
     Ok(Value::Bool(result))
 }
@@ -33,10 +30,7 @@ pub fn not_equal(
     let left_val = evaluator.eval(left, ctx)?;
     let right_val = evaluator.eval(right, ctx)?;
 
-    let result = match helpers::compare_values(&left_val, &right_val) {
-        Some(ordering) => ordering != std::cmp::Ordering::Equal,
-        None => true,
-    };
+    let result = helpers::compare_values(&left_val, &right_val).is_ne();
 
     Ok(Value::Bool(result))
 }
@@ -51,10 +45,7 @@ pub fn less_than(
     let left_val = evaluator.eval(left, ctx)?;
     let right_val = evaluator.eval(right, ctx)?;
 
-    let result = match helpers::compare_values(&left_val, &right_val) {
-        Some(ordering) => ordering == std::cmp::Ordering::Less,
-        None => false,
-    };
+    let result = helpers::compare_values(&left_val, &right_val).is_lt();
 
     Ok(Value::Bool(result))
 }
@@ -69,12 +60,7 @@ pub fn less_than_or_equal(
     let left_val = evaluator.eval(left, ctx)?;
     let right_val = evaluator.eval(right, ctx)?;
 
-    let result = match helpers::compare_values(&left_val, &right_val) {
-        Some(ordering) => {
-            ordering == std::cmp::Ordering::Less || ordering == std::cmp::Ordering::Equal
-        }
-        None => false,
-    };
+    let result = helpers::compare_values(&left_val, &right_val).is_le();
 
     Ok(Value::Bool(result))
 }
@@ -89,10 +75,7 @@ pub fn greater_than(
     let left_val = evaluator.eval(left, ctx)?;
     let right_val = evaluator.eval(right, ctx)?;
 
-    let result = match helpers::compare_values(&left_val, &right_val) {
-        Some(ordering) => ordering == std::cmp::Ordering::Greater,
-        None => false,
-    };
+    let result = helpers::compare_values(&left_val, &right_val).is_gt();
 
     Ok(Value::Bool(result))
 }
@@ -107,12 +90,7 @@ pub fn greater_than_or_equal(
     let left_val = evaluator.eval(left, ctx)?;
     let right_val = evaluator.eval(right, ctx)?;
 
-    let result = match helpers::compare_values(&left_val, &right_val) {
-        Some(ordering) => {
-            ordering == std::cmp::Ordering::Greater || ordering == std::cmp::Ordering::Equal
-        }
-        None => false,
-    };
+    let result = helpers::compare_values(&left_val, &right_val).is_ge();
 
     Ok(Value::Bool(result))
 }
@@ -291,10 +269,39 @@ mod tests {
         assert_eq!(result, true);
     }
 
-    // Array comparisons (should fail - arrays not comparable)
+    // Array and object comparisons
     #[test]
     fn test_compare_arrays() {
         let result = parse_and_eval("[1, 2] == [1, 2]", "null").unwrap();
-        assert_eq!(result, false); // Arrays are not comparable, returns false
+        assert_eq!(result, true);
+        let result = parse_and_eval("[1, 2] < [1, 3]", "null").unwrap();
+        assert_eq!(result, true);
+        let result = parse_and_eval("[1, 2] < [1, 2, 0]", "null").unwrap();
+        assert_eq!(result, true);
+    }
+
+    #[test]
+    fn test_compare_objects() {
+        let result = parse_and_eval(".[0] == .[1]", "[{a: 1, b: 2}, {b: 2, a: 1}]").unwrap();
+        assert_eq!(result, true);
+        // Key sets compare before values
+        let result = parse_and_eval(".[0] < .[1]", "[{a: 9}, {b: 1}]").unwrap();
+        assert_eq!(result, true);
+        let result = parse_and_eval(".[0] < .[1]", "[{a: 1}, {a: 2}]").unwrap();
+        assert_eq!(result, true);
+    }
+
+    #[test]
+    fn test_compare_across_types() {
+        let result = parse_and_eval("null < false", "null").unwrap();
+        assert_eq!(result, true);
+        let result = parse_and_eval("true < 0", "null").unwrap();
+        assert_eq!(result, true);
+        let result = parse_and_eval("100 < \"a\"", "null").unwrap();
+        assert_eq!(result, true);
+        let result = parse_and_eval("\"z\" < []", "null").unwrap();
+        assert_eq!(result, true);
+        let result = parse_and_eval("[] < {}", "null").unwrap();
+        assert_eq!(result, true);
     }
 }
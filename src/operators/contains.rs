@@ -0,0 +1,280 @@
+//! Contains function, and the string tests startswith and endswith
+
+use crate::evaluator::{Context, Evaluator, helpers};
+use crate::parser::expression::Expression;
+use anyhow::{Result, anyhow};
+use serde_yaml::Value;
+use std::cmp::Ordering;
+
+/// Evaluate contains function
+pub fn eval(
+    evaluator: &Evaluator,
+    target: &Expression,
+    value: &Expression,
+    ctx: &Context,
+) -> Result<Value> {
+    let target_val = evaluator.eval(target, ctx)?;
+    let value_val = evaluator.eval(value, ctx)?;
+
+    Ok(Value::Bool(contains(&target_val, &value_val)?))
+}
+
+/// Check whether `a` contains `b`, as in jq
+///
+/// `a` and `b` must be of the same type. A string contains its substrings;
+/// an array contains `b` when every element of `b` is contained in some
+/// element of `a` (so duplicates in either array don't matter); an object
+/// contains `b` when every key of `b` is present and its value is
+/// contained. Other values must be equal.
+pub fn contains(a: &Value, b: &Value) -> Result<bool> {
+    if helpers::value_type(a) != helpers::value_type(b) {
+        return Err(anyhow!(
+            "{} and {} cannot have their containment checked",
+            helpers::value_type(a),
+            helpers::value_type(b)
+        ));
+    }
+    Ok(contained(a, b))
+}
+
+/// `contains` below the top level, where values of different types simply
+/// don't contain each other
+fn contained(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Tagged(tagged), _) => contained(&tagged.value, b),
+        (_, Value::Tagged(tagged)) => contained(a, &tagged.value),
+        (Value::String(a), Value::String(b)) => a.contains(b.as_str()),
+        (Value::Sequence(a), Value::Sequence(b)) => b
+            .iter()
+            .all(|needle| a.iter().any(|item| contained(item, needle))),
+        (Value::Mapping(a), Value::Mapping(b)) => b
+            .iter()
+            .all(|(key, needle)| a.get(key).is_some_and(|item| contained(item, needle))),
+        _ => {
+            helpers::value_type(a) == helpers::value_type(b)
+                && helpers::compare_values(a, b) == Ordering::Equal
+        }
+    }
+}
+
+/// Evaluate startswith (or endswith, with `suffix` set) - the first
+/// result, or null if there is none
+pub fn eval_affix(
+    evaluator: &Evaluator,
+    target: &Expression,
+    affix: &Expression,
+    suffix: bool,
+    ctx: &Context,
+) -> Result<Value> {
+    Ok(eval_multi_affix(evaluator, target, affix, suffix, ctx)?
+        .into_iter()
+        .next()
+        .unwrap_or(Value::Null))
+}
+
+/// Test whether the input string starts (or, with `suffix`, ends) with
+/// each output of `affix`
+///
+/// A string argument gives one boolean. An array of strings gives one
+/// boolean per element, in order, so `startswith(["a", "b"])` streams
+/// like `startswith("a", "b")`; `[startswith(["a", "b"])] | contains([true])`
+/// tests for any of them.
+pub fn eval_multi_affix(
+    evaluator: &Evaluator,
+    target: &Expression,
+    affix: &Expression,
+    suffix: bool,
+    ctx: &Context,
+) -> Result<Vec<Value>> {
+    let func = if suffix { "endswith" } else { "startswith" };
+    let target_val = evaluator.eval(target, ctx)?;
+    let Value::String(s) = &target_val else {
+        return Err(anyhow!(
+            "{} requires string input, got {}",
+            func,
+            helpers::value_type(&target_val)
+        ));
+    };
+    let test = |affix: &Value| match affix {
+        Value::String(affix) if suffix => Ok(Value::Bool(s.ends_with(affix.as_str()))),
+        Value::String(affix) => Ok(Value::Bool(s.starts_with(affix.as_str()))),
+        other => Err(anyhow!(
+            "{} requires a string or an array of strings, got {}",
+            func,
+            helpers::value_type(other)
+        )),
+    };
+
+    let mut results = Vec::new();
+    for affix in evaluator.eval_multi(affix, ctx)? {
+        match &affix {
+            Value::Sequence(affixes) => {
+                for affix in affixes {
+                    results.push(test(affix)?);
+                }
+            }
+            affix => results.push(test(affix)?),
+        }
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval(expr_str: &str, input: &str) -> Result<Value> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate(&expr, Some(&input_val))
+    }
+
+    #[test]
+    fn test_contains_substring() {
+        assert_eq!(
+            parse_and_eval(r#"contains("oba")"#, "foobar").unwrap(),
+            true
+        );
+        assert_eq!(parse_and_eval(r#"contains("")"#, "foobar").unwrap(), true);
+        assert_eq!(
+            parse_and_eval(r#"contains("baz")"#, "foobar").unwrap(),
+            false
+        );
+    }
+
+    #[test]
+    fn test_contains_array_subset() {
+        assert_eq!(
+            parse_and_eval("contains([1, 3])", "[1, 2, 3]").unwrap(),
+            true
+        );
+        assert_eq!(parse_and_eval("contains([4])", "[1, 2, 3]").unwrap(), false);
+        assert_eq!(parse_and_eval("contains([])", "[1]").unwrap(), true);
+    }
+
+    #[test]
+    fn test_contains_array_duplicates() {
+        assert_eq!(parse_and_eval("contains([1, 1])", "[1]").unwrap(), true);
+        assert_eq!(parse_and_eval("contains([2])", "[2, 2, 2]").unwrap(), true);
+        assert_eq!(parse_and_eval("contains([1, 1, 5])", "[1]").unwrap(), false);
+    }
+
+    #[test]
+    fn test_contains_array_elements_recursively() {
+        let result = parse_and_eval(r#"contains(["bar", "baz"])"#, "[foobar, foobaz]").unwrap();
+        assert_eq!(result, true);
+    }
+
+    #[test]
+    fn test_contains_object() {
+        let input = "{a: 1, b: {c: [x, y], d: 2}}";
+        assert_eq!(
+            parse_and_eval(r#"contains({"b": {"c": ["y"]}})"#, input).unwrap(),
+            true
+        );
+        assert_eq!(
+            parse_and_eval(r#"contains({"z": 1})"#, input).unwrap(),
+            false
+        );
+    }
+
+    #[test]
+    fn test_contains_scalars() {
+        assert_eq!(parse_and_eval("contains(1)", "1").unwrap(), true);
+        assert_eq!(parse_and_eval("contains(2)", "1").unwrap(), false);
+        assert_eq!(parse_and_eval("contains(null)", "null").unwrap(), true);
+    }
+
+    #[test]
+    fn test_contains_type_mismatch() {
+        assert!(parse_and_eval(r#"contains("a")"#, "[a]").is_err());
+        assert!(parse_and_eval("contains([1])", "{a: 1}").is_err());
+        assert!(parse_and_eval("contains(1)", "'1'").is_err());
+    }
+
+    #[test]
+    fn test_contains_nested_type_mismatch_does_not_match() {
+        assert_eq!(
+            parse_and_eval(r#"contains(["a"])"#, "[1, a]").unwrap(),
+            true
+        );
+        assert_eq!(parse_and_eval("contains([1])", "[a, [1]]").unwrap(), false);
+        assert_eq!(
+            parse_and_eval(r#"contains({a: "x"})"#, "{a: 1}").unwrap(),
+            false
+        );
+        assert_eq!(
+            parse_and_eval(r#"contains({a: ["x"]})"#, "{a: [1, x]}").unwrap(),
+            true
+        );
+    }
+
+    fn parse_and_eval_multi(expr_str: &str, input: &str) -> Result<Vec<Value>> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate_multi(&expr, Some(&input_val))
+    }
+
+    #[test]
+    fn test_startswith_endswith() {
+        assert_eq!(
+            parse_and_eval(r#"startswith("foo")"#, "foobar").unwrap(),
+            true
+        );
+        assert_eq!(
+            parse_and_eval(r#"startswith("bar")"#, "foobar").unwrap(),
+            false
+        );
+        assert_eq!(
+            parse_and_eval(r#"endswith("bar")"#, "foobar").unwrap(),
+            true
+        );
+        assert_eq!(
+            parse_and_eval(r#"endswith("foo")"#, "foobar").unwrap(),
+            false
+        );
+        assert_eq!(parse_and_eval(r#"startswith("")"#, "foobar").unwrap(), true);
+    }
+
+    #[test]
+    fn test_startswith_array_streams_per_element() {
+        let result = parse_and_eval_multi(r#"startswith(["fo", "ba", "f"])"#, "foobar").unwrap();
+        assert_eq!(
+            result,
+            vec![Value::Bool(true), Value::Bool(false), Value::Bool(true)]
+        );
+        let result = parse_and_eval_multi(r#"endswith("ar", "x")"#, "foobar").unwrap();
+        assert_eq!(result, vec![Value::Bool(true), Value::Bool(false)]);
+        assert!(
+            parse_and_eval_multi("startswith([])", "foobar")
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_startswith_array_collected() {
+        let result = parse_and_eval(
+            r#"[endswith([".yml", ".yaml"])] | contains([true])"#,
+            "a.yaml",
+        )
+        .unwrap();
+        assert_eq!(result, true);
+    }
+
+    #[test]
+    fn test_startswith_errors() {
+        let err = parse_and_eval(r#"startswith("a")"#, "[a]").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "startswith requires string input, got array"
+        );
+        assert!(parse_and_eval("endswith(1)", "abc").is_err());
+        assert!(parse_and_eval(r#"endswith(["a", 1])"#, "abc").is_err());
+    }
+}
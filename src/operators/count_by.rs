@@ -0,0 +1,113 @@
+//! Count by function (count_by(f))
+
+use super::group_by;
+use crate::evaluator::{Context, Evaluator, helpers};
+use crate::parser::expression::Expression;
+use anyhow::{Result, anyhow};
+use serde_yaml::Value;
+
+/// Evaluate count_by function
+///
+/// Groups the array as `group_by` does and returns a `{key, count}` object
+/// per group, in key order. The key is the output of `f`, or an array of
+/// its outputs when `f` produces zero or several.
+pub fn eval(
+    evaluator: &Evaluator,
+    target: &Expression,
+    key_expr: &Expression,
+    ctx: &Context,
+) -> Result<Value> {
+    let target_val = evaluator.eval(target, ctx)?;
+
+    match target_val {
+        Value::Sequence(arr) => {
+            let counts = group_by::groups(evaluator, arr, key_expr, ctx)?
+                .into_iter()
+                .map(|(key, items)| {
+                    let key = match key {
+                        Value::Sequence(mut outputs) if outputs.len() == 1 => outputs.remove(0),
+                        key => key,
+                    };
+                    let mut obj = serde_yaml::Mapping::new();
+                    obj.insert("key".into(), key);
+                    obj.insert("count".into(), Value::Number(items.len().into()));
+                    Value::Mapping(obj)
+                })
+                .collect();
+            Ok(Value::Sequence(counts))
+        }
+        _ => Err(anyhow!(
+            "Cannot count by key in {}",
+            helpers::value_type(&target_val)
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval(expr_str: &str, input: &str) -> Result<Value> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate(&expr, Some(&input_val))
+    }
+
+    fn yaml(s: &str) -> Value {
+        serde_yaml::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_count_by_categories() {
+        let result = parse_and_eval(
+            "count_by(.category)",
+            "[{category: fruit}, {category: veg}, {category: fruit}, {category: dairy}]",
+        )
+        .unwrap();
+        assert_eq!(
+            result,
+            yaml("[{key: dairy, count: 1}, {key: fruit, count: 2}, {key: veg, count: 1}]")
+        );
+    }
+
+    #[test]
+    fn test_count_by_identity_in_key_order() {
+        let result = parse_and_eval("count_by(.)", "[b, 2, a, b, null, 2, b]").unwrap();
+        assert_eq!(
+            result,
+            yaml(
+                "[{key: null, count: 1}, {key: 2, count: 2}, \
+                 {key: a, count: 1}, {key: b, count: 3}]"
+            )
+        );
+    }
+
+    #[test]
+    fn test_count_by_with_target() {
+        let result =
+            parse_and_eval("count_by(.items; length)", "items: [[1], [2], [1, 2]]").unwrap();
+        assert_eq!(result, yaml("[{key: 1, count: 2}, {key: 2, count: 1}]"));
+    }
+
+    #[test]
+    fn test_count_by_missing_and_multiple_keys() {
+        let result = parse_and_eval(
+            "count_by(.tags[])",
+            "[{tags: [a]}, {tags: []}, {tags: [a, b]}]",
+        )
+        .unwrap();
+        assert_eq!(
+            result,
+            yaml("[{key: [], count: 1}, {key: a, count: 1}, {key: [a, b], count: 1}]")
+        );
+    }
+
+    #[test]
+    fn test_count_by_empty_and_errors() {
+        assert_eq!(parse_and_eval("count_by(.)", "[]").unwrap(), yaml("[]"));
+        assert!(parse_and_eval("count_by(.)", "{a: 1}").is_err());
+    }
+}
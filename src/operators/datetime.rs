@@ -0,0 +1,378 @@
+//! Date and time functions (now, todate, fromdate, strftime, gmtime,
+//! mktime, strptime)
+//!
+//! Times are Unix timestamps in seconds, interpreted as UTC, or jq's
+//! "broken down" arrays of `[year, month (0-11), day of month (1-31),
+//! hours, minutes, seconds, day of week (0-6, Sunday is 0), day of year
+//! (0-365)]`.
+
+use crate::evaluator::{Context, Evaluator, helpers};
+use crate::parser::expression::Expression;
+use anyhow::{Result, anyhow};
+use chrono::format::{Item, Parsed, StrftimeItems};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Timelike, Utc};
+use serde_yaml::Value;
+
+/// The format of `todate` and `fromdate`
+const ISO8601: &str = "%Y-%m-%dT%H:%M:%SZ";
+
+/// Evaluate now function - the current time as a (fractional) timestamp
+pub fn eval_now() -> Result<Value> {
+    let now = Utc::now();
+    let secs = now.timestamp() as f64 + f64::from(now.timestamp_subsec_micros()) / 1e6;
+    Ok(Value::Number(secs.into()))
+}
+
+/// Evaluate todate function - a timestamp (or broken down time) as an
+/// ISO 8601 string
+pub fn eval_todate(ctx: &Context) -> Result<Value> {
+    format_time(&ctx.value, "todate", ISO8601)
+}
+
+/// Evaluate fromdate function - an ISO 8601 string as a timestamp
+pub fn eval_fromdate(ctx: &Context) -> Result<Value> {
+    let Value::String(s) = &ctx.value else {
+        return Err(anyhow!(
+            "fromdate requires a string, got {}",
+            helpers::value_type(&ctx.value)
+        ));
+    };
+    let time = NaiveDateTime::parse_from_str(s, ISO8601)
+        .map_err(|_| anyhow!("date \"{}\" does not match format \"{}\"", s, ISO8601))?;
+    Ok(Value::Number(time.and_utc().timestamp().into()))
+}
+
+/// Evaluate strftime function - format a timestamp (or broken down time)
+/// with a strftime format
+pub fn eval_strftime(evaluator: &Evaluator, format: &Expression, ctx: &Context) -> Result<Value> {
+    let format = match evaluator.eval(format, ctx)? {
+        Value::String(s) => s,
+        other => {
+            return Err(anyhow!(
+                "strftime format must be a string, got {}",
+                helpers::value_type(&other)
+            ));
+        }
+    };
+    format_time(&ctx.value, "strftime", &format)
+}
+
+/// Evaluate gmtime function - a timestamp as a broken down time
+pub fn eval_gmtime(ctx: &Context) -> Result<Value> {
+    let time = timestamp(&ctx.value, "gmtime")?;
+    Ok(broken_down(&time))
+}
+
+/// Evaluate mktime function - a broken down time as a timestamp
+///
+/// Fields out of range carry over as in C's `timegm`, so month 12 is
+/// January of the next year; the day of week and day of year are ignored.
+pub fn eval_mktime(ctx: &Context) -> Result<Value> {
+    let time = from_broken_down(&ctx.value, "mktime")?;
+    Ok(Value::Number(time.timestamp().into()))
+}
+
+/// Evaluate strptime function - parse a string into a broken down time
+///
+/// Missing time fields default to midnight, and a parsed UTC offset
+/// (`%z`) is applied so the result is in UTC.
+pub fn eval_strptime(evaluator: &Evaluator, format: &Expression, ctx: &Context) -> Result<Value> {
+    let format = match evaluator.eval(format, ctx)? {
+        Value::String(s) => s,
+        other => {
+            return Err(anyhow!(
+                "strptime format must be a string, got {}",
+                helpers::value_type(&other)
+            ));
+        }
+    };
+    let Value::String(s) = &ctx.value else {
+        return Err(anyhow!(
+            "strptime requires a string, got {}",
+            helpers::value_type(&ctx.value)
+        ));
+    };
+
+    let no_match = || anyhow!("date \"{}\" does not match format \"{}\"", s, format);
+    let mut parsed = Parsed::new();
+    chrono::format::parse(&mut parsed, s, StrftimeItems::new(&format)).map_err(|_| no_match())?;
+    let date = parsed.to_naive_date().map_err(|_| no_match())?;
+    let time = parsed.to_naive_time().unwrap_or(NaiveTime::MIN);
+    let mut time = date.and_time(time).and_utc();
+    if let Some(offset) = parsed.offset {
+        time -= TimeDelta::seconds(offset.into());
+    }
+    Ok(broken_down(&time))
+}
+
+/// Split a time into jq's broken down array
+fn broken_down(time: &DateTime<Utc>) -> Value {
+    let nanos = time.timestamp_subsec_nanos();
+    let seconds = if nanos == 0 {
+        Value::Number(time.second().into())
+    } else {
+        Value::Number((f64::from(time.second()) + f64::from(nanos) / 1e9).into())
+    };
+    Value::Sequence(vec![
+        Value::Number(time.year().into()),
+        Value::Number(time.month0().into()),
+        Value::Number(time.day().into()),
+        Value::Number(time.hour().into()),
+        Value::Number(time.minute().into()),
+        seconds,
+        Value::Number(time.weekday().num_days_from_sunday().into()),
+        Value::Number(time.ordinal0().into()),
+    ])
+}
+
+/// Assemble a time from the first six fields of a broken down array
+fn from_broken_down(value: &Value, func: &str) -> Result<DateTime<Utc>> {
+    let requirement = || anyhow!("{} requires an array of at least 6 numbers", func);
+    let Value::Sequence(fields) = value else {
+        return Err(requirement());
+    };
+    let mut numbers = [0.0; 6];
+    for (number, field) in numbers.iter_mut().zip(fields) {
+        *number = field.as_f64().ok_or_else(requirement)?;
+    }
+    if fields.len() < 6 {
+        return Err(requirement());
+    }
+
+    let [year, month, day, hours, minutes, seconds] = numbers.map(|n| n.floor() as i64);
+    let out_of_range = || anyhow!("{} time is out of range", func);
+    let months = year
+        .checked_mul(12)
+        .and_then(|months| months.checked_add(month))
+        .ok_or_else(out_of_range)?;
+    let first = i32::try_from(months.div_euclid(12))
+        .ok()
+        .and_then(|year| NaiveDate::from_ymd_opt(year, months.rem_euclid(12) as u32 + 1, 1))
+        .ok_or_else(out_of_range)?;
+    let offset = [
+        day.checked_sub(1).and_then(TimeDelta::try_days),
+        TimeDelta::try_hours(hours),
+        TimeDelta::try_minutes(minutes),
+        TimeDelta::try_seconds(seconds),
+    ]
+    .into_iter()
+    .try_fold(TimeDelta::zero(), |total, part| total.checked_add(&part?))
+    .ok_or_else(out_of_range)?;
+    first
+        .and_time(NaiveTime::MIN)
+        .and_utc()
+        .checked_add_signed(offset)
+        .ok_or_else(out_of_range)
+}
+
+/// Format `value`, a timestamp or broken down time, as UTC
+fn format_time(value: &Value, func: &str, format: &str) -> Result<Value> {
+    let time = match value {
+        Value::Sequence(_) => from_broken_down(value, func)?,
+        _ => timestamp(value, func)?,
+    };
+    let items: Vec<Item> = StrftimeItems::new(format).collect();
+    // chrono panics when displaying an invalid specifier, so check first
+    if items.contains(&Item::Error) {
+        return Err(anyhow!("Invalid {} format: {}", func, format));
+    }
+    Ok(Value::String(
+        time.format_with_items(items.iter()).to_string(),
+    ))
+}
+
+/// Convert a numeric timestamp to a UTC time
+fn timestamp(value: &Value, func: &str) -> Result<DateTime<Utc>> {
+    let Value::Number(n) = value else {
+        return Err(anyhow!(
+            "{} requires a number, got {}",
+            func,
+            helpers::value_type(value)
+        ));
+    };
+    let time = match n.as_i64() {
+        Some(secs) => DateTime::from_timestamp(secs, 0),
+        None => {
+            let secs = n.as_f64().unwrap_or(f64::NAN);
+            if secs.is_finite() {
+                let whole = secs.floor();
+                let nanos = ((secs - whole) * 1e9) as u32;
+                DateTime::from_timestamp(whole as i64, nanos)
+            } else {
+                None
+            }
+        }
+    };
+    time.ok_or_else(|| anyhow!("Timestamp {} is out of range", n))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval(expr_str: &str, input: &str) -> Result<Value> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate(&expr, Some(&input_val))
+    }
+
+    #[test]
+    fn test_todate() {
+        let result = parse_and_eval("todate", "1425599621").unwrap();
+        assert_eq!(result, "2015-03-05T23:53:41Z");
+        let result = parse_and_eval("todateiso8601", "0").unwrap();
+        assert_eq!(result, "1970-01-01T00:00:00Z");
+    }
+
+    #[test]
+    fn test_todate_truncates_fractional_seconds() {
+        let result = parse_and_eval("todate", "1425599621.75").unwrap();
+        assert_eq!(result, "2015-03-05T23:53:41Z");
+    }
+
+    #[test]
+    fn test_fromdate() {
+        let result = parse_and_eval("fromdate", "'2015-03-05T23:53:41Z'").unwrap();
+        assert_eq!(result, 1425599621);
+        let result = parse_and_eval("fromdateiso8601", "'1969-12-31T23:59:59Z'").unwrap();
+        assert_eq!(result, -1);
+    }
+
+    #[test]
+    fn test_todate_fromdate_round_trip() {
+        let result = parse_and_eval("todate | fromdate", "1700000000").unwrap();
+        assert_eq!(result, 1700000000);
+    }
+
+    #[test]
+    fn test_fromdate_invalid() {
+        let err = parse_and_eval("fromdate", "'2015-03-05'").unwrap_err();
+        assert!(err.to_string().contains("does not match format"), "{}", err);
+        assert!(parse_and_eval("fromdate", "'2015-02-30T00:00:00Z'").is_err());
+        assert!(parse_and_eval("fromdate", "1").is_err());
+    }
+
+    #[test]
+    fn test_strftime() {
+        let result = parse_and_eval(r#"strftime("%A, %B %d, %Y")"#, "1425599621").unwrap();
+        assert_eq!(result, "Thursday, March 05, 2015");
+        let result = parse_and_eval(r#"strftime("%H:%M")"#, "1425599621").unwrap();
+        assert_eq!(result, "23:53");
+    }
+
+    #[test]
+    fn test_strftime_errors() {
+        assert!(parse_and_eval(r#"strftime("%Q")"#, "0").is_err());
+        assert!(parse_and_eval(r#"strftime(1)"#, "0").is_err());
+        assert!(parse_and_eval(r#"strftime("%Y")"#, "'2015'").is_err());
+        assert!(parse_and_eval("todate", "1e20").is_err());
+    }
+
+    #[test]
+    fn test_gmtime() {
+        let result = parse_and_eval("gmtime", "1425599621").unwrap();
+        let expected: Value = serde_yaml::from_str("[2015, 2, 5, 23, 53, 41, 4, 63]").unwrap();
+        assert_eq!(result, expected);
+        let result = parse_and_eval("gmtime | .[5]", "1425599621.5").unwrap();
+        assert_eq!(result, 41.5);
+    }
+
+    #[test]
+    fn test_gmtime_mktime_round_trip() {
+        for ts in ["1425599621", "0", "-86401", "4102444800"] {
+            let result = parse_and_eval("gmtime | mktime", ts).unwrap();
+            assert_eq!(result, serde_yaml::from_str::<Value>(ts).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_mktime_jq_layout() {
+        let result = parse_and_eval("mktime", "[2015, 2, 5, 23, 51, 47, 4, 63]").unwrap();
+        assert_eq!(result, 1425599507);
+    }
+
+    #[test]
+    fn test_mktime_normalizes_fields() {
+        // Month 12 of 2014 is January 2015; day 0 is the last day before
+        let result = parse_and_eval("mktime | todate", "[2014, 12, 0, 0, 0, 0]").unwrap();
+        assert_eq!(result, "2014-12-31T00:00:00Z");
+        let result = parse_and_eval("mktime | todate", "[2015, 0, 1, 0, 0, 90, 0, 0]").unwrap();
+        assert_eq!(result, "2015-01-01T00:01:30Z");
+    }
+
+    #[test]
+    fn test_mktime_errors() {
+        assert!(parse_and_eval("mktime", "[1, 2, 3]").is_err());
+        assert!(parse_and_eval("mktime", "[x, 0, 1, 0, 0, 0]").is_err());
+        assert!(parse_and_eval("mktime", "1425599621").is_err());
+    }
+
+    #[test]
+    fn test_mktime_huge_fields_are_out_of_range() {
+        for input in [
+            "[1e300, 0, 1, 0, 0, 0]",
+            "[1970, 1e300, 1, 0, 0, 0]",
+            "[1970, 0, 1e300, 0, 0, 0]",
+            "[1970, 0, 1, 0, 0, 1e300]",
+            "[1970, 0, -1e300, 0, 0, 0]",
+            "[-1e300, -1e300, 1, 0, 0, 0]",
+        ] {
+            let err = parse_and_eval("mktime", input).unwrap_err();
+            assert_eq!(err.to_string(), "mktime time is out of range", "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_strptime() {
+        let result = parse_and_eval(
+            r#"strptime("%Y-%m-%dT%H:%M:%SZ")"#,
+            "'2015-03-05T23:53:41Z'",
+        )
+        .unwrap();
+        let expected: Value = serde_yaml::from_str("[2015, 2, 5, 23, 53, 41, 4, 63]").unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_strptime_date_only_and_offset() {
+        let result = parse_and_eval(r#"strptime("%d/%m/%Y") | mktime"#, "'05/03/2015'").unwrap();
+        assert_eq!(result, 1425513600);
+        let result = parse_and_eval(
+            r#"strptime("%Y-%m-%d %H:%M %z") | todate"#,
+            "'2015-03-05 12:00 +0200'",
+        )
+        .unwrap();
+        assert_eq!(result, "2015-03-05T10:00:00Z");
+    }
+
+    #[test]
+    fn test_strptime_errors() {
+        let err = parse_and_eval(r#"strptime("%Y-%m-%d")"#, "'March 5'").unwrap_err();
+        assert!(err.to_string().contains("does not match format"), "{}", err);
+        assert!(parse_and_eval(r#"strptime("%Y-%m-%d")"#, "20150305").is_err());
+    }
+
+    #[test]
+    fn test_strftime_broken_down_time() {
+        let result = parse_and_eval(
+            r#"strftime("%Y-%m-%d %H:%M:%S")"#,
+            "[2015, 2, 5, 23, 53, 41, 4, 63]",
+        )
+        .unwrap();
+        assert_eq!(result, "2015-03-05 23:53:41");
+        let result = parse_and_eval("todate", "[2015, 2, 5, 23, 53, 41, 4, 63]").unwrap();
+        assert_eq!(result, "2015-03-05T23:53:41Z");
+    }
+
+    #[test]
+    fn test_now() {
+        let Value::Number(now) = parse_and_eval("now", "null").unwrap() else {
+            panic!("now is not a number");
+        };
+        // Some time after 2020
+        assert!(now.as_f64().unwrap() > 1_577_836_800.0);
+    }
+}
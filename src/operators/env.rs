@@ -6,8 +6,12 @@ use anyhow::Result;
 use serde_yaml::Value;
 use std::env;
 
-/// Evaluate env function - read environment variable
-pub fn eval(evaluator: &Evaluator, name: &Expression, ctx: &Context) -> Result<Value> {
+/// Evaluate env function - read one environment variable, or all of them when no name is given
+pub fn eval(evaluator: &Evaluator, name: Option<&Expression>, ctx: &Context) -> Result<Value> {
+    let Some(name) = name else {
+        return Ok(env_object());
+    };
+
     let name_val = evaluator.eval(name, ctx)?;
 
     if let Value::String(name_str) = name_val {
@@ -19,3 +23,22 @@ pub fn eval(evaluator: &Evaluator, name: &Expression, ctx: &Context) -> Result<V
         Ok(Value::Null)
     }
 }
+
+/// Build an object of all environment variables (the value of `env` and `$ENV`)
+pub fn env_object() -> Value {
+    let mut vars: Vec<(String, String)> = env::vars_os()
+        .map(|(k, v)| {
+            (
+                k.to_string_lossy().into_owned(),
+                v.to_string_lossy().into_owned(),
+            )
+        })
+        .collect();
+    vars.sort();
+
+    let mut map = serde_yaml::Mapping::new();
+    for (k, v) in vars {
+        map.insert(Value::String(k), Value::String(v));
+    }
+    Value::Mapping(map)
+}
@@ -14,13 +14,13 @@ pub fn eval(
 ) -> Result<Value> {
     let target_val = evaluator.eval(target, ctx)?;
 
+    // Missing fields and fields of null are null, as in jq
     match &target_val {
         Value::Mapping(map) => {
             let key = Value::String(field.to_string());
-            map.get(&key)
-                .cloned()
-                .ok_or_else(|| anyhow!("Field '{}' not found", field))
+            Ok(map.get(&key).cloned().unwrap_or(Value::Null))
         }
+        Value::Null => Ok(Value::Null),
         _ => Err(anyhow!("Cannot access field '{}' on non-object", field)),
     }
 }
@@ -53,14 +53,20 @@ mod tests {
 
     #[test]
     fn test_field_access_missing() {
-        let result = parse_and_eval(".missing", "name: test");
-        assert!(result.is_err());
-        assert!(
-            result
-                .unwrap_err()
-                .to_string()
-                .contains("Field 'missing' not found")
-        );
+        let result = parse_and_eval(".missing", "name: test").unwrap();
+        assert_eq!(result, Value::Null);
+    }
+
+    #[test]
+    fn test_field_access_missing_with_default() {
+        let result = parse_and_eval(".missing // \"x\"", "name: test").unwrap();
+        assert_eq!(result, "x");
+    }
+
+    #[test]
+    fn test_field_access_on_null() {
+        let result = parse_and_eval(".a.b", "{}").unwrap();
+        assert_eq!(result, Value::Null);
     }
 
     #[test]
@@ -117,4 +123,14 @@ mod tests {
         let result = parse_and_eval(".my_field", "my_field: value").unwrap();
         assert_eq!(result, "value");
     }
+
+    #[test]
+    fn test_field_access_special_characters() {
+        let input = "{'weird.key': 1, 'a b': 2, '1st': 3, foo: {'bar.baz': 4}}";
+        assert_eq!(parse_and_eval(r#".["weird.key"]"#, input).unwrap(), 1);
+        assert_eq!(parse_and_eval(r#"."a b""#, input).unwrap(), 2);
+        assert_eq!(parse_and_eval(r#".["1st"]"#, input).unwrap(), 3);
+        assert_eq!(parse_and_eval(r#".foo["bar.baz"]"#, input).unwrap(), 4);
+        assert_eq!(parse_and_eval(r#".foo."bar.baz""#, input).unwrap(), 4);
+    }
 }
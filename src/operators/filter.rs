@@ -32,3 +32,35 @@ pub fn eval(
         )),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval(expr_str: &str, input: &str) -> Result<Value> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate(&expr, Some(&input_val))
+    }
+
+    #[test]
+    fn test_filter_single_argument_on_piped_input() {
+        let result = parse_and_eval(
+            ".items | filter(.active)",
+            "items: [{n: 1, active: true}, {n: 2, active: false}, {n: 3}]",
+        )
+        .unwrap();
+        let expected: Value = serde_yaml::from_str("[{n: 1, active: true}]").unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_filter_two_arguments() {
+        let result = parse_and_eval("filter(.items; . > 1)", "items: [1, 2, 3]").unwrap();
+        let expected: Value = serde_yaml::from_str("[2, 3]").unwrap();
+        assert_eq!(result, expected);
+    }
+}
@@ -1,22 +1,92 @@
-//! First function
+//! First function (`first(f)`)
+//!
+//! The bare `first` is `.[0]`.
 
-use crate::evaluator::{Context, Evaluator};
+use crate::evaluator::{Context, Evaluator, ValueStream};
 use crate::parser::expression::Expression;
-use anyhow::{Result, anyhow};
+use anyhow::Result;
 use serde_yaml::Value;
 
-/// Evaluate first function
+/// Evaluate first function - the first output of `expr`, or null if there
+/// is none
 pub fn eval(evaluator: &Evaluator, expr: &Expression, ctx: &Context) -> Result<Value> {
-    let val = evaluator.eval(expr, ctx)?;
-
-    match val {
-        Value::Sequence(arr) => arr
-            .into_iter()
-            .next()
-            .ok_or_else(|| anyhow!("Cannot get first of empty array")),
-        _ => Err(anyhow!(
-            "Cannot get first of {}",
-            crate::evaluator::helpers::value_type(&val)
-        )),
+    Ok(eval_multi(evaluator, expr, ctx)?
+        .into_iter()
+        .next()
+        .unwrap_or(Value::Null))
+}
+
+/// Produce the first output of `expr`, if there is one
+pub fn eval_multi(evaluator: &Evaluator, expr: &Expression, ctx: &Context) -> Result<Vec<Value>> {
+    eval_lazy(evaluator, expr, ctx)?.collect()
+}
+
+/// Produce the first output of `expr` without evaluating the rest
+pub fn eval_lazy<'a>(
+    evaluator: &'a Evaluator,
+    expr: &'a Expression,
+    ctx: &Context,
+) -> Result<ValueStream<'a>> {
+    Ok(Box::new(evaluator.eval_lazy(expr, ctx)?.take(1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+    use std::time::{Duration, Instant};
+
+    fn parse_and_eval_multi(expr_str: &str, input: &str) -> Result<Vec<Value>> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate_multi(&expr, Some(&input_val))
+    }
+
+    fn yaml(s: &str) -> Value {
+        serde_yaml::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_first_output() {
+        let result = parse_and_eval_multi("first(.[] | select(. > 1))", "[1, 2, 3]").unwrap();
+        assert_eq!(result, vec![yaml("2")]);
+        let result = parse_and_eval_multi("first(.a, .b)", "{a: x, b: y}").unwrap();
+        assert_eq!(result, vec![yaml("x")]);
+    }
+
+    #[test]
+    fn test_first_of_nothing() {
+        assert!(
+            parse_and_eval_multi("first(empty)", "null")
+                .unwrap()
+                .is_empty()
+        );
+        let result = parse_and_eval_multi("[first(.[])]", "[]").unwrap();
+        assert_eq!(result, vec![yaml("[]")]);
+    }
+
+    #[test]
+    fn test_bare_first_is_index_zero() {
+        let result = parse_and_eval_multi("first", "[3, 4]").unwrap();
+        assert_eq!(result, vec![yaml("3")]);
+        assert!(parse_and_eval_multi("first", "[]").is_err());
+    }
+
+    #[test]
+    fn test_first_stops_a_huge_range() {
+        let start = Instant::now();
+        let result =
+            parse_and_eval_multi("first(range(1000000000) | select(. == 5))", "null").unwrap();
+        assert_eq!(result, vec![yaml("5")]);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_first_ignores_later_errors() {
+        let result = parse_and_eval_multi("first(1, tonumber)", "'x'").unwrap();
+        assert_eq!(result, vec![yaml("1")]);
+        assert!(parse_and_eval_multi("first(tonumber, 1)", "'x'").is_err());
     }
 }
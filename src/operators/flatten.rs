@@ -2,7 +2,7 @@
 
 use crate::evaluator::{Context, Evaluator, helpers};
 use crate::parser::expression::Expression;
-use anyhow::{Result, anyhow};
+use anyhow::Result;
 use serde_yaml::Value;
 
 /// Evaluate flatten function
@@ -21,9 +21,6 @@ pub fn eval(evaluator: &Evaluator, target: &Expression, ctx: &Context) -> Result
             }
             Ok(Value::Sequence(result))
         }
-        _ => Err(anyhow!(
-            "Cannot flatten {}",
-            helpers::value_type(&target_val)
-        )),
+        _ => Err(helpers::not_an_array("flatten", "flatten", &target_val)),
     }
 }
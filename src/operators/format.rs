@@ -0,0 +1,280 @@
+//! Format strings (@uri, @sh, @html, @base32, @base32d)
+
+use crate::evaluator::{Context, Evaluator, helpers};
+use crate::parser::expression::Expression;
+use anyhow::{Result, anyhow};
+use serde_yaml::Value;
+
+/// Evaluate a format string filter such as `@uri`
+pub fn eval(evaluator: &Evaluator, target: &Expression, fmt: &str, ctx: &Context) -> Result<Value> {
+    let target_val = evaluator.eval(target, ctx)?;
+
+    match fmt {
+        "uri" => uri(&target_val),
+        "sh" => sh(&target_val),
+        "html" => html(&target_val),
+        "base32" => base32(&target_val),
+        "base32d" => base32d(&target_val),
+        _ => Err(anyhow!("{} is not a valid format", fmt)),
+    }
+}
+
+/// Percent-encode every byte except RFC 3986 unreserved characters
+fn uri(value: &Value) -> Result<Value> {
+    let Value::String(s) = value else {
+        return Err(anyhow!(
+            "Cannot URI-encode {}, as it is not a string",
+            helpers::value_type(value)
+        ));
+    };
+
+    let mut encoded = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    Ok(Value::String(encoded))
+}
+
+/// Quote a string, or each element of an array, for use in a shell command
+///
+/// Strings are single-quoted with embedded quotes written as `'\''`; other
+/// scalars are written as-is. Array elements are joined with spaces.
+fn sh(value: &Value) -> Result<Value> {
+    let quoted = match value {
+        Value::Sequence(arr) => arr
+            .iter()
+            .map(sh_word)
+            .collect::<Result<Vec<_>>>()?
+            .join(" "),
+        other => sh_word(other)?,
+    };
+    Ok(Value::String(quoted))
+}
+
+fn sh_word(value: &Value) -> Result<String> {
+    match value {
+        Value::String(s) => Ok(format!("'{}'", s.replace('\'', "'\\''"))),
+        Value::Tagged(tagged) => sh_word(&tagged.value),
+        Value::Sequence(_) | Value::Mapping(_) => Err(anyhow!(
+            "{} cannot be escaped for shell",
+            helpers::value_type(value)
+        )),
+        other => Ok(helpers::value_to_string(other)),
+    }
+}
+
+/// Escape `<`, `>`, `&`, `'` and `"` as HTML entities
+///
+/// Other scalars are converted to strings first.
+fn html(value: &Value) -> Result<Value> {
+    if matches!(value, Value::Sequence(_) | Value::Mapping(_)) {
+        return Err(anyhow!(
+            "{} cannot be HTML-escaped",
+            helpers::value_type(value)
+        ));
+    }
+
+    let mut escaped = String::new();
+    for c in helpers::value_to_string(value).chars() {
+        match c {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '\'' => escaped.push_str("&#39;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    Ok(Value::String(escaped))
+}
+
+/// The RFC 4648 base32 alphabet
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Encode the UTF-8 bytes of a string as RFC 4648 base32, padded with `=`
+///
+/// Other scalars are converted to strings first.
+fn base32(value: &Value) -> Result<Value> {
+    if matches!(value, Value::Sequence(_) | Value::Mapping(_)) {
+        return Err(anyhow!(
+            "{} cannot be base32-encoded",
+            helpers::value_type(value)
+        ));
+    }
+
+    let bytes = helpers::value_to_string(value).into_bytes();
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    for chunk in bytes.chunks(5) {
+        let mut block = [0u8; 5];
+        block[..chunk.len()].copy_from_slice(chunk);
+        let bits = block.iter().fold(0u64, |acc, &b| (acc << 8) | u64::from(b));
+        // Each input byte contributes 8 bits, so 1-5 bytes need 2, 4, 5, 7
+        // or 8 characters; the rest of the 8-character group is padding
+        let chars = (chunk.len() * 8).div_ceil(5);
+        for i in 0..8 {
+            if i < chars {
+                let index = (bits >> (35 - i * 5)) & 0x1f;
+                encoded.push(BASE32_ALPHABET[index as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    Ok(Value::String(encoded))
+}
+
+/// Decode an RFC 4648 base32 string; the `=` padding is optional
+///
+/// Lowercase letters are accepted. Decoded bytes that are not valid UTF-8
+/// are replaced with U+FFFD.
+fn base32d(value: &Value) -> Result<Value> {
+    let Value::String(s) = value else {
+        return Err(anyhow!(
+            "Cannot base32-decode {}, as it is not a string",
+            helpers::value_type(value)
+        ));
+    };
+
+    let mut bytes = Vec::with_capacity(s.len() * 5 / 8);
+    let mut buffer = 0u64;
+    let mut bits = 0;
+    for c in s.trim_end_matches('=').chars() {
+        let index = BASE32_ALPHABET
+            .iter()
+            .position(|&a| a as char == c.to_ascii_uppercase())
+            .ok_or_else(|| anyhow!("{} is not valid base32 data", s))?;
+        buffer = (buffer << 5) | index as u64;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Ok(Value::String(String::from_utf8_lossy(&bytes).into_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval(expr_str: &str, input: &str) -> Result<Value> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate(&expr, Some(&input_val))
+    }
+
+    #[test]
+    fn test_uri_encodes_spaces_and_slashes() {
+        let result = parse_and_eval("@uri", "'a b/c?d=e&f'").unwrap();
+        assert_eq!(result, "a%20b%2Fc%3Fd%3De%26f");
+    }
+
+    #[test]
+    fn test_uri_keeps_unreserved() {
+        let result = parse_and_eval("@uri", "AZaz09-_.~").unwrap();
+        assert_eq!(result, "AZaz09-_.~");
+    }
+
+    #[test]
+    fn test_uri_encodes_utf8_bytes() {
+        let result = parse_and_eval("@uri", "'é✓'").unwrap();
+        assert_eq!(result, "%C3%A9%E2%9C%93");
+    }
+
+    #[test]
+    fn test_uri_non_string_error() {
+        assert!(parse_and_eval("@uri", "1").is_err());
+        assert!(parse_and_eval("@uri", "[a]").is_err());
+    }
+
+    #[test]
+    fn test_sh_quotes_string() {
+        let result = parse_and_eval("@sh", r#""it's here""#).unwrap();
+        assert_eq!(result, r#"'it'\''s here'"#);
+    }
+
+    #[test]
+    fn test_sh_joins_array() {
+        let result = parse_and_eval("@sh", "[a b, c, 1, true]").unwrap();
+        assert_eq!(result, "'a b' 'c' 1 true");
+    }
+
+    #[test]
+    fn test_sh_number_unquoted() {
+        assert_eq!(parse_and_eval("@sh", "42").unwrap(), "42");
+    }
+
+    #[test]
+    fn test_sh_rejects_containers() {
+        assert!(parse_and_eval("@sh", "{a: 1}").is_err());
+        assert!(parse_and_eval("@sh", "[[a]]").is_err());
+    }
+
+    #[test]
+    fn test_html_escapes_entities() {
+        let result = parse_and_eval("@html", r#"'<a>&"'"#).unwrap();
+        assert_eq!(result, "&lt;a&gt;&amp;&quot;");
+        let result = parse_and_eval("@html", r#""it's""#).unwrap();
+        assert_eq!(result, "it&#39;s");
+    }
+
+    #[test]
+    fn test_html_stringifies_scalars() {
+        assert_eq!(parse_and_eval("@html", "1").unwrap(), "1");
+        assert_eq!(parse_and_eval("@html", "null").unwrap(), "null");
+    }
+
+    #[test]
+    fn test_html_rejects_containers() {
+        assert!(parse_and_eval("@html", "[a]").is_err());
+        assert!(parse_and_eval("@html", "{a: 1}").is_err());
+    }
+
+    #[test]
+    fn test_base32_encodes_with_padding() {
+        assert_eq!(parse_and_eval("@base32", "hello").unwrap(), "NBSWY3DP");
+        assert_eq!(parse_and_eval("@base32", "f").unwrap(), "MY======");
+        assert_eq!(parse_and_eval("@base32", "foob").unwrap(), "MZXW6YQ=");
+        assert_eq!(parse_and_eval("@base32", "''").unwrap(), "");
+    }
+
+    #[test]
+    fn test_base32_round_trip() {
+        for input in ["hello", "f", "fo", "foo", "foob", "fooba", "foobar", "'é✓'"] {
+            let result = parse_and_eval("@base32 | @base32d", input).unwrap();
+            let expected: Value = serde_yaml::from_str(input).unwrap();
+            assert_eq!(result, expected, "round trip of {}", input);
+        }
+    }
+
+    #[test]
+    fn test_base32d_without_padding() {
+        assert_eq!(parse_and_eval("@base32d", "MZXW6YQ").unwrap(), "foob");
+        assert_eq!(parse_and_eval("@base32d", "mzxw6yq=").unwrap(), "foob");
+    }
+
+    #[test]
+    fn test_base32d_invalid_data() {
+        assert!(parse_and_eval("@base32d", "MZ1W").is_err());
+        assert!(parse_and_eval("@base32d", "1").is_err());
+    }
+
+    #[test]
+    fn test_base32_rejects_containers() {
+        assert!(parse_and_eval("@base32", "[a]").is_err());
+        assert!(parse_and_eval("@base32", "{a: 1}").is_err());
+    }
+
+    #[test]
+    fn test_unknown_format_error() {
+        assert!(parse_and_eval("@nope", "a").is_err());
+    }
+}
@@ -0,0 +1,249 @@
+//! User-defined functions (`def name(params): body;`)
+//!
+//! Functions are scoped lexically: the body sees the functions defined
+//! before it (and itself), and each filter argument runs in the caller's
+//! scope with the input it is given inside the body.
+
+use crate::evaluator::{Context, Evaluator, Function};
+use crate::parser::expression::Expression;
+use anyhow::{Result, anyhow};
+use serde_yaml::Value;
+use std::rc::Rc;
+
+/// The key of a function in the function table
+fn signature(name: &str, arity: usize) -> String {
+    format!("{}/{}", name, arity)
+}
+
+/// Return a context in which `name` is defined
+pub fn define(name: &str, params: &[String], body: &Expression, ctx: &Context) -> Context {
+    let function = Function {
+        params: params.to_vec(),
+        body: body.clone(),
+        env: Rc::clone(&ctx.functions),
+        recursive: true,
+    };
+    let mut ctx = ctx.clone();
+    Rc::make_mut(&mut ctx.functions).insert(signature(name, params.len()), Rc::new(function));
+    ctx
+}
+
+/// Look up `name` and build the contexts its body runs in
+///
+/// Each context keeps the input and variables of the call, with the
+/// functions of the definition plus the function itself and its arguments.
+/// A `$x` parameter binds `$x` to each output of its argument in turn, so
+/// there is one context per combination, the first parameter varying
+/// slowest; an argument with no output means the body doesn't run.
+pub fn call_contexts(
+    evaluator: &Evaluator,
+    name: &str,
+    args: &[Expression],
+    ctx: &Context,
+) -> Result<(Rc<Function>, Vec<Context>)> {
+    let key = signature(name, args.len());
+    let function = ctx
+        .functions
+        .get(&key)
+        .cloned()
+        .ok_or_else(|| anyhow!("{} is not defined", key))?;
+
+    // The definition's functions are copied only if the call adds to them
+    let mut functions = Rc::clone(&function.env);
+    if function.recursive {
+        Rc::make_mut(&mut functions).insert(key, Rc::clone(&function));
+    }
+    for (param, arg) in function.params.iter().zip(args) {
+        let closure = Function {
+            params: vec![],
+            body: arg.clone(),
+            env: Rc::clone(&ctx.functions),
+            recursive: false,
+        };
+        let param = param.trim_start_matches('$');
+        Rc::make_mut(&mut functions).insert(signature(param, 0), Rc::new(closure));
+    }
+
+    let mut call_ctx = ctx.clone();
+    call_ctx.functions = functions;
+    let mut contexts = vec![call_ctx];
+    for (param, arg) in function.params.iter().zip(args) {
+        let Some(variable) = param.strip_prefix('$') else {
+            continue;
+        };
+        let values = evaluator.eval_multi(arg, ctx)?;
+        let mut bound = Vec::with_capacity(contexts.len() * values.len());
+        for call_ctx in &contexts {
+            for value in &values {
+                let mut call_ctx = call_ctx.clone();
+                call_ctx.set_variable(variable.to_string(), value.clone());
+                bound.push(call_ctx);
+            }
+        }
+        contexts = bound;
+    }
+    Ok((function, contexts))
+}
+
+/// Evaluate a call of a user-defined function - the first output, or null
+/// if a `$x` argument produces nothing
+pub fn eval_call(
+    evaluator: &Evaluator,
+    name: &str,
+    args: &[Expression],
+    ctx: &Context,
+) -> Result<Value> {
+    let (function, contexts) = call_contexts(evaluator, name, args, ctx)?;
+    match contexts.first() {
+        Some(call_ctx) => evaluator.eval(&function.body, call_ctx),
+        None => Ok(Value::Null),
+    }
+}
+
+/// Stream every output of a call of a user-defined function
+pub fn eval_multi_call(
+    evaluator: &Evaluator,
+    name: &str,
+    args: &[Expression],
+    ctx: &Context,
+) -> Result<Vec<Value>> {
+    let (function, contexts) = call_contexts(evaluator, name, args, ctx)?;
+    let mut results = Vec::new();
+    for call_ctx in &contexts {
+        results.extend(evaluator.eval_multi(&function.body, call_ctx)?);
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval_multi(expr_str: &str, input: &str) -> Result<Vec<Value>> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate_multi(&expr, Some(&input_val))
+    }
+
+    fn yaml(s: &str) -> Value {
+        serde_yaml::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_def_without_params() {
+        let result = parse_and_eval_multi("def inc: . + 1; inc", "1").unwrap();
+        assert_eq!(result, vec![yaml("2")]);
+    }
+
+    #[test]
+    fn test_def_used_in_map() {
+        let result = parse_and_eval_multi("def inc: . + 1; map(inc)", "[1, 2]").unwrap();
+        assert_eq!(result, vec![yaml("[2, 3]")]);
+    }
+
+    #[test]
+    fn test_def_with_filter_param() {
+        let result = parse_and_eval_multi("def twice(f): f | f; twice(. * 3)", "2").unwrap();
+        assert_eq!(result, vec![yaml("18")]);
+    }
+
+    #[test]
+    fn test_def_filter_param_is_a_generator() {
+        let result = parse_and_eval_multi("def each(f): [f]; each(.[])", "[1, 2]").unwrap();
+        assert_eq!(result, vec![yaml("[1, 2]")]);
+        let result = parse_and_eval_multi("def f: .[]; f", "[1, 2]").unwrap();
+        assert_eq!(result, vec![yaml("1"), yaml("2")]);
+    }
+
+    #[test]
+    fn test_def_sequence_and_shadowing() {
+        let result =
+            parse_and_eval_multi("def one: 1; def two: one + one; [one, two]", "null").unwrap();
+        assert_eq!(result, vec![yaml("[1, 2]")]);
+        let result = parse_and_eval_multi("def length: 42; length", "[1]").unwrap();
+        assert_eq!(result, vec![yaml("42")]);
+    }
+
+    #[test]
+    fn test_filter_param_runs_in_caller_scope() {
+        let result = parse_and_eval_multi("def f: 1; def g(x): def f: 2; x; g(f)", "null").unwrap();
+        assert_eq!(result, vec![yaml("1")]);
+    }
+
+    #[test]
+    fn test_def_with_value_param() {
+        let result = parse_and_eval_multi("def addn($n): . + $n; map(addn(10))", "[1, 2]").unwrap();
+        assert_eq!(result, vec![yaml("[11, 12]")]);
+        // The parameter is a filter too
+        let result = parse_and_eval_multi("def addn($n): . + n; addn(10)", "1").unwrap();
+        assert_eq!(result, vec![yaml("11")]);
+    }
+
+    #[test]
+    fn test_def_value_param_sees_caller_input() {
+        let result = parse_and_eval_multi(
+            "def scale($f): .items | map(. * $f); scale(.k)",
+            "{k: 3, items: [1, 2]}",
+        )
+        .unwrap();
+        assert_eq!(result, vec![yaml("[3, 6]")]);
+    }
+
+    #[test]
+    fn test_def_value_params_combine() {
+        let result =
+            parse_and_eval_multi("def pair($a; $b): [$a, $b]; pair(1, 2; 3, 4)", "null").unwrap();
+        assert_eq!(
+            result,
+            vec![
+                yaml("[1, 3]"),
+                yaml("[1, 4]"),
+                yaml("[2, 3]"),
+                yaml("[2, 4]")
+            ]
+        );
+        let result = parse_and_eval_multi("def f($x): 1; f(empty)", "null").unwrap();
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn test_def_recursion() {
+        let result = parse_and_eval_multi(
+            "def fact: if . <= 1 then 1 else . * (. - 1 | fact) end; fact",
+            "5",
+        )
+        .unwrap();
+        assert_eq!(result, vec![yaml("120")]);
+        let result = parse_and_eval_multi(
+            "def count($n): if $n > 0 then $n, count($n - 1) else empty end; [count(3)]",
+            "null",
+        )
+        .unwrap();
+        assert_eq!(result, vec![yaml("[3, 2, 1]")]);
+    }
+
+    #[test]
+    fn test_def_unbounded_recursion_fails() {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::with_max_depth(64);
+        let expr = parser.parse("def f: f; f").unwrap();
+        let err = evaluator.evaluate_multi(&expr, None).unwrap_err();
+        assert!(err.to_string().contains("depth"), "{}", err);
+    }
+
+    #[test]
+    fn test_def_after_pipe() {
+        let result = parse_and_eval_multi(".a | def double: . * 2; double", "{a: 4}").unwrap();
+        assert_eq!(result, vec![yaml("8")]);
+    }
+
+    #[test]
+    fn test_def_out_of_scope() {
+        // Outside the group `inc` is a field name again
+        let result = parse_and_eval_multi("(def inc: 1; inc), inc", "{inc: 5}").unwrap();
+        assert_eq!(result, vec![yaml("1"), yaml("5")]);
+    }
+}
@@ -4,9 +4,11 @@ use crate::evaluator::{Context, Evaluator, helpers};
 use crate::parser::expression::Expression;
 use anyhow::{Result, anyhow};
 use serde_yaml::Value;
-use std::collections::HashMap;
 
 /// Evaluate group_by function
+///
+/// Sorts the array by key and returns an array of groups, each holding the
+/// elements that share a key, in key order.
 pub fn eval(
     evaluator: &Evaluator,
     target: &Expression,
@@ -16,30 +18,83 @@ pub fn eval(
     let target_val = evaluator.eval(target, ctx)?;
 
     match target_val {
-        Value::Sequence(arr) => {
-            let mut groups: HashMap<String, Vec<Value>> = HashMap::new();
+        Value::Sequence(arr) => Ok(Value::Sequence(
+            groups(evaluator, arr, key_expr, ctx)?
+                .into_iter()
+                .map(|(_, items)| Value::Sequence(items))
+                .collect(),
+        )),
+        _ => Err(anyhow!("Cannot group {}", helpers::value_type(&target_val))),
+    }
+}
 
-            for item in arr {
-                let item_ctx = ctx.child(item.clone());
-                let key_val = evaluator.eval(key_expr, &item_ctx)?;
-                let key = helpers::value_to_string(&key_val);
+/// Split `arr` into groups of elements that share a key, in key order
+///
+/// Each key is the array of every output of `key_expr` for the element;
+/// elements keep their original order within a group.
+pub fn groups(
+    evaluator: &Evaluator,
+    arr: Vec<Value>,
+    key_expr: &Expression,
+    ctx: &Context,
+) -> Result<Vec<(Value, Vec<Value>)>> {
+    let mut keyed = Vec::with_capacity(arr.len());
+    for item in arr {
+        let item_ctx = ctx.child(item.clone());
+        let key = evaluator.eval_multi(key_expr, &item_ctx)?;
+        keyed.push((Value::Sequence(key), item));
+    }
+    keyed.sort_by(|(a, _), (b, _)| helpers::compare_values(a, b));
 
-                groups.entry(key).or_default().push(item);
+    let mut groups: Vec<(Value, Vec<Value>)> = Vec::new();
+    for (key, item) in keyed {
+        match groups.last_mut() {
+            Some((group_key, items)) if helpers::compare_values(group_key, &key).is_eq() => {
+                items.push(item)
             }
-
-            // Convert to array of {key: ..., value: ...} objects
-            let result: Vec<Value> = groups
-                .into_iter()
-                .map(|(key, values)| {
-                    let mut obj = serde_yaml::Mapping::new();
-                    obj.insert(Value::String("key".to_string()), Value::String(key));
-                    obj.insert(Value::String("value".to_string()), Value::Sequence(values));
-                    Value::Mapping(obj)
-                })
-                .collect();
-
-            Ok(Value::Sequence(result))
+            _ => groups.push((key, vec![item])),
         }
-        _ => Err(anyhow!("Cannot group {}", helpers::value_type(&target_val))),
+    }
+    Ok(groups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval(expr_str: &str, input: &str) -> Result<Value> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate(&expr, Some(&input_val))
+    }
+
+    #[test]
+    fn test_group_by_single_argument_on_piped_input() {
+        let result = parse_and_eval(
+            ".items | group_by(.type)",
+            "items: [{type: b, n: 1}, {type: a, n: 2}, {type: b, n: 3}, {type: c, n: 4}]",
+        )
+        .unwrap();
+        let expected: Value = serde_yaml::from_str(
+            "[[{type: a, n: 2}], [{type: b, n: 1}, {type: b, n: 3}], [{type: c, n: 4}]]",
+        )
+        .unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_group_by_orders_mixed_keys() {
+        let result = parse_and_eval("group_by(.k)", "[{k: a}, {k: 1}, {}, {k: 1}]").unwrap();
+        let expected: Value = serde_yaml::from_str("[[{}], [{k: 1}, {k: 1}], [{k: a}]]").unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_group_by_empty_array() {
+        let result = parse_and_eval("group_by(.k)", "[]").unwrap();
+        assert_eq!(result, Value::Sequence(vec![]));
     }
 }
@@ -15,24 +15,68 @@ pub fn eval(
     let target_val = evaluator.eval(target, ctx)?;
     let key_val = evaluator.eval(key, ctx)?;
 
-    match &target_val {
-        Value::Mapping(map) => {
-            let has_key = map.contains_key(&key_val);
-            Ok(Value::Bool(has_key))
-        }
+    Ok(Value::Bool(has_key(&target_val, &key_val)))
+}
+
+/// Check whether an object has a key or an array has an index
+pub fn has_key(container: &Value, key: &Value) -> bool {
+    match container {
+        Value::Mapping(map) => map.contains_key(key),
         Value::Sequence(arr) => {
             // Check if index exists
-            if let Some(idx) = key_val.as_i64() {
+            if let Some(idx) = key.as_i64() {
                 let idx = if idx < 0 {
                     arr.len().checked_sub(idx.unsigned_abs() as usize)
                 } else {
                     Some(idx as usize)
                 };
-                Ok(Value::Bool(idx.map(|i| i < arr.len()).unwrap_or(false)))
+                idx.map(|i| i < arr.len()).unwrap_or(false)
             } else {
-                Ok(Value::Bool(false))
+                false
             }
         }
-        _ => Ok(Value::Bool(false)),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval(expr_str: &str, input: &str) -> Result<Value> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate(&expr, Some(&input_val))
+    }
+
+    #[test]
+    fn test_has_single_argument() {
+        assert_eq!(parse_and_eval("has(\"a\")", "{a: 1}").unwrap(), true);
+        assert_eq!(parse_and_eval("has(\"b\")", "{a: 1}").unwrap(), false);
+    }
+
+    #[test]
+    fn test_has_on_piped_object_literal() {
+        assert_eq!(
+            parse_and_eval("{\"a\": 1} | has(\"a\")", "null").unwrap(),
+            true
+        );
+    }
+
+    #[test]
+    fn test_has_array_index() {
+        assert_eq!(parse_and_eval("has(1)", "[x, y]").unwrap(), true);
+        assert_eq!(parse_and_eval("has(2)", "[x, y]").unwrap(), false);
+    }
+
+    #[test]
+    fn test_has_two_arguments() {
+        assert_eq!(
+            parse_and_eval("has(.obj; \"a\")", "obj: {a: 1}").unwrap(),
+            true
+        );
     }
 }
@@ -0,0 +1,56 @@
+//! Conditional (if-then-else)
+
+use crate::evaluator::{Context, Evaluator, helpers};
+use crate::parser::expression::Expression;
+use anyhow::Result;
+use serde_yaml::Value;
+
+/// Evaluate conditional
+pub fn eval(
+    evaluator: &Evaluator,
+    condition: &Expression,
+    then_branch: &Expression,
+    else_branch: &Expression,
+    ctx: &Context,
+) -> Result<Value> {
+    let condition_val = evaluator.eval(condition, ctx)?;
+
+    if helpers::is_truthy(&condition_val) {
+        evaluator.eval(then_branch, ctx)
+    } else {
+        evaluator.eval(else_branch, ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval(expr_str: &str, input: &str) -> Result<Value> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate(&expr, Some(&input_val))
+    }
+
+    #[test]
+    fn test_if_then_else() {
+        let expr = "if .n > 1 then \"big\" else \"small\" end";
+        assert_eq!(parse_and_eval(expr, "n: 5").unwrap(), "big");
+        assert_eq!(parse_and_eval(expr, "n: 0").unwrap(), "small");
+    }
+
+    #[test]
+    fn test_if_elif() {
+        let expr = "if . == 1 then \"one\" elif . == 2 then \"two\" else \"many\" end";
+        assert_eq!(parse_and_eval(expr, "2").unwrap(), "two");
+        assert_eq!(parse_and_eval(expr, "3").unwrap(), "many");
+    }
+
+    #[test]
+    fn test_if_without_else_passes_input_through() {
+        assert_eq!(parse_and_eval("if . > 5 then 0 end", "3").unwrap(), 3);
+    }
+}
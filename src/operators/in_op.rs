@@ -0,0 +1,51 @@
+//! In function
+
+use crate::evaluator::{Context, Evaluator};
+use crate::operators::has;
+use crate::parser::expression::Expression;
+use anyhow::Result;
+use serde_yaml::Value;
+
+/// Evaluate in function - whether the input is a key of the container
+pub fn eval(evaluator: &Evaluator, container: &Expression, ctx: &Context) -> Result<Value> {
+    let container_val = evaluator.eval(container, ctx)?;
+    Ok(Value::Bool(has::has_key(&container_val, &ctx.value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval(expr_str: &str, input: &str) -> Result<Value> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate(&expr, Some(&input_val))
+    }
+
+    #[test]
+    fn test_in_object() {
+        assert_eq!(
+            parse_and_eval("\"a\" | in({\"a\": 1})", "null").unwrap(),
+            true
+        );
+        assert_eq!(
+            parse_and_eval("\"b\" | in({\"a\": 1})", "null").unwrap(),
+            false
+        );
+    }
+
+    #[test]
+    fn test_in_array() {
+        assert_eq!(
+            parse_and_eval("1 | in([\"x\", \"y\"])", "null").unwrap(),
+            true
+        );
+        assert_eq!(
+            parse_and_eval("2 | in([\"x\", \"y\"])", "null").unwrap(),
+            false
+        );
+    }
+}
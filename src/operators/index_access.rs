@@ -1,6 +1,7 @@
 //! Index access operator (.[index])
 
-use crate::evaluator::{Context, Evaluator};
+use super::field_access;
+use crate::evaluator::{Context, Evaluator, helpers};
 use crate::parser::expression::Expression;
 use anyhow::{Result, anyhow};
 use serde_yaml::Value;
@@ -31,6 +32,29 @@ pub fn eval(
     }
 }
 
+/// Evaluate computed index access (`.[expr]`)
+///
+/// The index expression is evaluated against the input: a string result
+/// is used as an object key and a number as an array index.
+pub fn eval_dynamic(
+    evaluator: &Evaluator,
+    target: &Expression,
+    index: &Expression,
+    ctx: &Context,
+) -> Result<Value> {
+    match evaluator.eval(index, ctx)? {
+        Value::String(field) => field_access::eval(evaluator, target, &field, ctx),
+        Value::Number(n) => {
+            let index = n
+                .as_i64()
+                .or_else(|| n.as_f64().map(|f| f.floor() as i64))
+                .ok_or_else(|| anyhow!("Invalid index: {}", n))?;
+            eval(evaluator, target, index as isize, ctx)
+        }
+        other => Err(anyhow!("Cannot index with {}", helpers::value_type(&other))),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -112,4 +136,23 @@ mod tests {
         let result = parse_and_eval(".items[0]", "items:\n  - first\n  - second").unwrap();
         assert_eq!(result, "first");
     }
+
+    #[test]
+    fn test_dynamic_index_string_key() {
+        let result = parse_and_eval(".obj[.keyfield]", "{keyfield: b, obj: {a: 1, b: 2}}").unwrap();
+        assert_eq!(result, 2);
+    }
+
+    #[test]
+    fn test_dynamic_index_number() {
+        let result = parse_and_eval(".items[.i]", "{i: 1, items: [a, b, c]}").unwrap();
+        assert_eq!(result, "b");
+        let result = parse_and_eval(".[1 + 1]", "[a, b, c]").unwrap();
+        assert_eq!(result, "c");
+    }
+
+    #[test]
+    fn test_dynamic_index_invalid_key() {
+        assert!(parse_and_eval(".[.k]", "{k: [1]}").is_err());
+    }
 }
@@ -0,0 +1,51 @@
+//! String interpolation ("\(expr)")
+
+use crate::evaluator::{Context, Evaluator, helpers};
+use crate::parser::expression::Expression;
+use anyhow::Result;
+use serde_yaml::Value;
+
+/// Evaluate string interpolation - concatenate the parts into one string
+///
+/// Interpolated strings are inserted as-is; other values are written as
+/// JSON, so `"\([1, 2])"` is `"[1,2]"`.
+pub fn eval(evaluator: &Evaluator, parts: &[Expression], ctx: &Context) -> Result<Value> {
+    let mut result = String::new();
+    for part in parts {
+        let value = evaluator.eval(part, ctx)?;
+        result.push_str(&helpers::value_to_string(&value));
+    }
+    Ok(Value::String(result))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval(expr_str: &str, input: &str) -> Result<Value> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate(&expr, Some(&input_val))
+    }
+
+    #[test]
+    fn test_interpolate_fields() {
+        let result = parse_and_eval(r#""\(.name) is \(.age)""#, "{name: Ann, age: 30}").unwrap();
+        assert_eq!(result, "Ann is 30");
+    }
+
+    #[test]
+    fn test_interpolate_containers_as_json() {
+        let result = parse_and_eval(r#""v=\(.)""#, "{a: [1, null]}").unwrap();
+        assert_eq!(result, r#"v={"a":[1,null]}"#);
+    }
+
+    #[test]
+    fn test_interpolate_nested_expression() {
+        let result = parse_and_eval(r#""\(.a + 1)\("x")""#, "{a: 1}").unwrap();
+        assert_eq!(result, "2x");
+    }
+}
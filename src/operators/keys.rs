@@ -1,27 +1,34 @@
 //! Keys function
 
-use crate::evaluator::{Context, Evaluator};
+use crate::evaluator::{Context, Evaluator, helpers};
 use crate::parser::expression::Expression;
 use anyhow::{Result, anyhow};
 use serde_yaml::Value;
 
-/// Evaluate keys function
+/// Evaluate keys function - object keys in sorted order
 pub fn eval(evaluator: &Evaluator, target: &Expression, ctx: &Context) -> Result<Value> {
+    let mut keys = collect_keys(evaluator, target, ctx)?;
+    keys.sort_by(helpers::compare_values);
+    Ok(Value::Sequence(keys))
+}
+
+/// Evaluate keys_unsorted function - object keys in insertion order
+pub fn eval_unsorted(evaluator: &Evaluator, target: &Expression, ctx: &Context) -> Result<Value> {
+    Ok(Value::Sequence(collect_keys(evaluator, target, ctx)?))
+}
+
+fn collect_keys(evaluator: &Evaluator, target: &Expression, ctx: &Context) -> Result<Vec<Value>> {
     let target_val = evaluator.eval(target, ctx)?;
 
     match &target_val {
-        Value::Mapping(map) => {
-            let keys: Vec<Value> = map.keys().cloned().collect();
-            Ok(Value::Sequence(keys))
-        }
+        Value::Mapping(map) => Ok(map.keys().cloned().collect()),
         Value::Sequence(arr) => {
             // Return indices as numbers
-            let keys: Vec<Value> = (0..arr.len()).map(|i| Value::Number(i.into())).collect();
-            Ok(Value::Sequence(keys))
+            Ok((0..arr.len()).map(|i| Value::Number(i.into())).collect())
         }
         _ => Err(anyhow!(
             "Cannot get keys of {}",
-            crate::evaluator::helpers::value_type(&target_val)
+            helpers::value_type(&target_val)
         )),
     }
 }
@@ -94,4 +101,22 @@ mod tests {
         let arr = result.as_sequence().unwrap();
         assert_eq!(arr.len(), 2);
     }
+
+    #[test]
+    fn test_keys_sorted_vs_unsorted() {
+        let input = "b: 1\nc: 2\na: 3";
+        let sorted = parse_and_eval("keys", input).unwrap();
+        let unsorted = parse_and_eval("keys_unsorted", input).unwrap();
+        assert_eq!(sorted, serde_yaml::from_str::<Value>("[a, b, c]").unwrap());
+        assert_eq!(
+            unsorted,
+            serde_yaml::from_str::<Value>("[b, c, a]").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_keys_unsorted_array() {
+        let result = parse_and_eval("keys_unsorted", "[x, y]").unwrap();
+        assert_eq!(result, serde_yaml::from_str::<Value>("[0, 1]").unwrap());
+    }
 }
@@ -1,22 +1,58 @@
-//! Last function
+//! Last function (`last(f)`)
+//!
+//! The bare `last` is `.[-1]`.
 
 use crate::evaluator::{Context, Evaluator};
 use crate::parser::expression::Expression;
-use anyhow::{Result, anyhow};
+use anyhow::Result;
 use serde_yaml::Value;
 
-/// Evaluate last function
+/// Evaluate last function - the last output of `expr`, or null if there
+/// is none
 pub fn eval(evaluator: &Evaluator, expr: &Expression, ctx: &Context) -> Result<Value> {
-    let val = evaluator.eval(expr, ctx)?;
-
-    match val {
-        Value::Sequence(arr) => arr
-            .into_iter()
-            .last()
-            .ok_or_else(|| anyhow!("Cannot get last of empty array")),
-        _ => Err(anyhow!(
-            "Cannot get last of {}",
-            crate::evaluator::helpers::value_type(&val)
-        )),
+    Ok(eval_multi(evaluator, expr, ctx)?
+        .into_iter()
+        .next()
+        .unwrap_or(Value::Null))
+}
+
+/// Produce the last output of `expr`, if there is one
+pub fn eval_multi(evaluator: &Evaluator, expr: &Expression, ctx: &Context) -> Result<Vec<Value>> {
+    Ok(evaluator.eval_multi(expr, ctx)?.pop().into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval_multi(expr_str: &str, input: &str) -> Result<Vec<Value>> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate_multi(&expr, Some(&input_val))
+    }
+
+    fn yaml(s: &str) -> Value {
+        serde_yaml::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_last_output() {
+        let result = parse_and_eval_multi("last(range(5))", "null").unwrap();
+        assert_eq!(result, vec![yaml("4")]);
+        assert!(
+            parse_and_eval_multi("last(empty)", "null")
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_bare_last_is_index_minus_one() {
+        let result = parse_and_eval_multi("last", "[3, 4]").unwrap();
+        assert_eq!(result, vec![yaml("4")]);
+        assert!(parse_and_eval_multi("last", "[]").is_err());
     }
 }
@@ -0,0 +1,111 @@
+//! Limit function (`limit(n; f)`)
+
+use crate::evaluator::{Context, Evaluator, ValueStream, helpers};
+use crate::parser::expression::Expression;
+use anyhow::{Result, anyhow};
+use serde_yaml::Value;
+
+/// Evaluate limit function - the first output of `expr` if `n` allows one,
+/// or null
+pub fn eval(
+    evaluator: &Evaluator,
+    n: &Expression,
+    expr: &Expression,
+    ctx: &Context,
+) -> Result<Value> {
+    Ok(eval_multi(evaluator, n, expr, ctx)?
+        .into_iter()
+        .next()
+        .unwrap_or(Value::Null))
+}
+
+/// Produce at most `n` outputs of `expr`
+pub fn eval_multi(
+    evaluator: &Evaluator,
+    n: &Expression,
+    expr: &Expression,
+    ctx: &Context,
+) -> Result<Vec<Value>> {
+    eval_lazy(evaluator, n, expr, ctx)?.collect()
+}
+
+/// Produce at most `n` outputs of `expr` without evaluating the rest
+pub fn eval_lazy<'a>(
+    evaluator: &'a Evaluator,
+    n: &'a Expression,
+    expr: &'a Expression,
+    ctx: &Context,
+) -> Result<ValueStream<'a>> {
+    let n = count(&evaluator.eval(n, ctx)?, "limit count")?;
+    if n == 0 {
+        // Don't start `expr` at all, so its errors don't surface
+        return Ok(Box::new(std::iter::empty()));
+    }
+    Ok(Box::new(evaluator.eval_lazy(expr, ctx)?.take(n)))
+}
+
+/// `n` as a number of outputs, which `what` names in errors
+pub fn count(n: &Value, what: &str) -> Result<usize> {
+    let n = match n {
+        Value::Number(number) => number.as_f64().unwrap_or(f64::NAN),
+        other => {
+            return Err(anyhow!(
+                "{} must be a number, got {}",
+                what,
+                helpers::value_type(other)
+            ));
+        }
+    };
+    if n.is_nan() || n < 0.0 {
+        return Err(anyhow!("{} cannot be negative", what));
+    }
+    Ok(n as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval_multi(expr_str: &str, input: &str) -> Result<Vec<Value>> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate_multi(&expr, Some(&input_val))
+    }
+
+    fn yaml(s: &str) -> Value {
+        serde_yaml::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_limit() {
+        let result = parse_and_eval_multi("[limit(3; .[])]", "[1, 2, 3, 4, 5]").unwrap();
+        assert_eq!(result, vec![yaml("[1, 2, 3]")]);
+        let result = parse_and_eval_multi("[limit(10; .[])]", "[1, 2]").unwrap();
+        assert_eq!(result, vec![yaml("[1, 2]")]);
+    }
+
+    #[test]
+    fn test_limit_zero_evaluates_nothing() {
+        let result = parse_and_eval_multi("[limit(0; tonumber)]", "'x'").unwrap();
+        assert_eq!(result, vec![yaml("[]")]);
+    }
+
+    #[test]
+    fn test_limit_of_huge_range() {
+        let result = parse_and_eval_multi("[limit(3; range(1000000000) | . * 2)]", "null").unwrap();
+        assert_eq!(result, vec![yaml("[0, 2, 4]")]);
+        let result =
+            parse_and_eval_multi("[limit(2; range(1000000000), 1)] | add", "null").unwrap();
+        assert_eq!(result, vec![yaml("1")]);
+    }
+
+    #[test]
+    fn test_limit_count_errors() {
+        let err = parse_and_eval_multi("[limit(.[0]; .[])]", "[-1]").unwrap_err();
+        assert_eq!(err.to_string(), "limit count cannot be negative");
+        assert!(parse_and_eval_multi("[limit(\"2\"; .[])]", "[1]").is_err());
+    }
+}
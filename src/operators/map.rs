@@ -1,11 +1,14 @@
 //! Map function
 
-use crate::evaluator::{Context, Evaluator};
+use crate::evaluator::{Context, Evaluator, helpers};
 use crate::parser::expression::Expression;
 use anyhow::{Result, anyhow};
 use serde_yaml::Value;
 
 /// Evaluate map function
+///
+/// Equivalent to jq's `[.[] | f]`: every output of `expr` for each array
+/// element (or object value) is collected into the result array.
 pub fn eval(
     evaluator: &Evaluator,
     target: &Expression,
@@ -14,19 +17,75 @@ pub fn eval(
 ) -> Result<Value> {
     let target_val = evaluator.eval(target, ctx)?;
 
-    match target_val {
-        Value::Sequence(arr) => {
-            let mut result = Vec::new();
-            for item in arr {
-                let item_ctx = ctx.child(item);
-                let mapped = evaluator.eval(expr, &item_ctx)?;
-                result.push(mapped);
-            }
-            Ok(Value::Sequence(result))
+    let items: Vec<Value> = match target_val {
+        Value::Sequence(arr) => arr,
+        Value::Mapping(map) => map.into_iter().map(|(_, v)| v).collect(),
+        _ => {
+            return Err(anyhow!(
+                "Cannot map over {}",
+                helpers::value_type(&target_val)
+            ));
         }
-        _ => Err(anyhow!(
-            "Cannot map over {}",
-            crate::evaluator::helpers::value_type(&target_val)
-        )),
+    };
+
+    let mut result = Vec::new();
+    for item in items {
+        let item_ctx = ctx.child(item);
+        result.extend(evaluator.eval_multi(expr, &item_ctx)?);
+    }
+    Ok(Value::Sequence(result))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval(expr_str: &str, input: &str) -> Result<Value> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate(&expr, Some(&input_val))
+    }
+
+    #[test]
+    fn test_map_single_argument() {
+        let result = parse_and_eval("map(. * 2)", "[1, 2, 3]").unwrap();
+        let expected: Value = serde_yaml::from_str("[2, 4, 6]").unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_map_after_pipe() {
+        let result = parse_and_eval(".items | map(.x)", "items: [{x: 1}, {x: 2}]").unwrap();
+        let expected: Value = serde_yaml::from_str("[1, 2]").unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_map_two_arguments() {
+        let result = parse_and_eval("map(.items; . + 1)", "items: [1, 2]").unwrap();
+        let expected: Value = serde_yaml::from_str("[2, 3]").unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_map_object_values() {
+        let result = parse_and_eval("map(. + 1)", "{a: 1, b: 2}").unwrap();
+        let expected: Value = serde_yaml::from_str("[2, 3]").unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_map_select_filters() {
+        let result = parse_and_eval("map(select(. > 1))", "[1, 2, 3]").unwrap();
+        let expected: Value = serde_yaml::from_str("[2, 3]").unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_map_scalar_error() {
+        assert!(parse_and_eval("map(. + 1)", "5").is_err());
     }
 }
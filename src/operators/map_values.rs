@@ -0,0 +1,87 @@
+//! Map values function
+
+use crate::evaluator::{Context, Evaluator, helpers};
+use crate::parser::expression::Expression;
+use anyhow::{Result, anyhow};
+use serde_yaml::{Mapping, Value};
+
+/// Evaluate map_values function
+///
+/// Applies `expr` to every value of an object or element of an array and
+/// keeps the container's shape. As in jq, only the first output of `expr`
+/// is used, and entries for which it produces no output are dropped.
+pub fn eval(
+    evaluator: &Evaluator,
+    target: &Expression,
+    expr: &Expression,
+    ctx: &Context,
+) -> Result<Value> {
+    let target_val = evaluator.eval(target, ctx)?;
+
+    match target_val {
+        Value::Mapping(map) => {
+            let mut result = Mapping::new();
+            for (key, value) in map {
+                let value_ctx = ctx.child(value);
+                if let Some(mapped) = evaluator.eval_multi(expr, &value_ctx)?.into_iter().next() {
+                    result.insert(key, mapped);
+                }
+            }
+            Ok(Value::Mapping(result))
+        }
+        Value::Sequence(arr) => {
+            let mut result = Vec::new();
+            for item in arr {
+                let item_ctx = ctx.child(item);
+                if let Some(mapped) = evaluator.eval_multi(expr, &item_ctx)?.into_iter().next() {
+                    result.push(mapped);
+                }
+            }
+            Ok(Value::Sequence(result))
+        }
+        _ => Err(anyhow!(
+            "Cannot map values of {}",
+            helpers::value_type(&target_val)
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval(expr_str: &str, input: &str) -> Result<Value> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate(&expr, Some(&input_val))
+    }
+
+    #[test]
+    fn test_map_values_object() {
+        let result = parse_and_eval("map_values(. + 1)", "{a: 1, b: 2}").unwrap();
+        let expected: Value = serde_yaml::from_str("{a: 2, b: 3}").unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_map_values_array() {
+        let result = parse_and_eval("map_values(. * 10)", "[1, 2]").unwrap();
+        let expected: Value = serde_yaml::from_str("[10, 20]").unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_map_values_drops_empty_results() {
+        let result = parse_and_eval("map_values(select(. > 1))", "{a: 1, b: 2, c: 3}").unwrap();
+        let expected: Value = serde_yaml::from_str("{b: 2, c: 3}").unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_map_values_scalar_error() {
+        assert!(parse_and_eval("map_values(. + 1)", "5").is_err());
+    }
+}
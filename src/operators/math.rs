@@ -0,0 +1,228 @@
+//! Math functions (abs, signum, log, log2, log10, exp, exp2, exp10, sin,
+//! cos, tan, asin, acos, atan, atan2)
+//!
+//! Angles are in radians.
+
+use crate::evaluator::{Context, Evaluator, helpers};
+use crate::parser::expression::Expression;
+use anyhow::{Result, anyhow};
+use serde_yaml::{Number, Value};
+
+/// Evaluate abs function - absolute value, keeping integers as integers
+pub fn eval_abs(_evaluator: &Evaluator, ctx: &Context) -> Result<Value> {
+    let n = number(&ctx.value, "abs")?;
+    // i64::MIN has no integer absolute value, so it falls back to a float
+    match n.as_i64().and_then(i64::checked_abs) {
+        Some(i) => Ok(Value::Number(i.into())),
+        None => Ok(Value::Number(n.as_f64().unwrap_or(f64::NAN).abs().into())),
+    }
+}
+
+/// Evaluate signum function - -1, 0 or 1 by the sign of a number
+pub fn eval_signum(_evaluator: &Evaluator, ctx: &Context) -> Result<Value> {
+    let n = number(&ctx.value, "signum")?;
+    let sign = match n.as_i64() {
+        Some(i) => i.signum(),
+        None => match n.as_f64() {
+            Some(f) if f > 0.0 => 1,
+            Some(f) if f < 0.0 => -1,
+            _ => 0,
+        },
+    };
+    Ok(Value::Number(sign.into()))
+}
+
+/// Evaluate a libm-style unary function (`log`, `exp`, `sin`, ...) as a float
+///
+/// A result that isn't finite is an error rather than NaN or infinity,
+/// which JSON can't represent: `log` of zero or a negative number fails,
+/// and so do `exp` of a number too large for a float and `asin` outside
+/// -1 to 1.
+pub fn eval_unary(_evaluator: &Evaluator, name: &str, ctx: &Context) -> Result<Value> {
+    let f: fn(f64) -> f64 = match name {
+        "log" => f64::ln,
+        "log2" => f64::log2,
+        "log10" => f64::log10,
+        "exp" => f64::exp,
+        "exp2" => f64::exp2,
+        "exp10" => |x| 10f64.powf(x),
+        "sin" => f64::sin,
+        "cos" => f64::cos,
+        "tan" => f64::tan,
+        "asin" => f64::asin,
+        "acos" => f64::acos,
+        "atan" => f64::atan,
+        _ => return Err(anyhow!("Unknown math function: {}", name)),
+    };
+    let x = float(&ctx.value, name)?;
+    finite(f(x), || format!("{} of {}", name, x))
+}
+
+/// Evaluate a binary math function (`atan2(y; x)`) as a float
+///
+/// Both arguments are evaluated against the input.
+pub fn eval_binary(
+    evaluator: &Evaluator,
+    name: &str,
+    left: &Expression,
+    right: &Expression,
+    ctx: &Context,
+) -> Result<Value> {
+    let f: fn(f64, f64) -> f64 = match name {
+        "atan2" => f64::atan2,
+        _ => return Err(anyhow!("Unknown math function: {}", name)),
+    };
+    let a = float(&evaluator.eval(left, ctx)?, name)?;
+    let b = float(&evaluator.eval(right, ctx)?, name)?;
+    finite(f(a, b), || format!("{} of {} and {}", name, a, b))
+}
+
+/// `result` as a number, or an error describing the call if it isn't finite
+fn finite(result: f64, call: impl FnOnce() -> String) -> Result<Value> {
+    if !result.is_finite() {
+        return Err(anyhow!("{} is not a finite number", call()));
+    }
+    Ok(Value::Number(result.into()))
+}
+
+/// The number `value` as a float, or an error naming `func`
+fn float(value: &Value, func: &str) -> Result<f64> {
+    Ok(number(value, func)?.as_f64().unwrap_or(f64::NAN))
+}
+
+/// The number `value`, or an error naming `func`
+fn number<'a>(value: &'a Value, func: &str) -> Result<&'a Number> {
+    match value {
+        Value::Number(n) => Ok(n),
+        other => Err(anyhow!(
+            "{} requires a number, got {}",
+            func,
+            helpers::value_type(other)
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+    use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+    fn parse_and_eval(expr_str: &str, input: &str) -> Result<Value> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate(&expr, Some(&input_val))
+    }
+
+    #[test]
+    fn test_abs() {
+        let result = parse_and_eval("abs", "-5").unwrap();
+        assert_eq!(result, 5);
+        assert!(result.as_i64().is_some());
+        assert_eq!(parse_and_eval("abs", "-3.5").unwrap(), 3.5);
+        assert_eq!(parse_and_eval("abs", "7").unwrap(), 7);
+        assert_eq!(parse_and_eval("abs", "0").unwrap(), 0);
+    }
+
+    #[test]
+    fn test_abs_in_map() {
+        let result = parse_and_eval("map(abs)", "[-1, 2, -3.5]").unwrap();
+        let expected: Value = serde_yaml::from_str("[1, 2, 3.5]").unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_abs_min_integer() {
+        let result = parse_and_eval("abs", "-9223372036854775808").unwrap();
+        assert_eq!(result, 9223372036854775808.0);
+    }
+
+    #[test]
+    fn test_signum() {
+        assert_eq!(parse_and_eval("signum", "-42").unwrap(), -1);
+        assert_eq!(parse_and_eval("signum", "-0.5").unwrap(), -1);
+        assert_eq!(parse_and_eval("signum", "0").unwrap(), 0);
+        assert_eq!(parse_and_eval("signum", "0.0").unwrap(), 0);
+        assert_eq!(parse_and_eval("signum", "3").unwrap(), 1);
+        assert_eq!(parse_and_eval("signum", "2.5").unwrap(), 1);
+    }
+
+    #[test]
+    fn test_exp_and_log() {
+        assert_eq!(parse_and_eval("exp", "0").unwrap(), 1.0);
+        assert_eq!(parse_and_eval("log", "1").unwrap(), 0.0);
+        assert_eq!(parse_and_eval("exp2", "10").unwrap(), 1024.0);
+        assert_eq!(parse_and_eval("log2", "1024").unwrap(), 10.0);
+        assert_eq!(parse_and_eval("exp10", "3").unwrap(), 1000.0);
+        assert_eq!(parse_and_eval("log10", "1000").unwrap(), 3.0);
+        let e = parse_and_eval("1 | exp", "null").unwrap();
+        assert_eq!(e, std::f64::consts::E);
+        assert_eq!(parse_and_eval("exp | log", "2.5").unwrap(), 2.5);
+    }
+
+    #[test]
+    fn test_math_results_are_floats() {
+        let result = parse_and_eval("log10", "100").unwrap();
+        assert!(result.as_i64().is_none());
+        assert_eq!(result, 2.0);
+    }
+
+    #[test]
+    fn test_log_of_non_positive_fails() {
+        let err = parse_and_eval("log", "0").unwrap_err();
+        assert_eq!(err.to_string(), "log of 0 is not a finite number");
+        assert!(parse_and_eval("log10", "-1").is_err());
+        assert!(parse_and_eval("exp", "1000").is_err());
+        assert!(parse_and_eval("log", "'e'").is_err());
+    }
+
+    fn approx(result: Value, expected: f64) {
+        let actual = result.as_f64().unwrap();
+        assert!(
+            (actual - expected).abs() < 1e-12,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_trig() {
+        assert_eq!(parse_and_eval("sin", "0").unwrap(), 0.0);
+        assert_eq!(parse_and_eval("cos", "0").unwrap(), 1.0);
+        assert_eq!(parse_and_eval("tan", "0").unwrap(), 0.0);
+        approx(parse_and_eval("sin", "1.5707963267948966").unwrap(), 1.0);
+        approx(parse_and_eval("asin", "1").unwrap(), FRAC_PI_2);
+        approx(parse_and_eval("acos", "-1").unwrap(), PI);
+        approx(parse_and_eval("atan", "1").unwrap(), FRAC_PI_4);
+    }
+
+    #[test]
+    fn test_atan2() {
+        approx(parse_and_eval("atan2(1; 1)", "null").unwrap(), FRAC_PI_4);
+        approx(
+            parse_and_eval("atan2(.y; .x)", "{y: 1, x: 0}").unwrap(),
+            FRAC_PI_2,
+        );
+        approx(parse_and_eval("atan2(0; .)", "-1").unwrap(), PI);
+    }
+
+    #[test]
+    fn test_trig_errors() {
+        let err = parse_and_eval("asin", "2").unwrap_err();
+        assert_eq!(err.to_string(), "asin of 2 is not a finite number");
+        let err = parse_and_eval("atan2(1; 'x')", "null").unwrap_err();
+        assert_eq!(err.to_string(), "atan2 requires a number, got string");
+        assert!(ExpressionParser::new().parse("atan2(1)").is_err());
+    }
+
+    #[test]
+    fn test_non_numbers() {
+        let err = parse_and_eval("abs", "'x'").unwrap_err();
+        assert_eq!(err.to_string(), "abs requires a number, got string");
+        let err = parse_and_eval("signum", "null").unwrap_err();
+        assert_eq!(err.to_string(), "signum requires a number, got null");
+    }
+}
@@ -0,0 +1,117 @@
+//! Min and max functions (min, max, min_by, max_by)
+
+use crate::evaluator::{Context, Evaluator, helpers};
+use crate::parser::expression::Expression;
+use anyhow::Result;
+use serde_yaml::Value;
+use std::cmp::Ordering;
+
+/// Evaluate min/max (or min_by/max_by with a key) in one pass over an array
+///
+/// Every output of the key expression is part of an element's key, as in
+/// `sort_by`. Ties go to the first minimum and the last maximum, like jq;
+/// an empty array gives null.
+pub fn eval(
+    evaluator: &Evaluator,
+    target: &Expression,
+    key_expr: Option<&Expression>,
+    max: bool,
+    ctx: &Context,
+) -> Result<Value> {
+    let target_val = evaluator.eval(target, ctx)?;
+    let func = match (key_expr.is_some(), max) {
+        (false, false) => "min",
+        (false, true) => "max",
+        (true, false) => "min_by",
+        (true, true) => "max_by",
+    };
+    let Value::Sequence(arr) = target_val else {
+        let action = if max { "get max of" } else { "get min of" };
+        return Err(helpers::not_an_array(action, func, &target_val));
+    };
+
+    let mut best: Option<(Value, Value)> = None;
+    for item in arr {
+        let key = match key_expr {
+            Some(key_expr) => {
+                Value::Sequence(evaluator.eval_multi(key_expr, &ctx.child(item.clone()))?)
+            }
+            None => item.clone(),
+        };
+        let better = match &best {
+            None => true,
+            Some((best_key, _)) => match helpers::compare_values(&key, best_key) {
+                Ordering::Less => !max,
+                Ordering::Equal => max,
+                Ordering::Greater => max,
+            },
+        };
+        if better {
+            best = Some((key, item));
+        }
+    }
+    Ok(best.map(|(_, item)| item).unwrap_or(Value::Null))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval(expr_str: &str, input: &str) -> Result<Value> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate(&expr, Some(&input_val))
+    }
+
+    fn yaml(s: &str) -> Value {
+        serde_yaml::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_min_max() {
+        assert_eq!(parse_and_eval("min", "[3, 1, 2]").unwrap(), 1);
+        assert_eq!(parse_and_eval("max", "[3, 1, 2]").unwrap(), 3);
+        assert_eq!(parse_and_eval("max", "[1, 'a', null]").unwrap(), "a");
+        assert_eq!(parse_and_eval("min", "[]").unwrap(), Value::Null);
+    }
+
+    #[test]
+    fn test_min_by_max_by() {
+        let input = "[{n: a, age: 30}, {n: b, age: 25}, {n: c, age: 35}]";
+        assert_eq!(
+            parse_and_eval("min_by(.age)", input).unwrap(),
+            yaml("{n: b, age: 25}")
+        );
+        assert_eq!(parse_and_eval("max_by(.age) | .n", input).unwrap(), "c");
+    }
+
+    #[test]
+    fn test_ties() {
+        let input = "[{n: a, k: 1}, {n: b, k: 1}]";
+        assert_eq!(parse_and_eval("min_by(.k) | .n", input).unwrap(), "a");
+        assert_eq!(parse_and_eval("max_by(.k) | .n", input).unwrap(), "b");
+    }
+
+    #[test]
+    fn test_min_by_generator_key() {
+        let input = "[{a: 1, b: 9}, {a: 1, b: 2}, {a: 2, b: 0}]";
+        assert_eq!(
+            parse_and_eval("min_by(.a, .b)", input).unwrap(),
+            yaml("{a: 1, b: 2}")
+        );
+    }
+
+    #[test]
+    fn test_min_non_array() {
+        let err = parse_and_eval("min", "{a: 1}").unwrap_err();
+        assert!(
+            err.to_string().starts_with("Cannot get min of object"),
+            "{}",
+            err
+        );
+        assert!(parse_and_eval("max_by(.a)", "'x'").is_err());
+    }
+}
@@ -7,31 +7,55 @@ pub mod alternative;
 pub mod arithmetic;
 pub mod array;
 pub mod assign;
+pub mod bind;
+pub mod codepoints;
 pub mod comma;
 pub mod comparison;
+pub mod contains;
+pub mod count_by;
+pub mod datetime;
 pub mod env;
 pub mod field_access;
 pub mod filter;
 pub mod first;
 pub mod flatten;
+pub mod format;
+pub mod function;
 pub mod group_by;
 pub mod has;
+pub mod if_then_else;
+pub mod in_op;
 pub mod index_access;
+pub mod interpolation;
 pub mod iterator;
 pub mod keys;
 pub mod last;
 pub mod length;
+pub mod limit;
 pub mod logical;
 pub mod map;
+pub mod map_values;
+pub mod math;
+pub mod min_max;
+pub mod nth;
 pub mod object;
+pub mod path;
 pub mod pipe;
+pub mod range;
 pub mod recurse;
+pub mod regex;
 pub mod reverse;
 pub mod select;
 pub mod slice;
 pub mod sort;
+pub mod sort_by;
+pub mod split;
+pub mod stream;
 pub mod tonumber;
 pub mod tostring;
+pub mod try_catch;
+pub mod type_filters;
 pub mod type_op;
 pub mod unique;
 pub mod update;
+pub mod walk;
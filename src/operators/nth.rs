@@ -0,0 +1,99 @@
+//! Nth function (`nth(n; f)`)
+//!
+//! `nth(n)` with one argument is `.[n]`.
+
+use crate::evaluator::{Context, Evaluator, ValueStream};
+use crate::operators::limit;
+use crate::parser::expression::Expression;
+use anyhow::Result;
+use serde_yaml::Value;
+
+/// Evaluate nth function - output `n` (counting from 0) of `expr`, or null
+pub fn eval(
+    evaluator: &Evaluator,
+    n: &Expression,
+    expr: &Expression,
+    ctx: &Context,
+) -> Result<Value> {
+    Ok(eval_multi(evaluator, n, expr, ctx)?
+        .into_iter()
+        .next()
+        .unwrap_or(Value::Null))
+}
+
+/// Produce output `n` of `expr`, if there are that many
+pub fn eval_multi(
+    evaluator: &Evaluator,
+    n: &Expression,
+    expr: &Expression,
+    ctx: &Context,
+) -> Result<Vec<Value>> {
+    eval_lazy(evaluator, n, expr, ctx)?.collect()
+}
+
+/// Produce output `n` of `expr` without evaluating the ones after it
+pub fn eval_lazy<'a>(
+    evaluator: &'a Evaluator,
+    n: &'a Expression,
+    expr: &'a Expression,
+    ctx: &Context,
+) -> Result<ValueStream<'a>> {
+    let n = limit::count(&evaluator.eval(n, ctx)?, "nth index")?;
+    let mut outputs = evaluator.eval_lazy(expr, ctx)?;
+    // Errors before output `n` still fail, as the outputs are computed
+    for _ in 0..n {
+        match outputs.next() {
+            Some(Err(err)) => return Err(err),
+            Some(Ok(_)) => {}
+            None => return Ok(Box::new(std::iter::empty())),
+        }
+    }
+    Ok(Box::new(outputs.take(1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval_multi(expr_str: &str, input: &str) -> Result<Vec<Value>> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate_multi(&expr, Some(&input_val))
+    }
+
+    fn yaml(s: &str) -> Value {
+        serde_yaml::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_nth_output() {
+        let result = parse_and_eval_multi("nth(2; .[] | . * 10)", "[1, 2, 3, 4]").unwrap();
+        assert_eq!(result, vec![yaml("30")]);
+        assert!(
+            parse_and_eval_multi("nth(5; .[])", "[1]")
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_nth_of_huge_range() {
+        let result = parse_and_eval_multi("nth(10; range(0; 1000000000; 3))", "null").unwrap();
+        assert_eq!(result, vec![yaml("30")]);
+    }
+
+    #[test]
+    fn test_nth_index() {
+        let result = parse_and_eval_multi("nth(1)", "[a, b, c]").unwrap();
+        assert_eq!(result, vec![yaml("b")]);
+    }
+
+    #[test]
+    fn test_nth_negative() {
+        let err = parse_and_eval_multi("nth(.[0]; .[])", "[-1]").unwrap_err();
+        assert_eq!(err.to_string(), "nth index cannot be negative");
+    }
+}
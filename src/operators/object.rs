@@ -1,21 +1,165 @@
 //! Object constructor
 
-use crate::evaluator::{Context, Evaluator};
+use crate::evaluator::{Context, Evaluator, helpers};
 use crate::parser::expression::Expression;
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use serde_yaml::Value;
 
-/// Evaluate object constructor
+/// Evaluate object constructor - returns the first object built, or null
 pub fn eval(
     evaluator: &Evaluator,
     fields: &[(Expression, Expression)],
     ctx: &Context,
 ) -> Result<Value> {
-    let mut result = serde_yaml::Mapping::new();
+    Ok(eval_multi(evaluator, fields, ctx)?
+        .into_iter()
+        .next()
+        .unwrap_or(Value::Null))
+}
+
+/// Build one object per combination of key and value outputs
+///
+/// Keys may be computed (`{(.k): .v}`), but must evaluate to a scalar. A
+/// field with several outputs multiplies the objects built, so
+/// `{a: (1, 2), b: (3, 4)}` produces four objects, varying `b` fastest.
+pub fn eval_multi(
+    evaluator: &Evaluator,
+    fields: &[(Expression, Expression)],
+    ctx: &Context,
+) -> Result<Vec<Value>> {
+    let mut results = vec![serde_yaml::Mapping::new()];
     for (key_expr, value_expr) in fields {
-        let key = evaluator.eval(key_expr, ctx)?;
-        let value = evaluator.eval(value_expr, ctx)?;
-        result.insert(key, value);
+        let mut pairs = Vec::new();
+        for key in evaluator.eval_multi(key_expr, ctx)? {
+            if matches!(
+                key,
+                Value::Null | Value::Sequence(_) | Value::Mapping(_) | Value::Tagged(_)
+            ) {
+                return Err(anyhow!(
+                    "Object keys must be strings, got {}",
+                    helpers::value_type(&key)
+                ));
+            }
+            for value in evaluator.eval_multi(value_expr, ctx)? {
+                pairs.push((key.clone(), value));
+            }
+        }
+
+        let mut next = Vec::with_capacity(results.len() * pairs.len());
+        for partial in &results {
+            for (key, value) in &pairs {
+                let mut object = partial.clone();
+                object.insert(key.clone(), value.clone());
+                next.push(object);
+            }
+        }
+        results = next;
+    }
+    Ok(results.into_iter().map(Value::Mapping).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval(expr_str: &str, input: &str) -> Result<Value> {
+        let parser = ExpressionParser::new();
+        let mut evaluator = Evaluator::new();
+        evaluator.set_variable("x".to_string(), Value::from(7));
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate(&expr, Some(&input_val))
+    }
+
+    fn yaml(s: &str) -> Value {
+        serde_yaml::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_object_bare_keys_are_literal() {
+        let result = parse_and_eval("{a: 1, b: .a}", "{a: 5}").unwrap();
+        assert_eq!(result, yaml("{a: 1, b: 5}"));
+    }
+
+    #[test]
+    fn test_object_field_shorthand() {
+        let result = parse_and_eval("{name, age}", "{name: Ann, age: 30, x: 1}").unwrap();
+        assert_eq!(result, yaml("{name: Ann, age: 30}"));
+
+        let result = parse_and_eval(r#"{"a b"}"#, "{a b: 1}").unwrap();
+        assert_eq!(result, yaml("{a b: 1}"));
+    }
+
+    #[test]
+    fn test_object_variable_shorthand() {
+        let result = parse_and_eval("{$x, y: 1}", "null").unwrap();
+        assert_eq!(result, yaml("{x: 7, y: 1}"));
+    }
+
+    #[test]
+    fn test_object_computed_key() {
+        let result = parse_and_eval("{(.k): .v}", "{k: name, v: 1}").unwrap();
+        assert_eq!(result, yaml("{name: 1}"));
+
+        let result = parse_and_eval(r#"{("a" + "b"): 2}"#, "null").unwrap();
+        assert_eq!(result, yaml("{ab: 2}"));
+    }
+
+    fn parse_and_eval_multi(expr_str: &str, input: &str) -> Result<Vec<Value>> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate_multi(&expr, Some(&input_val))
+    }
+
+    #[test]
+    fn test_object_value_generator() {
+        let result = parse_and_eval_multi("{a: (1, 2)}", "null").unwrap();
+        assert_eq!(result, vec![yaml("{a: 1}"), yaml("{a: 2}")]);
+    }
+
+    #[test]
+    fn test_object_cartesian_product() {
+        let result = parse_and_eval_multi("{a: (1, 2), b: (3, 4)}", "null").unwrap();
+        assert_eq!(
+            result,
+            vec![
+                yaml("{a: 1, b: 3}"),
+                yaml("{a: 1, b: 4}"),
+                yaml("{a: 2, b: 3}"),
+                yaml("{a: 2, b: 4}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_object_generator_over_input() {
+        let result =
+            parse_and_eval_multi("{user, title: .titles[]}", "{user: ann, titles: [x, y]}")
+                .unwrap();
+        assert_eq!(
+            result,
+            vec![yaml("{user: ann, title: x}"), yaml("{user: ann, title: y}")]
+        );
+    }
+
+    #[test]
+    fn test_object_key_generator() {
+        let result = parse_and_eval_multi(r#"{("a", "b"): 1}"#, "null").unwrap();
+        assert_eq!(result, vec![yaml("{a: 1}"), yaml("{b: 1}")]);
+    }
+
+    #[test]
+    fn test_object_empty_value_produces_nothing() {
+        let result = parse_and_eval_multi("{a: empty}", "null").unwrap();
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn test_object_invalid_computed_key() {
+        assert!(parse_and_eval("{(.k): 1}", "{}").is_err());
+        assert!(parse_and_eval("{(.k): 1}", "{k: [1]}").is_err());
     }
-    Ok(Value::Mapping(result))
 }
@@ -0,0 +1,449 @@
+//! Path function (path(f))
+//!
+//! A path is an array of object keys and array indices addressing a value
+//! inside the input, as in jq: `path(.a[0].b)` is `["a", 0, "b"]`.
+
+use super::{bind, function};
+use crate::evaluator::{Context, Evaluator, helpers};
+use crate::parser::expression::Expression;
+use anyhow::{Result, anyhow};
+use serde_yaml::Value;
+
+/// Evaluate path function - returns the first path addressed by `expr`, or null
+pub fn eval(evaluator: &Evaluator, expr: &Expression, ctx: &Context) -> Result<Value> {
+    Ok(eval_multi(evaluator, expr, ctx)?
+        .into_iter()
+        .next()
+        .unwrap_or(Value::Null))
+}
+
+/// Stream every path addressed by `expr`, each as an array
+pub fn eval_multi(evaluator: &Evaluator, expr: &Expression, ctx: &Context) -> Result<Vec<Value>> {
+    Ok(paths(evaluator, expr, ctx)?
+        .into_iter()
+        .map(Value::Sequence)
+        .collect())
+}
+
+/// Collect the paths that `expr` addresses within the context value
+///
+/// Only path expressions are accepted: field and index access, `.[]`,
+/// `..`, pipes, commas, conditionals, `select`, type filters, `empty`,
+/// optional (`?`) access, `as` bindings and calls of functions whose
+/// bodies are paths. Anything that computes a new value is an error.
+pub fn paths(evaluator: &Evaluator, expr: &Expression, ctx: &Context) -> Result<Vec<Vec<Value>>> {
+    match expr {
+        Expression::Identity => Ok(vec![vec![]]),
+        Expression::Empty => Ok(vec![]),
+        Expression::Group { expr } => paths(evaluator, expr, ctx),
+        Expression::FieldAccess { target, field } => {
+            let mut results = Vec::new();
+            for mut path in paths(evaluator, target, ctx)? {
+                let value = get_path(&ctx.value, &path);
+                if !matches!(value, Value::Mapping(_) | Value::Null) {
+                    return Err(anyhow!(
+                        "Cannot index {} with \"{}\"",
+                        helpers::value_type(&value),
+                        field
+                    ));
+                }
+                path.push(Value::String(field.clone()));
+                results.push(path);
+            }
+            Ok(results)
+        }
+        Expression::IndexAccess { target, index } => {
+            let mut results = Vec::new();
+            for mut path in paths(evaluator, target, ctx)? {
+                let value = get_path(&ctx.value, &path);
+                if !matches!(value, Value::Sequence(_) | Value::Null) {
+                    return Err(anyhow!(
+                        "Cannot index {} with number",
+                        helpers::value_type(&value)
+                    ));
+                }
+                path.push(Value::Number((*index as i64).into()));
+                results.push(path);
+            }
+            Ok(results)
+        }
+        Expression::DynamicIndex { target, index } => {
+            let key = match evaluator.eval(index, ctx)? {
+                key @ Value::String(_) => key,
+                Value::Number(n) => match n.as_i64() {
+                    Some(i) => Value::Number(i.into()),
+                    None => Value::Number((n.as_f64().unwrap_or(0.0).floor() as i64).into()),
+                },
+                other => {
+                    return Err(anyhow!("Cannot index with {}", helpers::value_type(&other)));
+                }
+            };
+            let mut results = Vec::new();
+            for mut path in paths(evaluator, target, ctx)? {
+                path.push(key.clone());
+                results.push(path);
+            }
+            Ok(results)
+        }
+        Expression::Iterator { target } => {
+            let mut results = Vec::new();
+            for path in paths(evaluator, target, ctx)? {
+                match get_path(&ctx.value, &path) {
+                    Value::Sequence(arr) => {
+                        for i in 0..arr.len() {
+                            let mut child = path.clone();
+                            child.push(Value::Number(i.into()));
+                            results.push(child);
+                        }
+                    }
+                    Value::Mapping(map) => {
+                        for key in map.keys() {
+                            let mut child = path.clone();
+                            child.push(key.clone());
+                            results.push(child);
+                        }
+                    }
+                    Value::Null => {}
+                    other => {
+                        return Err(anyhow!(
+                            "Cannot iterate over {}",
+                            helpers::value_type(&other)
+                        ));
+                    }
+                }
+            }
+            Ok(results)
+        }
+        Expression::Recurse => {
+            let mut results = Vec::new();
+            collect_recursive(&ctx.value, &mut Vec::new(), &mut results);
+            Ok(results)
+        }
+        Expression::Pipe { left, right } => {
+            let mut results = Vec::new();
+            for prefix in paths(evaluator, left, ctx)? {
+                let child_ctx = ctx.child(get_path(&ctx.value, &prefix));
+                for suffix in paths(evaluator, right, &child_ctx)? {
+                    let mut path = prefix.clone();
+                    path.extend(suffix);
+                    results.push(path);
+                }
+            }
+            Ok(results)
+        }
+        Expression::Comma { left, right } => {
+            let mut results = paths(evaluator, left, ctx)?;
+            results.extend(paths(evaluator, right, ctx)?);
+            Ok(results)
+        }
+        Expression::IfThenElse {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            let condition_val = evaluator.eval(condition, ctx)?;
+            if helpers::is_truthy(&condition_val) {
+                paths(evaluator, then_branch, ctx)
+            } else {
+                paths(evaluator, else_branch, ctx)
+            }
+        }
+        Expression::Select { condition } => {
+            let condition_val = evaluator.eval(condition, ctx)?;
+            Ok(if helpers::is_truthy(&condition_val) {
+                vec![vec![]]
+            } else {
+                vec![]
+            })
+        }
+        Expression::TypeFilter { .. } => Ok(evaluator
+            .eval_multi(expr, ctx)?
+            .into_iter()
+            .map(|_| vec![])
+            .collect()),
+        Expression::Try { expr, catch: None } => {
+            Ok(paths(evaluator, expr, ctx).unwrap_or_default())
+        }
+        Expression::Bind {
+            source,
+            pattern,
+            body,
+        } => {
+            let mut results = Vec::new();
+            for value in evaluator.eval_multi(source, ctx)? {
+                let bound_ctx = bind::bound(evaluator, pattern, &value, ctx)?;
+                results.extend(paths(evaluator, body, &bound_ctx)?);
+            }
+            Ok(results)
+        }
+        Expression::FunctionDef {
+            name,
+            params,
+            body,
+            rest,
+        } => paths(evaluator, rest, &function::define(name, params, body, ctx)),
+        Expression::FunctionCall { name, args } => {
+            let (function, contexts) = function::call_contexts(evaluator, name, args, ctx)?;
+            let mut results = Vec::new();
+            for call_ctx in &contexts {
+                results.extend(paths(evaluator, &function.body, call_ctx)?);
+            }
+            Ok(results)
+        }
+        other => Err(anyhow!(
+            "Invalid path expression: cannot take the path of {}",
+            describe(other)
+        )),
+    }
+}
+
+/// Name the kind of a non-path expression for error messages
+fn describe(expr: &Expression) -> &'static str {
+    match expr {
+        Expression::Literal(_) => "a literal",
+        Expression::Array { .. } => "an array construction",
+        Expression::Object { .. } => "an object construction",
+        Expression::Interpolation { .. } => "a string interpolation",
+        Expression::Variable { .. } => "a variable",
+        Expression::Add { .. }
+        | Expression::Subtract { .. }
+        | Expression::Multiply { .. }
+        | Expression::Divide { .. }
+        | Expression::Modulo { .. } => "an arithmetic expression",
+        Expression::Equal { .. }
+        | Expression::NotEqual { .. }
+        | Expression::LessThan { .. }
+        | Expression::LessThanOrEqual { .. }
+        | Expression::GreaterThan { .. }
+        | Expression::GreaterThanOrEqual { .. } => "a comparison",
+        Expression::And { .. } | Expression::Or { .. } => "a boolean expression",
+        Expression::Assign { .. }
+        | Expression::Update { .. }
+        | Expression::ArithmeticUpdate { .. } => "an assignment",
+        Expression::Alternative { .. } => "an alternative (`//`)",
+        Expression::Slice { .. } => "a slice",
+        Expression::Try { .. } => "a try with a catch",
+        _ => "a function call",
+    }
+}
+
+fn collect_recursive(value: &Value, path: &mut Vec<Value>, results: &mut Vec<Vec<Value>>) {
+    results.push(path.clone());
+
+    match value {
+        Value::Sequence(arr) => {
+            for (i, item) in arr.iter().enumerate() {
+                path.push(Value::Number(i.into()));
+                collect_recursive(item, path, results);
+                path.pop();
+            }
+        }
+        Value::Mapping(map) => {
+            for (key, item) in map {
+                path.push(key.clone());
+                collect_recursive(item, path, results);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Get the value at `path`, or null if any step is missing
+pub fn get_path(value: &Value, path: &[Value]) -> Value {
+    let mut current = value;
+    for key in path {
+        let next = match (current, key) {
+            (Value::Mapping(map), key) => map.get(key),
+            (Value::Sequence(arr), Value::Number(n)) => n.as_i64().and_then(|i| {
+                let i = if i < 0 { arr.len() as i64 + i } else { i };
+                usize::try_from(i).ok().and_then(|i| arr.get(i))
+            }),
+            _ => None,
+        };
+        match next {
+            Some(next) => current = next,
+            None => return Value::Null,
+        }
+    }
+    current.clone()
+}
+
+/// Set the value at `path`, creating objects and arrays along the way
+///
+/// A missing step is created as an object for a string key and as an array
+/// (padded with nulls) for an index. Negative indices count from the end.
+pub fn set_path(target: &mut Value, path: &[Value], value: Value) -> Result<()> {
+    let Some((key, rest)) = path.split_first() else {
+        *target = value;
+        return Ok(());
+    };
+
+    if target.is_null() {
+        *target = match key {
+            Value::Number(_) => Value::Sequence(Vec::new()),
+            _ => Value::Mapping(serde_yaml::Mapping::new()),
+        };
+    }
+
+    match (target, key) {
+        (Value::Mapping(map), key) => {
+            let entry = map.entry(key.clone()).or_insert(Value::Null);
+            set_path(entry, rest, value)
+        }
+        (Value::Sequence(arr), Value::Number(n)) => {
+            let index = n
+                .as_i64()
+                .ok_or_else(|| anyhow!("Invalid array index: {}", n))?;
+            let index = if index < 0 {
+                usize::try_from(arr.len() as i64 + index)
+                    .map_err(|_| anyhow!("Out of bounds negative array index: {}", index))?
+            } else {
+                index as usize
+            };
+            if arr.len() <= index {
+                arr.resize(index + 1, Value::Null);
+            }
+            set_path(&mut arr[index], rest, value)
+        }
+        (target, key) => Err(anyhow!(
+            "Cannot index {} with {}",
+            helpers::value_type(target),
+            helpers::value_type(key)
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval_multi(expr_str: &str, input: &str) -> Result<Vec<Value>> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate_multi(&expr, Some(&input_val))
+    }
+
+    fn yaml(s: &str) -> Value {
+        serde_yaml::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_path_of_access_chain() {
+        let result = parse_and_eval_multi("path(.a[0].b)", "{}").unwrap();
+        assert_eq!(result, vec![yaml("[a, 0, b]")]);
+    }
+
+    #[test]
+    fn test_path_of_identity() {
+        let result = parse_and_eval_multi("path(.)", "1").unwrap();
+        assert_eq!(result, vec![yaml("[]")]);
+    }
+
+    #[test]
+    fn test_path_iterator_streams_paths() {
+        let result = parse_and_eval_multi("path(.[])", "[a, b, c]").unwrap();
+        assert_eq!(result, vec![yaml("[0]"), yaml("[1]"), yaml("[2]")]);
+
+        let result = parse_and_eval_multi("path(.x[])", "{x: {a: 1, b: 2}}").unwrap();
+        assert_eq!(result, vec![yaml("[x, a]"), yaml("[x, b]")]);
+    }
+
+    #[test]
+    fn test_path_with_select() {
+        let result =
+            parse_and_eval_multi("path(.[] | select(.n > 1) | .n)", "[{n: 1}, {n: 2}]").unwrap();
+        assert_eq!(result, vec![yaml("[1, n]")]);
+    }
+
+    #[test]
+    fn test_path_comma() {
+        let result = parse_and_eval_multi("path(.a, .b[0])", "{}").unwrap();
+        assert_eq!(result, vec![yaml("[a]"), yaml("[b, 0]")]);
+    }
+
+    #[test]
+    fn test_path_recurse() {
+        let result = parse_and_eval_multi("path(..)", "{a: [1]}").unwrap();
+        assert_eq!(result, vec![yaml("[]"), yaml("[a]"), yaml("[a, 0]")]);
+    }
+
+    #[test]
+    fn test_path_dynamic_index() {
+        let result = parse_and_eval_multi(".[.k] = 1", "{k: x}").unwrap();
+        assert_eq!(result, vec![yaml("{k: x, x: 1}")]);
+    }
+
+    #[test]
+    fn test_path_through_function() {
+        let result =
+            parse_and_eval_multi("def items: .items[]; path(items)", "{items: [1, 2]}").unwrap();
+        assert_eq!(result, vec![yaml("[items, 0]"), yaml("[items, 1]")]);
+        let result = parse_and_eval_multi("def first: .[0]; first |= . + 1", "[1, 2]").unwrap();
+        assert_eq!(result, vec![yaml("[2, 2]")]);
+    }
+
+    #[test]
+    fn test_path_through_binding() {
+        let result = parse_and_eval_multi("path(.k as $k | .[$k])", "{k: a}").unwrap();
+        assert_eq!(result, vec![yaml("[a]")]);
+    }
+
+    #[test]
+    fn test_path_invalid_expression() {
+        let err = parse_and_eval_multi("path(1)", "{}").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid path expression: cannot take the path of a literal"
+        );
+        let err = parse_and_eval_multi("path(.a + 1)", "{}").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid path expression: cannot take the path of an arithmetic expression"
+        );
+        let err = parse_and_eval_multi("path(length)", "{}").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid path expression: cannot take the path of a function call"
+        );
+    }
+
+    #[test]
+    fn test_path_index_into_wrong_type() {
+        assert!(parse_and_eval_multi("path(.a.b)", "{a: 1}").is_err());
+    }
+
+    #[test]
+    fn test_set_path_creates_containers() {
+        let mut value = Value::Null;
+        set_path(&mut value, &[yaml("a"), yaml("1"), yaml("b")], yaml("x")).unwrap();
+        assert_eq!(value, yaml("{a: [null, {b: x}]}"));
+    }
+
+    #[test]
+    fn test_set_path_negative_index() {
+        let mut value = yaml("[1, 2, 3]");
+        set_path(&mut value, &[yaml("-1")], yaml("9")).unwrap();
+        assert_eq!(value, yaml("[1, 2, 9]"));
+        assert!(set_path(&mut value, &[yaml("-5")], yaml("9")).is_err());
+    }
+
+    #[test]
+    fn test_set_path_wrong_type() {
+        let mut value = yaml("{a: 1}");
+        assert!(set_path(&mut value, &[yaml("a"), yaml("b")], yaml("2")).is_err());
+        let mut value = yaml("[1]");
+        assert!(set_path(&mut value, &[yaml("a")], yaml("2")).is_err());
+    }
+
+    #[test]
+    fn test_get_path() {
+        let value = yaml("{a: [1, {b: 2}]}");
+        assert_eq!(get_path(&value, &[yaml("a"), yaml("1"), yaml("b")]), 2);
+        assert_eq!(get_path(&value, &[yaml("a"), yaml("-1"), yaml("b")]), 2);
+        assert_eq!(get_path(&value, &[yaml("x"), yaml("y")]), Value::Null);
+    }
+}
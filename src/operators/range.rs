@@ -0,0 +1,137 @@
+//! Range function (range(n), range(from; upto), range(from; upto; by))
+
+use crate::evaluator::{Context, Evaluator, helpers};
+use crate::parser::expression::Expression;
+use anyhow::{Result, anyhow};
+use serde_yaml::Value;
+
+/// Evaluate range function - returns every number as an array
+pub fn eval(
+    evaluator: &Evaluator,
+    start: &Expression,
+    end: &Expression,
+    by: Option<&Expression>,
+    ctx: &Context,
+) -> Result<Value> {
+    Ok(Value::Sequence(eval_multi(evaluator, start, end, by, ctx)?))
+}
+
+/// Stream the numbers from `start` up to (but excluding) `end`
+pub fn eval_multi(
+    evaluator: &Evaluator,
+    start: &Expression,
+    end: &Expression,
+    by: Option<&Expression>,
+    ctx: &Context,
+) -> Result<Vec<Value>> {
+    Ok(iter(evaluator, start, end, by, ctx)?.collect())
+}
+
+/// Produce the numbers from `start` up to (but excluding) `end` lazily, so
+/// `first(range(1e9))` doesn't build the whole range
+///
+/// The step defaults to 1; a negative step counts down. The numbers are
+/// integers when every argument is.
+pub fn iter(
+    evaluator: &Evaluator,
+    start: &Expression,
+    end: &Expression,
+    by: Option<&Expression>,
+    ctx: &Context,
+) -> Result<Box<dyn Iterator<Item = Value>>> {
+    let start = number(evaluator.eval(start, ctx)?)?;
+    let end = number(evaluator.eval(end, ctx)?)?;
+    let by = match by {
+        Some(by) => number(evaluator.eval(by, ctx)?)?,
+        None => serde_yaml::Number::from(1),
+    };
+
+    if let (Some(start), Some(end), Some(by)) = (start.as_i64(), end.as_i64(), by.as_i64()) {
+        if by == 0 {
+            return Err(anyhow!("range step cannot be zero"));
+        }
+        let numbers = std::iter::successors(Some(start), move |i| i.checked_add(by))
+            .take_while(move |&i| (by > 0 && i < end) || (by < 0 && i > end))
+            .map(|i| Value::Number(i.into()));
+        return Ok(Box::new(numbers));
+    }
+
+    let (start, end, by) = (as_f64(&start), as_f64(&end), as_f64(&by));
+    if by == 0.0 {
+        return Err(anyhow!("range step cannot be zero"));
+    }
+    let numbers = std::iter::successors(Some(start), move |i| Some(i + by))
+        .take_while(move |&i| (by > 0.0 && i < end) || (by < 0.0 && i > end))
+        .map(|i| Value::Number(i.into()));
+    Ok(Box::new(numbers))
+}
+
+fn number(value: Value) -> Result<serde_yaml::Number> {
+    match value {
+        Value::Number(n) => Ok(n),
+        other => Err(anyhow!(
+            "Range bounds must be numbers, got {}",
+            helpers::value_type(&other)
+        )),
+    }
+}
+
+fn as_f64(n: &serde_yaml::Number) -> f64 {
+    n.as_f64().unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval_multi(expr_str: &str, input: &str) -> Result<Vec<Value>> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate_multi(&expr, Some(&input_val))
+    }
+
+    fn yaml(s: &str) -> Value {
+        serde_yaml::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_range_collected() {
+        let result = parse_and_eval_multi("[range(3)]", "null").unwrap();
+        assert_eq!(result, vec![yaml("[0, 1, 2]")]);
+    }
+
+    #[test]
+    fn test_range_streams() {
+        let result = parse_and_eval_multi("range(2; 5)", "null").unwrap();
+        assert_eq!(result, vec![yaml("2"), yaml("3"), yaml("4")]);
+    }
+
+    #[test]
+    fn test_range_with_step() {
+        let result = parse_and_eval_multi("[range(0; 10; 3)]", "null").unwrap();
+        assert_eq!(result, vec![yaml("[0, 3, 6, 9]")]);
+        let result = parse_and_eval_multi("[range(5; 0; .)]", "-2").unwrap();
+        assert_eq!(result, vec![yaml("[5, 3, 1]")]);
+        let result = parse_and_eval_multi("[range(0; 1; 0.5)]", "null").unwrap();
+        assert_eq!(result, vec![yaml("[0.0, 0.5]")]);
+    }
+
+    #[test]
+    fn test_range_empty() {
+        assert!(parse_and_eval_multi("range(0)", "null").unwrap().is_empty());
+        assert!(
+            parse_and_eval_multi("range(3; 1)", "null")
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_range_errors() {
+        assert!(parse_and_eval_multi("range(0; 3; 0)", "null").is_err());
+        assert!(parse_and_eval_multi(r#"range("a")"#, "null").is_err());
+    }
+}
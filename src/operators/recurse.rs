@@ -1,30 +1,218 @@
 //! Recurse function (..)
 
-use crate::evaluator::{Context, Evaluator};
-use anyhow::Result;
+use crate::evaluator::{Context, Evaluator, helpers};
+use crate::parser::expression::Expression;
+use anyhow::{Result, anyhow};
 use serde_yaml::Value;
 
-/// Evaluate recurse function - returns all values recursively
-pub fn eval(_evaluator: &Evaluator, ctx: &Context) -> Result<Value> {
+/// Evaluate recurse function - returns all values recursively as an array
+pub fn eval(evaluator: &Evaluator, ctx: &Context) -> Result<Value> {
+    Ok(Value::Sequence(eval_multi(evaluator, ctx)?))
+}
+
+/// Stream the input and every value nested inside it, parents first
+///
+/// Fails if the input nests deeper than the evaluator's maximum depth.
+pub fn eval_multi(evaluator: &Evaluator, ctx: &Context) -> Result<Vec<Value>> {
     let mut results = Vec::new();
-    collect_values(&ctx.value, &mut results);
-    Ok(Value::Sequence(results))
+    collect_values(&ctx.value, evaluator.max_depth(), &mut results)?;
+    Ok(results)
 }
 
-fn collect_values(value: &Value, results: &mut Vec<Value>) {
+fn collect_values(value: &Value, depth_left: usize, results: &mut Vec<Value>) -> Result<()> {
+    let Some(depth_left) = depth_left.checked_sub(1) else {
+        return Err(anyhow!("Document nests too deeply to recurse into"));
+    };
     results.push(value.clone());
 
     match value {
         Value::Sequence(arr) => {
             for item in arr {
-                collect_values(item, results);
+                collect_values(item, depth_left, results)?;
             }
         }
         Value::Mapping(map) => {
             for (_, v) in map {
-                collect_values(v, results);
+                collect_values(v, depth_left, results)?;
             }
         }
         _ => {}
     }
+    Ok(())
+}
+
+/// Evaluate `recurse(f)` and `recurse(f; cond)` - returns every value reached
+/// by applying `f` repeatedly, as an array
+pub fn eval_with(
+    evaluator: &Evaluator,
+    f: &Expression,
+    cond: Option<&Expression>,
+    ctx: &Context,
+) -> Result<Value> {
+    Ok(Value::Sequence(eval_multi_with(evaluator, f, cond, ctx)?))
+}
+
+/// Stream the input followed by every value reached by applying `f`
+/// repeatedly, depth first
+///
+/// Descent continues only through outputs of `f` for which `cond` is truthy.
+/// Without a condition it stops at `null`, as jq's `recurse(f)` does.
+pub fn eval_multi_with(
+    evaluator: &Evaluator,
+    f: &Expression,
+    cond: Option<&Expression>,
+    ctx: &Context,
+) -> Result<Vec<Value>> {
+    let mut results = Vec::new();
+    descend(
+        evaluator,
+        f,
+        cond,
+        ctx.child(ctx.value.clone()),
+        &mut results,
+    )?;
+    Ok(results)
+}
+
+fn descend(
+    evaluator: &Evaluator,
+    f: &Expression,
+    cond: Option<&Expression>,
+    ctx: Context,
+    results: &mut Vec<Value>,
+) -> Result<()> {
+    results.push(ctx.value.clone());
+
+    for next in evaluator.eval_multi(f, &ctx)? {
+        let next_ctx = ctx.child(next);
+        let keep = match cond {
+            Some(cond) => helpers::is_truthy(&evaluator.eval(cond, &next_ctx)?),
+            None => !next_ctx.value.is_null(),
+        };
+        if keep {
+            descend(evaluator, f, cond, next_ctx, results)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval_multi(expr_str: &str, input: &str) -> Result<Vec<Value>> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let ctx = Context::new(serde_yaml::from_str(input)?);
+        evaluator.eval_multi(&expr, &ctx)
+    }
+
+    #[test]
+    fn test_recurse_deep_document_errors() {
+        let mut deep = Value::Null;
+        for _ in 0..1_000 {
+            deep = Value::Sequence(vec![deep]);
+        }
+        let evaluator = Evaluator::with_max_depth(100);
+        let ctx = Context::new(deep);
+        let err = eval_multi(&evaluator, &ctx).unwrap_err().to_string();
+        assert!(err.contains("nests too deeply"), "{}", err);
+
+        let evaluator = Evaluator::with_max_depth(2_000);
+        assert_eq!(eval_multi(&evaluator, &ctx).unwrap().len(), 1_001);
+    }
+
+    #[test]
+    fn test_recurse_operator_reports_depth_limit() {
+        // Deeper than the default limit of 512 but shallow enough to build
+        // and drop without exhausting the stack
+        let mut deep = Value::Null;
+        for _ in 0..600 {
+            deep = Value::Sequence(vec![deep]);
+        }
+        let expr = ExpressionParser::new().parse("..").unwrap();
+        let err = Evaluator::new()
+            .eval_multi(&expr, &Context::new(deep))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Document nests too deeply to recurse into");
+    }
+
+    #[test]
+    fn test_recurse_streams_every_node() {
+        // The root, a, a.b, a.b[0], a.b[1] and c
+        let results = parse_and_eval_multi("..", "{a: {b: [1, 2]}, c: x}").unwrap();
+        assert_eq!(results.len(), 6);
+        assert_eq!(results[3], 1);
+        assert_eq!(results[5], "x");
+    }
+
+    #[test]
+    fn test_recurse_pipe_filters_each_node() {
+        let results = parse_and_eval_multi(
+            ".. | select(type == \"number\")",
+            "{a: 1, b: [2, {c: 3}], d: x}",
+        )
+        .unwrap();
+        assert_eq!(
+            results,
+            vec![Value::from(1), Value::from(2), Value::from(3)]
+        );
+    }
+
+    #[test]
+    fn test_recurse_optional_field() {
+        let results = parse_and_eval_multi(".. | .name?", "[{name: a}, {items: [{name: b}]}]")
+            .unwrap()
+            .into_iter()
+            .filter(|v| !v.is_null())
+            .collect::<Vec<_>>();
+        assert_eq!(results, vec![Value::from("a"), Value::from("b")]);
+    }
+
+    #[test]
+    fn test_recurse_with_custom_descent() {
+        let results = parse_and_eval_multi(
+            "recurse(.children[]) | .id",
+            "{id: 1, children: [{id: 2, children: [{id: 3, children: []}]}, {id: 4, children: []}]}",
+        )
+        .unwrap();
+        assert_eq!(
+            results,
+            vec![
+                Value::from(1),
+                Value::from(2),
+                Value::from(3),
+                Value::from(4)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_recurse_linked_list() {
+        let results = parse_and_eval_multi(
+            "recurse(.next?) | .v",
+            "{v: 1, next: {v: 2, next: {v: 3, next: null}}}",
+        )
+        .unwrap();
+        assert_eq!(
+            results,
+            vec![Value::from(1), Value::from(2), Value::from(3)]
+        );
+    }
+
+    #[test]
+    fn test_recurse_with_condition() {
+        let results = parse_and_eval_multi("recurse(. * 2; . < 20)", "2").unwrap();
+        assert_eq!(
+            results,
+            vec![
+                Value::from(2),
+                Value::from(4),
+                Value::from(8),
+                Value::from(16)
+            ]
+        );
+    }
 }
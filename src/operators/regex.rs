@@ -0,0 +1,607 @@
+//! Regular expression functions (test, match, sub, gsub, splits)
+
+use crate::evaluator::{Context, Evaluator, helpers};
+use crate::parser::expression::Expression;
+use anyhow::{Context as _, Result, anyhow};
+use regex::{Regex, RegexBuilder};
+use serde_yaml::Value;
+
+/// A compiled pattern together with the flags that affect matching
+struct Pattern {
+    regex: Regex,
+    /// Report every match rather than the first (the `g` flag)
+    global: bool,
+    /// Skip empty matches (the `n` flag)
+    skip_empty: bool,
+}
+
+impl Pattern {
+    /// Compile `pattern` with jq-style flags
+    ///
+    /// Supported flags are `g` (global), `i` (case-insensitive), `x`
+    /// (extended, whitespace and comments ignored), `s` (`.` matches
+    /// newlines), `m` (`^` and `$` match at line boundaries) and `n` (ignore
+    /// empty matches).
+    fn new(pattern: &str, flags: &str) -> Result<Self> {
+        let mut builder = RegexBuilder::new(pattern);
+        let mut global = false;
+        let mut skip_empty = false;
+        for flag in flags.chars() {
+            match flag {
+                'g' => global = true,
+                'i' => {
+                    builder.case_insensitive(true);
+                }
+                'x' => {
+                    builder.ignore_whitespace(true);
+                }
+                's' => {
+                    builder.dot_matches_new_line(true);
+                }
+                'm' => {
+                    builder.multi_line(true);
+                }
+                'n' => skip_empty = true,
+                other => return Err(anyhow!("{} is not a valid regex flag", other)),
+            }
+        }
+        let regex = builder
+            .build()
+            .with_context(|| format!("Invalid regex: {}", pattern))?;
+        Ok(Self {
+            regex,
+            global,
+            skip_empty,
+        })
+    }
+}
+
+/// Evaluate the pattern and optional flags arguments into a [`Pattern`]
+fn compile(
+    evaluator: &Evaluator,
+    pattern: &Expression,
+    flags: Option<&Expression>,
+    ctx: &Context,
+) -> Result<Pattern> {
+    let pattern = match evaluator.eval(pattern, ctx)? {
+        Value::String(s) => s,
+        other => {
+            return Err(anyhow!(
+                "Regex must be a string, got {}",
+                helpers::value_type(&other)
+            ));
+        }
+    };
+    let flags = match flags {
+        None => String::new(),
+        Some(flags) => match evaluator.eval(flags, ctx)? {
+            Value::String(s) => s,
+            Value::Null => String::new(),
+            other => {
+                return Err(anyhow!(
+                    "Regex flags must be a string, got {}",
+                    helpers::value_type(&other)
+                ));
+            }
+        },
+    };
+    Pattern::new(&pattern, &flags)
+}
+
+/// Evaluate the target, which must be a string for `verb`
+fn target_string(
+    evaluator: &Evaluator,
+    target: &Expression,
+    verb: &str,
+    ctx: &Context,
+) -> Result<String> {
+    match evaluator.eval(target, ctx)? {
+        Value::String(s) => Ok(s),
+        other => Err(anyhow!(
+            "Cannot {} {}, as it is not a string",
+            verb,
+            helpers::value_type(&other)
+        )),
+    }
+}
+
+/// Evaluate test function - whether the regex matches anywhere
+pub fn eval_test(
+    evaluator: &Evaluator,
+    target: &Expression,
+    pattern: &Expression,
+    flags: Option<&Expression>,
+    ctx: &Context,
+) -> Result<Value> {
+    let s = target_string(evaluator, target, "test", ctx)?;
+    let pattern = compile(evaluator, pattern, flags, ctx)?;
+    let found = if pattern.skip_empty {
+        pattern.regex.find_iter(&s).any(|m| !m.is_empty())
+    } else {
+        pattern.regex.is_match(&s)
+    };
+    Ok(Value::Bool(found))
+}
+
+/// Evaluate match function - returns the first match object, or null
+pub fn eval_match(
+    evaluator: &Evaluator,
+    target: &Expression,
+    pattern: &Expression,
+    flags: Option<&Expression>,
+    ctx: &Context,
+) -> Result<Value> {
+    Ok(eval_multi_match(evaluator, target, pattern, flags, ctx)?
+        .into_iter()
+        .next()
+        .unwrap_or(Value::Null))
+}
+
+/// Stream a match object for the first match, or for every match with `g`
+///
+/// Each object has the `offset` and `length` (in codepoints) and `string`
+/// of the match, plus its `captures` with the same fields and a `name`.
+pub fn eval_multi_match(
+    evaluator: &Evaluator,
+    target: &Expression,
+    pattern: &Expression,
+    flags: Option<&Expression>,
+    ctx: &Context,
+) -> Result<Vec<Value>> {
+    let s = target_string(evaluator, target, "match", ctx)?;
+    let pattern = compile(evaluator, pattern, flags, ctx)?;
+    let names: Vec<Option<&str>> = pattern.regex.capture_names().skip(1).collect();
+
+    let mut results = Vec::new();
+    for caps in pattern.regex.captures_iter(&s) {
+        let whole = caps.get_match();
+        if pattern.skip_empty && whole.is_empty() {
+            continue;
+        }
+        let captures = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let name = name.map_or(Value::Null, |n| Value::String(n.to_string()));
+                match caps.get(i + 1) {
+                    Some(m) => match_object(&s, m.start(), m.as_str(), Some(name)),
+                    None => {
+                        let mut obj = serde_yaml::Mapping::new();
+                        obj.insert("offset".into(), Value::Number((-1).into()));
+                        obj.insert("length".into(), Value::Number(0.into()));
+                        obj.insert("string".into(), Value::Null);
+                        obj.insert("name".into(), name);
+                        Value::Mapping(obj)
+                    }
+                }
+            })
+            .collect();
+        let mut obj = match_object(&s, whole.start(), whole.as_str(), None);
+        if let Value::Mapping(map) = &mut obj {
+            map.insert("captures".into(), Value::Sequence(captures));
+        }
+        results.push(obj);
+        if !pattern.global {
+            break;
+        }
+    }
+    Ok(results)
+}
+
+/// Build a match object, converting the byte offset to codepoints
+fn match_object(s: &str, start: usize, text: &str, name: Option<Value>) -> Value {
+    let mut obj = serde_yaml::Mapping::new();
+    obj.insert(
+        "offset".into(),
+        Value::Number(s[..start].chars().count().into()),
+    );
+    obj.insert("length".into(), Value::Number(text.chars().count().into()));
+    obj.insert("string".into(), Value::String(text.to_string()));
+    if let Some(name) = name {
+        obj.insert("name".into(), name);
+    }
+    Value::Mapping(obj)
+}
+
+/// Evaluate sub function - returns the first result of `eval_multi_sub`
+pub fn eval_sub(
+    evaluator: &Evaluator,
+    target: &Expression,
+    pattern: &Expression,
+    replacement: &Expression,
+    flags: Option<&Expression>,
+    ctx: &Context,
+) -> Result<Value> {
+    Ok(
+        eval_multi_sub(evaluator, target, pattern, replacement, flags, ctx)?
+            .into_iter()
+            .next()
+            .unwrap_or(Value::Null),
+    )
+}
+
+/// Stream the target with the first match replaced (every match with `g`)
+pub fn eval_multi_sub(
+    evaluator: &Evaluator,
+    target: &Expression,
+    pattern: &Expression,
+    replacement: &Expression,
+    flags: Option<&Expression>,
+    ctx: &Context,
+) -> Result<Vec<Value>> {
+    let pattern = compile(evaluator, pattern, flags, ctx)?;
+    let global = pattern.global;
+    replace(evaluator, target, pattern, replacement, global, ctx)
+}
+
+/// Evaluate gsub function - returns the first result of `eval_multi_gsub`
+pub fn eval_gsub(
+    evaluator: &Evaluator,
+    target: &Expression,
+    pattern: &Expression,
+    replacement: &Expression,
+    flags: Option<&Expression>,
+    ctx: &Context,
+) -> Result<Value> {
+    Ok(
+        eval_multi_gsub(evaluator, target, pattern, replacement, flags, ctx)?
+            .into_iter()
+            .next()
+            .unwrap_or(Value::Null),
+    )
+}
+
+/// Stream the target with every match replaced
+pub fn eval_multi_gsub(
+    evaluator: &Evaluator,
+    target: &Expression,
+    pattern: &Expression,
+    replacement: &Expression,
+    flags: Option<&Expression>,
+    ctx: &Context,
+) -> Result<Vec<Value>> {
+    let pattern = compile(evaluator, pattern, flags, ctx)?;
+    replace(evaluator, target, pattern, replacement, true, ctx)
+}
+
+/// Replace matches of `pattern` in the target string
+///
+/// The replacement is an expression evaluated once per match, with an
+/// object of the named captures as its input, so `"\(.name)"` refers to
+/// the capture `(?<name>...)`. Each of its outputs must be a string.
+///
+/// As in jq, there is one result per combination of replacement outputs,
+/// varying the last match fastest, so a replacement producing two strings
+/// for each of two matches gives four results and one producing `empty`
+/// gives none.
+///
+/// The results are rebuilt from byte ranges of the input, which the regex
+/// engine only places on character boundaries, so multibyte characters
+/// next to (or inside) a match are never split.
+fn replace(
+    evaluator: &Evaluator,
+    target: &Expression,
+    pattern: Pattern,
+    replacement: &Expression,
+    global: bool,
+    ctx: &Context,
+) -> Result<Vec<Value>> {
+    let s = target_string(evaluator, target, "replace in", ctx)?;
+    let names: Vec<&str> = pattern.regex.capture_names().flatten().collect();
+
+    let mut results = vec![String::with_capacity(s.len())];
+    let mut last = 0;
+    for caps in pattern.regex.captures_iter(&s) {
+        let whole = caps.get_match();
+        if pattern.skip_empty && whole.is_empty() {
+            continue;
+        }
+
+        let mut captures = serde_yaml::Mapping::new();
+        for name in &names {
+            let value = caps
+                .name(name)
+                .map_or(Value::Null, |m| Value::String(m.as_str().to_string()));
+            captures.insert(Value::String(name.to_string()), value);
+        }
+        let replacements = evaluator
+            .eval_multi(replacement, &ctx.child(Value::Mapping(captures)))?
+            .into_iter()
+            .map(|value| match value {
+                Value::String(replacement) => Ok(replacement),
+                other => Err(anyhow!(
+                    "Replacement must be a string, got {}",
+                    helpers::value_type(&other)
+                )),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let gap = &s[last..whole.start()];
+        results = results
+            .iter()
+            .flat_map(|prefix| {
+                replacements
+                    .iter()
+                    .map(move |replacement| format!("{prefix}{gap}{replacement}"))
+            })
+            .collect();
+        last = whole.end();
+        if !global {
+            break;
+        }
+    }
+    Ok(results
+        .into_iter()
+        .map(|result| Value::String(result + &s[last..]))
+        .collect())
+}
+
+/// Evaluate splits function - returns every piece as an array
+pub fn eval_splits(
+    evaluator: &Evaluator,
+    target: &Expression,
+    pattern: &Expression,
+    flags: Option<&Expression>,
+    ctx: &Context,
+) -> Result<Value> {
+    Ok(Value::Sequence(eval_multi_splits(
+        evaluator, target, pattern, flags, ctx,
+    )?))
+}
+
+/// Stream the substrings between matches of the regex
+pub fn eval_multi_splits(
+    evaluator: &Evaluator,
+    target: &Expression,
+    pattern: &Expression,
+    flags: Option<&Expression>,
+    ctx: &Context,
+) -> Result<Vec<Value>> {
+    let s = &target_string(evaluator, target, "split", ctx)?;
+    let pattern = compile(evaluator, pattern, flags, ctx)?;
+
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    for m in pattern.regex.find_iter(s) {
+        if pattern.skip_empty && m.is_empty() {
+            continue;
+        }
+        pieces.push(Value::String(s[start..m.start()].to_string()));
+        start = m.end();
+    }
+    pieces.push(Value::String(s[start..].to_string()));
+    Ok(pieces)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval(expr_str: &str, input: &str) -> Result<Value> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate(&expr, Some(&input_val))
+    }
+
+    fn parse_and_eval_multi(expr_str: &str, input: &str) -> Result<Vec<Value>> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate_multi(&expr, Some(&input_val))
+    }
+
+    fn yaml(s: &str) -> Value {
+        serde_yaml::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_test_matches() {
+        assert_eq!(parse_and_eval(r#"test("b+")"#, "abbc").unwrap(), true);
+        assert_eq!(parse_and_eval(r#"test("^b")"#, "abbc").unwrap(), false);
+    }
+
+    #[test]
+    fn test_test_case_insensitive_flag() {
+        assert_eq!(parse_and_eval(r#"test("abc")"#, "ABC").unwrap(), false);
+        assert_eq!(parse_and_eval(r#"test("abc"; "i")"#, "ABC").unwrap(), true);
+    }
+
+    #[test]
+    fn test_test_multiline_anchoring() {
+        let input = r#""first\nsecond""#;
+        assert_eq!(parse_and_eval(r#"test("^second$")"#, input).unwrap(), false);
+        assert_eq!(
+            parse_and_eval(r#"test("^second$"; "m")"#, input).unwrap(),
+            true
+        );
+    }
+
+    #[test]
+    fn test_test_extended_and_dotall_flags() {
+        assert_eq!(
+            parse_and_eval(r#"test("a b c"; "x")"#, "abc").unwrap(),
+            true
+        );
+        let input = r#""a\nb""#;
+        assert_eq!(parse_and_eval(r#"test("a.b")"#, input).unwrap(), false);
+        assert_eq!(parse_and_eval(r#"test("a.b"; "s")"#, input).unwrap(), true);
+    }
+
+    #[test]
+    fn test_test_non_string_error() {
+        assert!(parse_and_eval(r#"test("a")"#, "1").is_err());
+    }
+
+    #[test]
+    fn test_match_object() {
+        let result = parse_and_eval(r#"match("(?<word>b+)(x)?")"#, "abbc").unwrap();
+        assert_eq!(
+            result,
+            yaml(
+                "{offset: 1, length: 2, string: bb, captures: [\
+                 {offset: 1, length: 2, string: bb, name: word},\
+                 {offset: -1, length: 0, string: null, name: null}]}"
+            )
+        );
+    }
+
+    #[test]
+    fn test_match_global_flag() {
+        let result = parse_and_eval_multi(r#"match("a"; "g") | .offset"#, "abaca").unwrap();
+        assert_eq!(result, vec![yaml("0"), yaml("2"), yaml("4")]);
+
+        let result = parse_and_eval_multi(r#"match("a") | .offset"#, "abaca").unwrap();
+        assert_eq!(result, vec![yaml("0")]);
+    }
+
+    #[test]
+    fn test_match_offsets_in_codepoints() {
+        let result = parse_and_eval(r#"match("b").offset"#, "éb").unwrap();
+        assert_eq!(result, 1);
+    }
+
+    #[test]
+    fn test_match_no_match() {
+        let result = parse_and_eval_multi(r#"match("z")"#, "abc").unwrap();
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn test_sub_replaces_first_match() {
+        let result = parse_and_eval(r#"sub("o"; "0")"#, "foo boo").unwrap();
+        assert_eq!(result, "f0o boo");
+    }
+
+    #[test]
+    fn test_gsub_replaces_every_match() {
+        let result = parse_and_eval(r#"gsub("o"; "0")"#, "foo boo").unwrap();
+        assert_eq!(result, "f00 b00");
+        let result = parse_and_eval(r#"sub("o"; "0"; "g")"#, "foo boo").unwrap();
+        assert_eq!(result, "f00 b00");
+    }
+
+    #[test]
+    fn test_gsub_named_capture_interpolation() {
+        let result = parse_and_eval(
+            r#"gsub("(?<y>[0-9]+)-(?<m>[0-9]+)"; "\(.m)/\(.y)")"#,
+            "2020-01",
+        )
+        .unwrap();
+        assert_eq!(result, "01/2020");
+    }
+
+    #[test]
+    fn test_gsub_captures_per_match() {
+        let result = parse_and_eval(
+            r#"gsub("(?<k>[a-z]+)=(?<v>[0-9]+)"; "\(.v):\(.k)")"#,
+            "a=1, bc=23",
+        )
+        .unwrap();
+        assert_eq!(result, "1:a, 23:bc");
+    }
+
+    #[test]
+    fn test_sub_unmatched_capture_is_null() {
+        let result = parse_and_eval(r#"sub("(?<a>x)?(?<b>y)"; "\(.a)-\(.b)")"#, "y").unwrap();
+        assert_eq!(result, "null-y");
+    }
+
+    #[test]
+    fn test_sub_case_insensitive_flag() {
+        let result = parse_and_eval(r#"gsub("a"; "-"; "i")"#, "AbaB").unwrap();
+        assert_eq!(result, "-b-B");
+    }
+
+    #[test]
+    fn test_sub_next_to_multibyte_characters() {
+        let result = parse_and_eval(r#"sub("l+"; "L")"#, "héllo").unwrap();
+        assert_eq!(result, "héLo");
+        let result = parse_and_eval(r#"sub("é"; "e")"#, "héllo").unwrap();
+        assert_eq!(result, "hello");
+        let result = parse_and_eval(r#"gsub("l"; "ł")"#, "héllo✓").unwrap();
+        assert_eq!(result, "héłło✓");
+    }
+
+    #[test]
+    fn test_gsub_empty_matches_keep_codepoints_whole() {
+        let result = parse_and_eval(r#"gsub(""; "-")"#, "hé✓").unwrap();
+        assert_eq!(result, "-h-é-✓-");
+        let result = parse_and_eval(r#"gsub("[^a-z]"; "?")"#, "héllo✓").unwrap();
+        assert_eq!(result, "h?llo?");
+    }
+
+    #[test]
+    fn test_gsub_multibyte_captures() {
+        let result = parse_and_eval(r#"gsub("(?<c>[^a-z])"; "<\(.c)>")"#, "aé😀b").unwrap();
+        assert_eq!(result, "a<é><😀>b");
+    }
+
+    #[test]
+    fn test_sub_replacement_must_be_string() {
+        assert!(parse_and_eval(r#"sub("a"; 1)"#, "abc").is_err());
+    }
+
+    #[test]
+    fn test_sub_streams_every_replacement() {
+        let result = parse_and_eval_multi(r#"sub("b"; "1", "2")"#, "abc").unwrap();
+        assert_eq!(result, vec![yaml("a1c"), yaml("a2c")]);
+    }
+
+    #[test]
+    fn test_gsub_combines_replacements_across_matches() {
+        let result = parse_and_eval_multi(r#"gsub("x"; "1", "2")"#, "axbxc").unwrap();
+        assert_eq!(
+            result,
+            vec![yaml("a1b1c"), yaml("a1b2c"), yaml("a2b1c"), yaml("a2b2c")]
+        );
+    }
+
+    #[test]
+    fn test_sub_empty_replacement_produces_nothing() {
+        let result = parse_and_eval_multi(r#"sub("b"; empty)"#, "abc").unwrap();
+        assert!(result.is_empty());
+        // Without a match the replacement is never evaluated
+        let result = parse_and_eval_multi(r#"sub("z"; empty)"#, "abc").unwrap();
+        assert_eq!(result, vec![yaml("abc")]);
+    }
+
+    #[test]
+    fn test_splits_streams_pieces() {
+        let result = parse_and_eval(r#"[splits("[0-9]")]"#, "a1b2c").unwrap();
+        let expected: Value = serde_yaml::from_str("[a, b, c]").unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_splits_keeps_empty_pieces() {
+        let result = parse_and_eval(r#"[splits(", *")]"#, "'a, b,,c,'").unwrap();
+        let expected: Value = serde_yaml::from_str("[a, b, '', c, '']").unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_splits_with_flags() {
+        let result = parse_and_eval(r#"[splits("x"; "i")]"#, "aXbxc").unwrap();
+        let expected: Value = serde_yaml::from_str("[a, b, c]").unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_splits_invalid_flag() {
+        assert!(parse_and_eval(r#"[splits("x"; "q")]"#, "axb").is_err());
+    }
+
+    #[test]
+    fn test_splits_invalid_regex() {
+        assert!(parse_and_eval(r#"[splits("(")]"#, "a(b").is_err());
+    }
+
+    #[test]
+    fn test_splits_non_string_error() {
+        assert!(parse_and_eval(r#"[splits("a")]"#, "1").is_err());
+    }
+}
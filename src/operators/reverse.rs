@@ -2,7 +2,7 @@
 
 use crate::evaluator::{Context, Evaluator, helpers};
 use crate::parser::expression::Expression;
-use anyhow::{Result, anyhow};
+use anyhow::Result;
 use serde_yaml::Value;
 
 /// Evaluate reverse function
@@ -18,9 +18,6 @@ pub fn eval(evaluator: &Evaluator, target: &Expression, ctx: &Context) -> Result
             let reversed: String = s.chars().rev().collect();
             Ok(Value::String(reversed))
         }
-        _ => Err(anyhow!(
-            "Cannot reverse {}",
-            helpers::value_type(&target_val)
-        )),
+        _ => Err(helpers::not_an_array("reverse", "reverse", &target_val)),
     }
 }
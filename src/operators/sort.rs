@@ -2,7 +2,7 @@
 
 use crate::evaluator::{Context, Evaluator, helpers};
 use crate::parser::expression::Expression;
-use anyhow::{Result, anyhow};
+use anyhow::Result;
 use serde_yaml::Value;
 
 /// Evaluate sort function
@@ -11,9 +11,9 @@ pub fn eval(evaluator: &Evaluator, target: &Expression, ctx: &Context) -> Result
 
     match target_val {
         Value::Sequence(mut arr) => {
-            arr.sort_by(|a, b| helpers::compare_values(a, b).unwrap_or(std::cmp::Ordering::Equal));
+            arr.sort_by(helpers::compare_values);
             Ok(Value::Sequence(arr))
         }
-        _ => Err(anyhow!("Cannot sort {}", helpers::value_type(&target_val))),
+        _ => Err(helpers::not_an_array("sort", "sort", &target_val)),
     }
 }
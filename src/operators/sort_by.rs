@@ -0,0 +1,106 @@
+//! Sort by function
+
+use crate::evaluator::{Context, Evaluator, helpers};
+use crate::parser::expression::Expression;
+use anyhow::{Result, anyhow};
+use serde_yaml::Value;
+
+/// Evaluate sort_by function
+///
+/// Every output of the key expression becomes part of the element's sort
+/// key, so `sort_by(.a, .b)` sorts by `.a` and then by `.b`. The sort is
+/// stable.
+pub fn eval(
+    evaluator: &Evaluator,
+    target: &Expression,
+    key_expr: &Expression,
+    ctx: &Context,
+) -> Result<Value> {
+    let target_val = evaluator.eval(target, ctx)?;
+
+    match target_val {
+        Value::Sequence(arr) => {
+            let mut keyed = Vec::with_capacity(arr.len());
+            for item in arr {
+                let item_ctx = ctx.child(item.clone());
+                let key = evaluator.eval_multi(key_expr, &item_ctx)?;
+                keyed.push((Value::Sequence(key), item));
+            }
+
+            keyed.sort_by(|(a, _), (b, _)| helpers::compare_values(a, b));
+            Ok(Value::Sequence(
+                keyed.into_iter().map(|(_, item)| item).collect(),
+            ))
+        }
+        _ => Err(anyhow!("Cannot sort {}", helpers::value_type(&target_val))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval(expr_str: &str, input: &str) -> Result<Value> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate(&expr, Some(&input_val))
+    }
+
+    #[test]
+    fn test_sort_by_single_key() {
+        let result = parse_and_eval(
+            "sort_by(.age)",
+            "[{name: b, age: 30}, {name: a, age: 25}, {name: c, age: 35}]",
+        )
+        .unwrap();
+        let expected: Value =
+            serde_yaml::from_str("[{name: a, age: 25}, {name: b, age: 30}, {name: c, age: 35}]")
+                .unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_sort_by_multiple_keys() {
+        let result = parse_and_eval(
+            "sort_by(.a, .b)",
+            "[{a: 2, b: 1}, {a: 1, b: 2}, {a: 1, b: 1}, {a: 2, b: 0}]",
+        )
+        .unwrap();
+        let expected: Value =
+            serde_yaml::from_str("[{a: 1, b: 1}, {a: 1, b: 2}, {a: 2, b: 0}, {a: 2, b: 1}]")
+                .unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_sort_by_is_stable() {
+        let result = parse_and_eval(
+            "sort_by(.k)",
+            "[{k: 1, n: first}, {k: 0, n: x}, {k: 1, n: second}]",
+        )
+        .unwrap();
+        let expected: Value =
+            serde_yaml::from_str("[{k: 0, n: x}, {k: 1, n: first}, {k: 1, n: second}]").unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_sort_by_mixed_types() {
+        let result = parse_and_eval(
+            "sort_by(.k)",
+            "[{k: a}, {k: 1}, {k: true}, {k: null}, {k: false}]",
+        )
+        .unwrap();
+        let expected: Value =
+            serde_yaml::from_str("[{k: null}, {k: false}, {k: true}, {k: 1}, {k: a}]").unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_sort_by_non_array_error() {
+        assert!(parse_and_eval("sort_by(.k)", "{k: 1}").is_err());
+    }
+}
@@ -0,0 +1,102 @@
+//! String split function (split)
+
+use crate::evaluator::{Context, Evaluator, helpers};
+use crate::parser::expression::Expression;
+use anyhow::{Result, anyhow};
+use serde_yaml::Value;
+
+/// Evaluate split function - the pieces of a string between occurrences
+/// of a literal separator
+///
+/// As in jq, an empty string has no pieces and an empty separator splits
+/// the string into its characters; a separator at either end gives an
+/// empty first or last piece (`"a\n" | split("\n")` is `["a", ""]`).
+pub fn eval(
+    evaluator: &Evaluator,
+    target: &Expression,
+    separator: &Expression,
+    ctx: &Context,
+) -> Result<Value> {
+    let target_val = evaluator.eval(target, ctx)?;
+    let separator_val = evaluator.eval(separator, ctx)?;
+
+    let (Value::String(s), Value::String(sep)) = (&target_val, &separator_val) else {
+        return Err(anyhow!(
+            "split requires string input and a string separator, got {} and {}",
+            helpers::value_type(&target_val),
+            helpers::value_type(&separator_val)
+        ));
+    };
+
+    let pieces: Vec<Value> = if s.is_empty() {
+        Vec::new()
+    } else if sep.is_empty() {
+        s.chars().map(|c| Value::String(c.to_string())).collect()
+    } else {
+        s.split(sep.as_str())
+            .map(|piece| Value::String(piece.to_string()))
+            .collect()
+    };
+    Ok(Value::Sequence(pieces))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval(expr_str: &str, input: &str) -> Result<Value> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate(&expr, Some(&input_val))
+    }
+
+    fn yaml(s: &str) -> Value {
+        serde_yaml::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_split_literal() {
+        let result = parse_and_eval(r#"split(", ")"#, "'a, b, c'").unwrap();
+        assert_eq!(result, yaml("[a, b, c]"));
+        // The separator is not a regex
+        let result = parse_and_eval(r#"split(".")"#, "'1.2'").unwrap();
+        assert_eq!(result, yaml("['1', '2']"));
+    }
+
+    #[test]
+    fn test_split_trailing_newline() {
+        let result = parse_and_eval(r#"split("\n")"#, "\"a\\n\\nb\\n\"").unwrap();
+        assert_eq!(result, yaml("[a, '', b, '']"));
+        let result = parse_and_eval(
+            r#"split("\n") | map(select(length > 0))"#,
+            "\"a\\n\\nb\\n\"",
+        )
+        .unwrap();
+        assert_eq!(result, yaml("[a, b]"));
+    }
+
+    #[test]
+    fn test_split_empty_string_and_separator() {
+        let result = parse_and_eval(r#"split(",")"#, "''").unwrap();
+        assert_eq!(result, yaml("[]"));
+        let result = parse_and_eval(r#"split("")"#, "abc").unwrap();
+        assert_eq!(result, yaml("[a, b, c]"));
+    }
+
+    #[test]
+    fn test_split_regex_with_flags() {
+        let result = parse_and_eval(r#"split(", *"; null)"#, "'a, b,c'").unwrap();
+        assert_eq!(result, yaml("[a, b, c]"));
+        let result = parse_and_eval(r#"split("X"; "i")"#, "axbXc").unwrap();
+        assert_eq!(result, yaml("[a, b, c]"));
+    }
+
+    #[test]
+    fn test_split_non_string_error() {
+        let err = parse_and_eval(r#"split(",")"#, "[1]").unwrap_err();
+        assert!(err.to_string().contains("split requires string input"));
+    }
+}
@@ -0,0 +1,181 @@
+//! Streaming form functions (tostream, fromstream)
+//!
+//! The streaming form describes a value as `[path, leaf]` events, one per
+//! scalar or empty container, plus a closing `[path]` event after the last
+//! child of each non-empty container.
+
+use super::path;
+use crate::evaluator::{Context, Evaluator, helpers};
+use crate::parser::expression::Expression;
+use anyhow::{Result, anyhow};
+use serde_yaml::Value;
+
+/// Evaluate tostream function - returns every event as an array
+pub fn eval_tostream(_evaluator: &Evaluator, ctx: &Context) -> Result<Value> {
+    Ok(Value::Sequence(events(&ctx.value)))
+}
+
+/// Stream the events describing the input
+pub fn eval_multi_tostream(ctx: &Context) -> Vec<Value> {
+    events(&ctx.value)
+}
+
+/// Convert a value into its streaming form
+pub fn events(value: &Value) -> Vec<Value> {
+    let mut events = Vec::new();
+    collect_events(value, &mut Vec::new(), &mut events);
+    events
+}
+
+fn collect_events(value: &Value, path: &mut Vec<Value>, events: &mut Vec<Value>) {
+    let children: Vec<(Value, &Value)> = match value {
+        Value::Sequence(arr) => arr
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (Value::Number(i.into()), v))
+            .collect(),
+        Value::Mapping(map) => map.iter().map(|(k, v)| (k.clone(), v)).collect(),
+        _ => Vec::new(),
+    };
+
+    if children.is_empty() {
+        events.push(Value::Sequence(vec![
+            Value::Sequence(path.clone()),
+            value.clone(),
+        ]));
+        return;
+    }
+
+    let mut last = Value::Null;
+    for (key, child) in children {
+        path.push(key);
+        collect_events(child, path, events);
+        last = path.pop().unwrap_or(Value::Null);
+    }
+
+    // Close the container with the path of its last child
+    let mut closing = path.clone();
+    closing.push(last);
+    events.push(Value::Sequence(vec![Value::Sequence(closing)]));
+}
+
+/// Evaluate fromstream function - returns the first reassembled value, or null
+pub fn eval_fromstream(evaluator: &Evaluator, f: &Expression, ctx: &Context) -> Result<Value> {
+    Ok(eval_multi_fromstream(evaluator, f, ctx)?
+        .into_iter()
+        .next()
+        .unwrap_or(Value::Null))
+}
+
+/// Reassemble the events produced by `f` into values
+///
+/// A value is emitted when a top-level leaf event (empty path) or the
+/// closing event of a top-level container (path of length 1) is seen.
+pub fn eval_multi_fromstream(
+    evaluator: &Evaluator,
+    f: &Expression,
+    ctx: &Context,
+) -> Result<Vec<Value>> {
+    let mut results = Vec::new();
+    let mut current = Value::Null;
+
+    for event in evaluator.eval_multi(f, ctx)? {
+        let (path, leaf) = match &event {
+            Value::Sequence(parts) => match parts.as_slice() {
+                [Value::Sequence(path), leaf] => (path, Some(leaf)),
+                [Value::Sequence(path)] => (path, None),
+                _ => return Err(invalid_event(&event)),
+            },
+            _ => return Err(invalid_event(&event)),
+        };
+
+        let done = match leaf {
+            Some(leaf) => {
+                path::set_path(&mut current, path, leaf.clone())?;
+                path.is_empty()
+            }
+            None => path.len() == 1,
+        };
+        if done {
+            results.push(std::mem::replace(&mut current, Value::Null));
+        }
+    }
+
+    Ok(results)
+}
+
+fn invalid_event(event: &Value) -> anyhow::Error {
+    anyhow!(
+        "Invalid stream event: expected [path, leaf] or [path], got {}",
+        helpers::value_type(event)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval_multi(expr_str: &str, input: &str) -> Result<Vec<Value>> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate_multi(&expr, Some(&input_val))
+    }
+
+    fn yaml(s: &str) -> Value {
+        serde_yaml::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_tostream_events() {
+        let result = parse_and_eval_multi("tostream", "{a: [1, 2]}").unwrap();
+        assert_eq!(
+            result,
+            vec![
+                yaml("[[a, 0], 1]"),
+                yaml("[[a, 1], 2]"),
+                yaml("[[a, 1]]"),
+                yaml("[[a]]"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tostream_scalar_and_empty() {
+        assert_eq!(
+            parse_and_eval_multi("tostream", "3").unwrap(),
+            vec![yaml("[[], 3]")]
+        );
+        assert_eq!(
+            parse_and_eval_multi("tostream", "{}").unwrap(),
+            vec![yaml("[[], {}]")]
+        );
+    }
+
+    #[test]
+    fn test_fromstream_round_trip() {
+        for input in [
+            "{a: {b: [1, {c: 2}], d: []}, e: null}",
+            "[[1, 2], {x: [3]}, {}]",
+            "hello",
+            "[]",
+        ] {
+            let result = parse_and_eval_multi("fromstream(tostream)", input).unwrap();
+            assert_eq!(result, vec![yaml(input)], "round trip of {}", input);
+        }
+    }
+
+    #[test]
+    fn test_fromstream_multiple_values() {
+        let result =
+            parse_and_eval_multi("fromstream(.[] | tostream)", "[{a: 1}, 2, [3]]").unwrap();
+        assert_eq!(result, vec![yaml("{a: 1}"), yaml("2"), yaml("[3]")]);
+    }
+
+    #[test]
+    fn test_fromstream_invalid_event() {
+        assert!(parse_and_eval_multi("fromstream(.[])", "[1]").is_err());
+    }
+}
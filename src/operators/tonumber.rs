@@ -6,6 +6,9 @@ use anyhow::{Result, anyhow};
 use serde_yaml::Value;
 
 /// Evaluate tonumber function
+///
+/// Numbers pass through unchanged. Strings must contain a finite number and
+/// nothing else: surrounding whitespace, `nan` and `inf` are rejected like jq.
 pub fn eval(evaluator: &Evaluator, target: &Expression, ctx: &Context) -> Result<Value> {
     let target_val = evaluator.eval(target, ctx)?;
 
@@ -14,15 +17,74 @@ pub fn eval(evaluator: &Evaluator, target: &Expression, ctx: &Context) -> Result
         Value::String(s) => {
             if let Ok(i) = s.parse::<i64>() {
                 Ok(Value::Number(i.into()))
-            } else if let Ok(f) = s.parse::<f64>() {
+            } else if let Some(f) = s.parse::<f64>().ok().filter(|f| f.is_finite()) {
                 Ok(Value::Number(serde_yaml::Number::from(f)))
             } else {
                 Err(anyhow!("Cannot parse '{}' as number", s))
             }
         }
+        Value::Bool(b) => Err(anyhow!("Cannot convert boolean ({}) to number", b)),
         _ => Err(anyhow!(
             "Cannot convert {} to number",
             helpers::value_type(&target_val)
         )),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval(expr_str: &str, input: &str) -> Result<Value> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate(&expr, Some(&input_val))
+    }
+
+    #[test]
+    fn test_tonumber_strings() {
+        assert_eq!(parse_and_eval("tonumber", "\"42\"").unwrap(), 42);
+        assert_eq!(parse_and_eval("tonumber", "\"1.5\"").unwrap(), 1.5);
+        assert_eq!(parse_and_eval("tonumber", "7").unwrap(), 7);
+    }
+
+    #[test]
+    fn test_tonumber_rejects_malformed_strings() {
+        assert!(parse_and_eval("tonumber", "\" 5 \"").is_err());
+        assert!(parse_and_eval("tonumber", "\"1.2.3\"").is_err());
+        assert!(parse_and_eval("tonumber", "\"nan\"").is_err());
+        assert!(parse_and_eval("tonumber", "\"inf\"").is_err());
+        assert!(parse_and_eval("tonumber", "\"\"").is_err());
+    }
+
+    #[test]
+    fn test_tonumber_boolean_error() {
+        let err = parse_and_eval("tonumber", "true").unwrap_err();
+        assert!(err.to_string().contains("boolean"));
+    }
+
+    #[test]
+    fn test_tonumber_optional_drops_failures() {
+        let result = parse_and_eval("map(tonumber?)", r#"["1", "x", "3"]"#).unwrap();
+        let expected: Value = serde_yaml::from_str("[1, 3]").unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_tonumber_optional_with_default() {
+        let result = parse_and_eval("map(tonumber? // 0)", r#"["1", "x", "3"]"#).unwrap();
+        let expected: Value = serde_yaml::from_str("[1, 0, 3]").unwrap();
+        assert_eq!(result, expected);
+
+        let result = parse_and_eval(
+            "map(.num | tonumber? // 0)",
+            r#"[{num: "2"}, {num: "n/a"}, {}]"#,
+        )
+        .unwrap();
+        let expected: Value = serde_yaml::from_str("[2, 0, 0]").unwrap();
+        assert_eq!(result, expected);
+    }
+}
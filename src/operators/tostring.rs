@@ -6,7 +6,41 @@ use anyhow::Result;
 use serde_yaml::Value;
 
 /// Evaluate tostring function
+///
+/// Strings are returned unchanged; every other value is JSON-encoded.
 pub fn eval(evaluator: &Evaluator, target: &Expression, ctx: &Context) -> Result<Value> {
     let target_val = evaluator.eval(target, ctx)?;
     Ok(Value::String(helpers::value_to_string(&target_val)))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval(expr_str: &str, input: &str) -> Result<Value> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate(&expr, Some(&input_val))
+    }
+
+    #[test]
+    fn test_tostring_scalars() {
+        assert_eq!(parse_and_eval("tostring", "abc").unwrap(), "abc");
+        assert_eq!(parse_and_eval("tostring", "42").unwrap(), "42");
+        assert_eq!(parse_and_eval("tostring", "1.5").unwrap(), "1.5");
+        assert_eq!(parse_and_eval("tostring", "true").unwrap(), "true");
+        assert_eq!(parse_and_eval("tostring", "null").unwrap(), "null");
+    }
+
+    #[test]
+    fn test_tostring_json_encodes_collections() {
+        assert_eq!(parse_and_eval("tostring", "[1, 2]").unwrap(), "[1,2]");
+        assert_eq!(
+            parse_and_eval("tostring", "{a: x}").unwrap(),
+            "{\"a\":\"x\"}"
+        );
+    }
+}
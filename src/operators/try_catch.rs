@@ -0,0 +1,74 @@
+//! Try-catch and the optional `?` suffix
+
+use crate::evaluator::{Context, Evaluator};
+use crate::parser::expression::Expression;
+use anyhow::Result;
+use serde_yaml::Value;
+
+/// Evaluate try expression
+///
+/// On error the handler, if any, runs with the error message as input;
+/// without a handler the error is suppressed and null is returned.
+pub fn eval(
+    evaluator: &Evaluator,
+    expr: &Expression,
+    catch: Option<&Expression>,
+    ctx: &Context,
+) -> Result<Value> {
+    match evaluator.eval(expr, ctx) {
+        Ok(value) => Ok(value),
+        Err(err) => match catch {
+            Some(handler) => {
+                let err_ctx = ctx.child(Value::String(err.to_string()));
+                evaluator.eval(handler, &err_ctx)
+            }
+            None => Ok(Value::Null),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval(expr_str: &str, input: &str) -> Result<Value> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate(&expr, Some(&input_val))
+    }
+
+    fn parse_and_eval_multi(expr_str: &str, input: &str) -> Result<Vec<Value>> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let ctx = Context::new(serde_yaml::from_str(input)?);
+        evaluator.eval_multi(&expr, &ctx)
+    }
+
+    #[test]
+    fn test_optional_field_on_wrong_type_is_null() {
+        assert!(parse_and_eval(".name", "[1, 2]").is_err());
+        assert_eq!(parse_and_eval(".name?", "[1, 2]").unwrap(), Value::Null);
+    }
+
+    #[test]
+    fn test_optional_index_on_wrong_type_is_null() {
+        assert_eq!(parse_and_eval(".[0]?", "{a: 1}").unwrap(), Value::Null);
+        assert_eq!(parse_and_eval(".a[0]?", "{a: x}").unwrap(), Value::Null);
+    }
+
+    #[test]
+    fn test_optional_access_over_mixed_array() {
+        let results =
+            parse_and_eval_multi(".[] | .name?", "[{name: a}, 1, {name: b}, text, [x]]").unwrap();
+        assert_eq!(results, vec![Value::from("a"), Value::from("b")]);
+    }
+
+    #[test]
+    fn test_optional_access_keeps_valid_results() {
+        assert_eq!(parse_and_eval(".a.b?", "{a: {b: 1}}").unwrap(), 1);
+    }
+}
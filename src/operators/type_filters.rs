@@ -0,0 +1,106 @@
+//! Type-selecting filters (values, numbers, strings, ...)
+
+use crate::evaluator::{Context, helpers};
+use serde_yaml::Value;
+
+/// Whether `value` passes the type filter called `name`
+pub fn matches(name: &str, value: &Value) -> bool {
+    let ty = helpers::value_type(value);
+    match name {
+        "values" => ty != "null",
+        "nulls" => ty == "null",
+        "booleans" => ty == "boolean",
+        "numbers" => ty == "number",
+        "strings" => ty == "string",
+        "arrays" => ty == "array",
+        "objects" => ty == "object",
+        "iterables" => ty == "array" || ty == "object",
+        "scalars" => ty != "array" && ty != "object",
+        _ => false,
+    }
+}
+
+/// Evaluate a type filter - the input if it matches, otherwise null
+pub fn eval(name: &str, ctx: &Context) -> Value {
+    if matches(name, &ctx.value) {
+        ctx.value.clone()
+    } else {
+        Value::Null
+    }
+}
+
+/// Evaluate a type filter as a stream - no output when the type does not match
+pub fn eval_multi(name: &str, ctx: &Context) -> Vec<Value> {
+    if matches(name, &ctx.value) {
+        vec![ctx.value.clone()]
+    } else {
+        vec![]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::Evaluator;
+    use crate::parser::expression::ExpressionParser;
+    use anyhow::Result;
+
+    fn parse_and_eval_multi(expr_str: &str, input: &str) -> Result<Vec<Value>> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let ctx = Context::new(serde_yaml::from_str(input)?);
+        evaluator.eval_multi(&expr, &ctx)
+    }
+
+    const MIXED: &str = "[1, a, null, true, [2], {k: v}, 3.5]";
+
+    #[test]
+    fn test_numbers() {
+        let result = parse_and_eval_multi(".[] | numbers", MIXED).unwrap();
+        assert_eq!(result, vec![Value::from(1), Value::from(3.5)]);
+    }
+
+    #[test]
+    fn test_strings_and_booleans() {
+        assert_eq!(
+            parse_and_eval_multi(".[] | strings", MIXED).unwrap(),
+            vec![Value::from("a")]
+        );
+        assert_eq!(
+            parse_and_eval_multi(".[] | booleans", MIXED).unwrap(),
+            vec![Value::from(true)]
+        );
+    }
+
+    #[test]
+    fn test_values_and_nulls() {
+        assert_eq!(
+            parse_and_eval_multi(".[] | values", MIXED).unwrap().len(),
+            6
+        );
+        assert_eq!(
+            parse_and_eval_multi(".[] | nulls", MIXED).unwrap(),
+            vec![Value::Null]
+        );
+    }
+
+    #[test]
+    fn test_iterables_and_scalars() {
+        let iterables = parse_and_eval_multi(".[] | iterables", MIXED).unwrap();
+        let expected: Vec<Value> = serde_yaml::from_str("[[2], {k: v}]").unwrap();
+        assert_eq!(iterables, expected);
+        assert_eq!(
+            parse_and_eval_multi(".[] | scalars", MIXED).unwrap().len(),
+            5
+        );
+        assert_eq!(
+            parse_and_eval_multi(".[] | arrays", MIXED).unwrap().len(),
+            1
+        );
+        assert_eq!(
+            parse_and_eval_multi(".[] | objects", MIXED).unwrap().len(),
+            1
+        );
+    }
+}
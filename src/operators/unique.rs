@@ -1,29 +1,54 @@
-//! Unique function
+//! Unique functions
 
 use crate::evaluator::{Context, Evaluator, helpers};
 use crate::parser::expression::Expression;
 use anyhow::{Result, anyhow};
 use serde_yaml::Value;
-use std::collections::HashSet;
 
-/// Evaluate unique function
+/// Evaluate unique function - sorts the array and removes duplicates
 pub fn eval(evaluator: &Evaluator, target: &Expression, ctx: &Context) -> Result<Value> {
     let target_val = evaluator.eval(target, ctx)?;
 
     match target_val {
-        Value::Sequence(arr) => {
-            let mut seen = HashSet::new();
-            let mut unique = Vec::new();
+        Value::Sequence(mut arr) => {
+            arr.sort_by(helpers::compare_values);
+            arr.dedup_by(|a, b| helpers::compare_values(a, b).is_eq());
+            Ok(Value::Sequence(arr))
+        }
+        _ => Err(helpers::not_an_array(
+            "get unique of",
+            "unique",
+            &target_val,
+        )),
+    }
+}
 
+/// Evaluate unique_by function
+///
+/// Keeps the first element for each distinct key, ordered by key as in jq.
+pub fn eval_by(
+    evaluator: &Evaluator,
+    target: &Expression,
+    key_expr: &Expression,
+    ctx: &Context,
+) -> Result<Value> {
+    let target_val = evaluator.eval(target, ctx)?;
+
+    match target_val {
+        Value::Sequence(arr) => {
+            let mut keyed = Vec::with_capacity(arr.len());
             for item in arr {
-                // Use JSON string representation for comparison
-                let key = serde_json::to_string(&item).unwrap_or_default();
-                if seen.insert(key) {
-                    unique.push(item);
-                }
+                let item_ctx = ctx.child(item.clone());
+                let key = evaluator.eval_multi(key_expr, &item_ctx)?;
+                keyed.push((Value::Sequence(key), item));
             }
 
-            Ok(Value::Sequence(unique))
+            // Stable sort keeps the first element of each key in front
+            keyed.sort_by(|(a, _), (b, _)| helpers::compare_values(a, b));
+            keyed.dedup_by(|(a, _), (b, _)| helpers::compare_values(a, b).is_eq());
+            Ok(Value::Sequence(
+                keyed.into_iter().map(|(_, item)| item).collect(),
+            ))
         }
         _ => Err(anyhow!(
             "Cannot get unique of {}",
@@ -31,3 +56,42 @@ pub fn eval(evaluator: &Evaluator, target: &Expression, ctx: &Context) -> Result
         )),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval(expr_str: &str, input: &str) -> Result<Value> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate(&expr, Some(&input_val))
+    }
+
+    #[test]
+    fn test_unique_sorts_output() {
+        let result = parse_and_eval("unique", "[3, 1, b, 3, a, 1, null, [1], [1]]").unwrap();
+        let expected: Value = serde_yaml::from_str("[null, 1, 3, a, b, [1]]").unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_unique_by_keeps_first_per_key() {
+        let result = parse_and_eval(
+            "unique_by(.id)",
+            "[{id: 2, n: a}, {id: 1, n: b}, {id: 2, n: c}, {id: 1, n: d}]",
+        )
+        .unwrap();
+        let expected: Value = serde_yaml::from_str("[{id: 1, n: b}, {id: 2, n: a}]").unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_unique_by_length() {
+        let result = parse_and_eval("unique_by(length)", "[ab, c, de, fgh]").unwrap();
+        let expected: Value = serde_yaml::from_str("[c, ab, fgh]").unwrap();
+        assert_eq!(result, expected);
+    }
+}
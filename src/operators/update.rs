@@ -1,39 +1,150 @@
 //! Update assignment operator (|=)
 
+use super::path;
 use crate::evaluator::{Context, Evaluator};
 use crate::parser::expression::Expression;
 use anyhow::{Result, anyhow};
 use serde_yaml::Value;
 
 /// Evaluate update assignment
+///
+/// For every path the target addresses, the right-hand side is evaluated
+/// with the current value at that path as input and the result is stored
+/// back. A missing path is updated from `null` and created, as in jq.
 pub fn eval(
     evaluator: &Evaluator,
     target: &Expression,
     value: &Expression,
     ctx: &Context,
 ) -> Result<Value> {
-    match target {
-        Expression::FieldAccess { target: _, field } => {
-            // Get current value
-            let current = if let Value::Mapping(map) = &ctx.value {
-                map.get(Value::String(field.clone()))
-                    .cloned()
-                    .unwrap_or(Value::Null)
-            } else {
-                Value::Null
-            };
-
-            // Evaluate RHS with current value as context
-            let child_ctx = ctx.child(current);
-            let new_value = evaluator.eval(value, &child_ctx)?;
-
-            // Return updated object
-            let mut result = ctx.value.clone();
-            if let Value::Mapping(ref mut map) = result {
-                map.insert(Value::String(field.clone()), new_value);
-            }
-            Ok(result)
-        }
-        _ => Err(anyhow!("Update target must be a field access")),
+    let mut result = ctx.value.clone();
+    for p in path::paths(evaluator, target, ctx)? {
+        let current = path::get_path(&result, &p);
+        let new_value = evaluator.eval(value, &ctx.child(current))?;
+        path::set_path(&mut result, &p, new_value)?;
+    }
+    Ok(result)
+}
+
+/// Evaluate arithmetic update assignment (`+=`, `-=`, `*=`, `/=`, `%=`, `//=`)
+///
+/// As in jq, `a op= b` evaluates `b` once against the input (not against
+/// the value at the path), then combines it with the value at every path
+/// the target addresses.
+pub fn eval_arithmetic(
+    evaluator: &Evaluator,
+    target: &Expression,
+    op: &str,
+    value: &Expression,
+    ctx: &Context,
+) -> Result<Value> {
+    let rhs = Box::new(Expression::Literal(evaluator.eval(value, ctx)?));
+
+    let mut result = ctx.value.clone();
+    for p in path::paths(evaluator, target, ctx)? {
+        let left = Box::new(Expression::Literal(path::get_path(&result, &p)));
+        let right = rhs.clone();
+        let combined = match op {
+            "+" => Expression::Add { left, right },
+            "-" => Expression::Subtract { left, right },
+            "*" => Expression::Multiply { left, right },
+            "/" => Expression::Divide { left, right },
+            "%" => Expression::Modulo { left, right },
+            "//" => Expression::Alternative { left, right },
+            _ => return Err(anyhow!("Unknown update operator: {}=", op)),
+        };
+        path::set_path(&mut result, &p, evaluator.eval(&combined, ctx)?)?;
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval(expr_str: &str, input: &str) -> Result<Value> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate(&expr, Some(&input_val))
+    }
+
+    fn yaml(s: &str) -> Value {
+        serde_yaml::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_update_top_level_field() {
+        let result = parse_and_eval(".count |= . + 1", "{count: 5}").unwrap();
+        assert_eq!(result, yaml("{count: 6}"));
+    }
+
+    #[test]
+    fn test_update_nested_field() {
+        let result = parse_and_eval(".a.b |= . + 1", "{a: {b: 1, c: 2}}").unwrap();
+        assert_eq!(result, yaml("{a: {b: 2, c: 2}}"));
+    }
+
+    #[test]
+    fn test_update_array_element() {
+        let result =
+            parse_and_eval(".items[0].qty |= . * 2", "{items: [{qty: 3}, {qty: 4}]}").unwrap();
+        assert_eq!(result, yaml("{items: [{qty: 6}, {qty: 4}]}"));
+    }
+
+    #[test]
+    fn test_update_every_iterated_element() {
+        let result = parse_and_eval(".[] |= . * 10", "[1, 2, 3]").unwrap();
+        assert_eq!(result, yaml("[10, 20, 30]"));
+    }
+
+    #[test]
+    fn test_update_missing_path_starts_from_null() {
+        let result = parse_and_eval(r#".a.b |= "x""#, "{}").unwrap();
+        assert_eq!(result, yaml("{a: {b: x}}"));
+
+        let result = parse_and_eval(".a.n |= . // 0", "{}").unwrap();
+        assert_eq!(result, yaml("{a: {n: 0}}"));
+    }
+
+    #[test]
+    fn test_arithmetic_update_add() {
+        let result = parse_and_eval(".count += 1", "{count: 5}").unwrap();
+        assert_eq!(result, yaml("{count: 6}"));
+    }
+
+    #[test]
+    fn test_arithmetic_update_multiply_float() {
+        let result = parse_and_eval(".price *= 1.5", "{price: 10}").unwrap();
+        assert_eq!(result, yaml("{price: 15.0}"));
+    }
+
+    #[test]
+    fn test_arithmetic_update_alternative() {
+        let result = parse_and_eval(r#".name //= "anon""#, "{}").unwrap();
+        assert_eq!(result, yaml("{name: anon}"));
+        let result = parse_and_eval(r#".name //= "anon""#, "{name: bob}").unwrap();
+        assert_eq!(result, yaml("{name: bob}"));
+    }
+
+    #[test]
+    fn test_arithmetic_update_other_operators() {
+        let result = parse_and_eval(".[] -= 1", "[5, 6]").unwrap();
+        assert_eq!(result, yaml("[4, 5]"));
+        let result = parse_and_eval(".a /= 2 | .b %= 3", "{a: 8, b: 7}").unwrap();
+        assert_eq!(result, yaml("{a: 4, b: 1}"));
+    }
+
+    #[test]
+    fn test_arithmetic_update_rhs_uses_input() {
+        let result = parse_and_eval(".a += .step", "{a: 1, step: 10}").unwrap();
+        assert_eq!(result, yaml("{a: 11, step: 10}"));
+    }
+
+    #[test]
+    fn test_update_through_scalar_error() {
+        assert!(parse_and_eval(".a.b |= 1", "{a: 1}").is_err());
     }
 }
@@ -0,0 +1,92 @@
+//! Walk function
+
+use crate::evaluator::{Context, Evaluator};
+use crate::parser::expression::Expression;
+use anyhow::Result;
+use serde_yaml::{Mapping, Value};
+
+/// Evaluate walk function - returns the first result of the walk
+pub fn eval(evaluator: &Evaluator, f: &Expression, ctx: &Context) -> Result<Value> {
+    Ok(eval_multi(evaluator, f, ctx)?
+        .into_iter()
+        .next()
+        .unwrap_or(Value::Null))
+}
+
+/// Apply `f` to every node bottom-up
+///
+/// Children are transformed first and their container rebuilt before `f`
+/// runs on it. As in jq, array elements keep every output of `f`, while
+/// object values keep the first output and are dropped if there is none.
+pub fn eval_multi(evaluator: &Evaluator, f: &Expression, ctx: &Context) -> Result<Vec<Value>> {
+    walk(evaluator, f, ctx.value.clone(), ctx)
+}
+
+fn walk(evaluator: &Evaluator, f: &Expression, value: Value, ctx: &Context) -> Result<Vec<Value>> {
+    let rebuilt = match value {
+        Value::Sequence(arr) => {
+            let mut result = Vec::with_capacity(arr.len());
+            for item in arr {
+                result.extend(walk(evaluator, f, item, ctx)?);
+            }
+            Value::Sequence(result)
+        }
+        Value::Mapping(map) => {
+            let mut result = Mapping::new();
+            for (key, v) in map {
+                if let Some(walked) = walk(evaluator, f, v, ctx)?.into_iter().next() {
+                    result.insert(key, walked);
+                }
+            }
+            Value::Mapping(result)
+        }
+        other => other,
+    };
+
+    evaluator.eval_multi(f, &ctx.child(rebuilt))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expression::ExpressionParser;
+
+    fn parse_and_eval(expr_str: &str, input: &str) -> Result<Value> {
+        let parser = ExpressionParser::new();
+        let evaluator = Evaluator::new();
+        let expr = parser.parse(expr_str)?;
+        let input_val = serde_yaml::from_str(input)?;
+        evaluator.evaluate(&expr, Some(&input_val))
+    }
+
+    #[test]
+    fn test_walk_increments_numbers_at_any_depth() {
+        let result = parse_and_eval(
+            "walk(if type == \"number\" then . + 1 else . end)",
+            "{a: 1, b: {c: [1, 2, {d: 3}], e: x}, f: [[4]]}",
+        )
+        .unwrap();
+        let expected: Value =
+            serde_yaml::from_str("{a: 2, b: {c: [2, 3, {d: 4}], e: x}, f: [[5]]}").unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_walk_is_bottom_up() {
+        // Inner arrays are already sorted when the outer one is
+        let result = parse_and_eval(
+            "walk(if type == \"array\" then sort else . end)",
+            "[[3, 1], [2, 0]]",
+        )
+        .unwrap();
+        let expected: Value = serde_yaml::from_str("[[0, 2], [1, 3]]").unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_walk_preserves_key_order() {
+        let result = parse_and_eval("walk(.)", "{z: 1, a: {y: 2, b: 3}}").unwrap();
+        let output = serde_yaml::to_string(&result).unwrap();
+        assert_eq!(output, "z: 1\na:\n  y: 2\n  b: 3\n");
+    }
+}
@@ -4,14 +4,26 @@
 
 #![allow(dead_code)]
 
+use crate::evaluator::helpers;
+use crate::parser::input::DATETIME_TAG;
 use anyhow::{Context, Result, anyhow};
+use serde::Serialize;
 use serde_yaml::Value;
 
+/// Indentation used for block-style output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indent {
+    /// Indent with the given number of spaces; zero means single-line output
+    Spaces(usize),
+    /// Indent JSON with tabs (YAML forbids tabs and keeps two spaces)
+    Tab,
+}
+
 /// Output format options
 #[derive(Debug, Clone)]
 pub struct OutputOptions {
-    /// Indentation level
-    pub indent: usize,
+    /// Indentation
+    pub indent: Indent,
     /// Pretty print
     pub pretty_print: bool,
     /// Unwrap scalar values
@@ -20,6 +32,39 @@ pub struct OutputOptions {
     pub no_doc: bool,
     /// Use colors
     pub colors: bool,
+    /// Sort object keys recursively
+    pub sort_keys: bool,
+    /// Emit each value on a single line
+    pub compact: bool,
+    /// Render YAML in flow style
+    pub flow: bool,
+    /// Prefix each JSON value with an RS character (RFC 7464)
+    pub seq: bool,
+    /// Escape non-ASCII characters in JSON output as `\uXXXX`
+    pub ascii_output: bool,
+    /// Start CSV/TSV output from an array of objects with a header row
+    pub csv_headers: bool,
+    /// Double-quote every string scalar in YAML output
+    pub quote_strings: bool,
+}
+
+impl Default for OutputOptions {
+    fn default() -> Self {
+        Self {
+            indent: Indent::Spaces(2),
+            pretty_print: false,
+            unwrap_scalar: false,
+            no_doc: false,
+            colors: false,
+            sort_keys: false,
+            compact: false,
+            flow: false,
+            seq: false,
+            ascii_output: false,
+            csv_headers: true,
+            quote_strings: false,
+        }
+    }
 }
 
 /// Format output value
@@ -28,20 +73,141 @@ pub fn format_output(
     format: crate::OutputFormat,
     options: OutputOptions,
 ) -> Result<String> {
+    let mut value = normalize_numbers(value.clone());
+    if !matches!(format, crate::OutputFormat::Toml) {
+        value = untag_datetimes(value);
+    }
+    let value = &if options.sort_keys {
+        sort_keys(value)
+    } else {
+        value
+    };
+
     match format {
         crate::OutputFormat::Yaml => format_yaml(value, &options),
         crate::OutputFormat::Json => format_json(value, &options),
         crate::OutputFormat::Toml => format_toml(value, &options),
+        crate::OutputFormat::Csv => format_csv(value, &options),
+        crate::OutputFormat::Tsv => format_tsv(value, &options),
         crate::OutputFormat::Auto => format_yaml(value, &options),
     }
 }
 
+/// Render integral floats as integers so `1.0`, `4 / 2` and `2` print alike
+///
+/// Floats beyond 2^53 are left alone since they no longer represent exact
+/// integers. Other floats keep Rust's shortest round-trip formatting.
+fn normalize_numbers(value: Value) -> Value {
+    match value {
+        Value::Number(n) if n.is_f64() => {
+            let f = n.as_f64().unwrap_or(0.0);
+            if f.fract() == 0.0 && f.abs() < 9_007_199_254_740_992.0 {
+                Value::Number((f as i64).into())
+            } else {
+                Value::Number(n)
+            }
+        }
+        Value::Mapping(map) => Value::Mapping(
+            map.into_iter()
+                .map(|(k, v)| (k, normalize_numbers(v)))
+                .collect(),
+        ),
+        Value::Sequence(arr) => Value::Sequence(arr.into_iter().map(normalize_numbers).collect()),
+        Value::Tagged(mut tagged) => {
+            tagged.value = normalize_numbers(tagged.value);
+            Value::Tagged(tagged)
+        }
+        other => other,
+    }
+}
+
+/// Whether a value holds a TOML datetime anywhere
+pub fn has_datetimes(value: &Value) -> bool {
+    match value {
+        Value::Tagged(tagged) => tagged.tag == DATETIME_TAG || has_datetimes(&tagged.value),
+        Value::Mapping(map) => map.values().any(has_datetimes),
+        Value::Sequence(arr) => arr.iter().any(has_datetimes),
+        _ => false,
+    }
+}
+
+/// Turn TOML datetimes into plain strings, for expressions (which treat
+/// them as strings) and for formats without a datetime type
+pub fn untag_datetimes(value: Value) -> Value {
+    match value {
+        Value::Tagged(tagged) if tagged.tag == DATETIME_TAG => tagged.value,
+        Value::Tagged(mut tagged) => {
+            tagged.value = untag_datetimes(tagged.value);
+            Value::Tagged(tagged)
+        }
+        Value::Mapping(map) => Value::Mapping(
+            map.into_iter()
+                .map(|(k, v)| (k, untag_datetimes(v)))
+                .collect(),
+        ),
+        Value::Sequence(arr) => Value::Sequence(arr.into_iter().map(untag_datetimes).collect()),
+        other => other,
+    }
+}
+
+/// Give back the datetime type to strings that are still where `original`
+/// had a TOML datetime with the same text
+///
+/// Expressions see datetimes as plain strings, so TOML output restores
+/// them by position: a datetime left in place (as by `.` or an update of
+/// another key) is written as a datetime again, while one that was moved
+/// or computed stays a string.
+pub fn restore_datetimes(value: Value, original: &Value) -> Value {
+    match (value, original) {
+        (Value::String(s), Value::Tagged(tagged))
+            if tagged.tag == DATETIME_TAG && tagged.value.as_str() == Some(s.as_str()) =>
+        {
+            Value::Tagged(tagged.clone())
+        }
+        (Value::Mapping(map), Value::Mapping(original)) => Value::Mapping(
+            map.into_iter()
+                .map(|(k, v)| {
+                    let v = match original.get(&k) {
+                        Some(o) => restore_datetimes(v, o),
+                        None => v,
+                    };
+                    (k, v)
+                })
+                .collect(),
+        ),
+        (Value::Sequence(arr), Value::Sequence(original)) => Value::Sequence(
+            arr.into_iter()
+                .enumerate()
+                .map(|(i, v)| match original.get(i) {
+                    Some(o) => restore_datetimes(v, o),
+                    None => v,
+                })
+                .collect(),
+        ),
+        (value, _) => value,
+    }
+}
+
+/// Recursively sort the keys of every object, including objects nested in arrays
+fn sort_keys(value: Value) -> Value {
+    match value {
+        Value::Mapping(map) => {
+            let mut entries: Vec<(Value, Value)> =
+                map.into_iter().map(|(k, v)| (k, sort_keys(v))).collect();
+            entries.sort_by(|(a, _), (b, _)| helpers::compare_values(a, b));
+            Value::Mapping(entries.into_iter().collect())
+        }
+        Value::Sequence(arr) => Value::Sequence(arr.into_iter().map(sort_keys).collect()),
+        other => other,
+    }
+}
+
 /// Format as YAML
 fn format_yaml(value: &Value, options: &OutputOptions) -> Result<String> {
     // Handle unwrapped scalars
     if options.unwrap_scalar {
         match value {
-            Value::String(s) => {
+            Value::String(s) if !options.quote_strings => {
                 return Ok(s.clone());
             }
             Value::Number(n) => {
@@ -59,15 +225,36 @@ fn format_yaml(value: &Value, options: &OutputOptions) -> Result<String> {
 
     let mut output = String::new();
 
-    // Add document separator if not disabled
-    if !options.no_doc && !matches!(value, Value::Null) {
+    // Start every document with a separator, so consecutive documents
+    // stay apart, unless disabled
+    if !options.no_doc {
         output.push_str("---\n");
     }
 
     // Serialize YAML
-    let yaml_str = serde_yaml::to_string(value).context("Failed to serialize YAML")?;
-
-    output.push_str(&yaml_str);
+    let width = match options.indent {
+        Indent::Spaces(n) => n,
+        Indent::Tab => 2,
+    };
+    if options.compact || options.flow || width == 0 {
+        output.push_str(&to_flow_yaml(value, options.quote_strings)?);
+        output.push('\n');
+    } else if options.quote_strings {
+        let mut yaml_str = String::new();
+        for line in to_quoted_yaml(value)? {
+            yaml_str.push_str(&line);
+            yaml_str.push('\n');
+        }
+        output.push_str(&reindent_yaml(&yaml_str, width));
+    } else {
+        let yaml_str = serde_yaml::to_string(value).context("Failed to serialize YAML")?;
+        let yaml_str = if options.no_doc {
+            strip_document_markers(&yaml_str)
+        } else {
+            yaml_str
+        };
+        output.push_str(&reindent_yaml(&yaml_str, width));
+    }
 
     // Apply colors if requested
     if options.colors {
@@ -77,30 +264,504 @@ fn format_yaml(value: &Value, options: &OutputOptions) -> Result<String> {
     Ok(output)
 }
 
+/// Remove `---` and `...` document markers emitted by the serializer
+///
+/// Markers always start at column 0, so indented block scalar content that
+/// happens to read `---` is left alone.
+fn strip_document_markers(yaml: &str) -> String {
+    let mut output = String::with_capacity(yaml.len());
+    for line in yaml.lines() {
+        if let Some(rest) = line
+            .strip_prefix("---")
+            .or_else(|| line.strip_prefix("..."))
+            .filter(|rest| rest.is_empty() || rest.starts_with(' '))
+        {
+            // `--- value` keeps the value on its own line
+            let rest = rest.trim_start();
+            if !rest.is_empty() {
+                output.push_str(rest);
+                output.push('\n');
+            }
+            continue;
+        }
+        output.push_str(line);
+        output.push('\n');
+    }
+    output
+}
+
+/// Render a value as block-style YAML lines with every string double-quoted
+///
+/// The layout matches serde_yaml's two-space block output (a sequence
+/// under a key is not indented, a collection in a sequence starts on the
+/// line of its `- `), so it can be re-indented the same way. Strings are
+/// written by `double_quoted`, so a string such as `no` or `0123` can't be
+/// read back as another type.
+fn to_quoted_yaml(value: &Value) -> Result<Vec<String>> {
+    let mut lines = Vec::new();
+    match value {
+        Value::Mapping(map) if !map.is_empty() => {
+            for (k, v) in map {
+                let key = to_flow_yaml(k, true)?;
+                match v {
+                    Value::Mapping(m) if !m.is_empty() => {
+                        lines.push(format!("{}:", key));
+                        lines.extend(to_quoted_yaml(v)?.into_iter().map(|l| format!("  {}", l)));
+                    }
+                    Value::Sequence(s) if !s.is_empty() => {
+                        lines.push(format!("{}:", key));
+                        lines.extend(to_quoted_yaml(v)?);
+                    }
+                    _ => lines.push(format!("{}: {}", key, to_flow_yaml(v, true)?)),
+                }
+            }
+        }
+        Value::Sequence(arr) if !arr.is_empty() => {
+            for item in arr {
+                for (i, line) in to_quoted_yaml(item)?.into_iter().enumerate() {
+                    let marker = if i == 0 { "- " } else { "  " };
+                    lines.push(format!("{}{}", marker, line));
+                }
+            }
+        }
+        _ => lines.push(to_flow_yaml(value, true)?),
+    }
+    Ok(lines)
+}
+
+/// Render a string as a YAML double-quoted scalar
+///
+/// JSON escapes are all valid in YAML, but JSON leaves U+0085, U+2028 and
+/// U+2029 as they are, and YAML folds them as line breaks when reading a
+/// scalar back, so those get YAML's own `\N`, `\L` and `\P` escapes.
+fn double_quoted(s: &str) -> Result<String> {
+    let quoted = serde_json::to_string(s).context("Failed to serialize YAML")?;
+    Ok(quoted
+        .replace('\u{85}', "\\N")
+        .replace('\u{2028}', "\\L")
+        .replace('\u{2029}', "\\P"))
+}
+
+/// Render a value as single-line YAML flow style, double-quoting every
+/// string if `quote_strings` is set
+fn to_flow_yaml(value: &Value, quote_strings: bool) -> Result<String> {
+    match value {
+        Value::Sequence(arr) => {
+            let items = arr
+                .iter()
+                .map(|v| to_flow_yaml(v, quote_strings))
+                .collect::<Result<Vec<_>>>()?;
+            Ok(format!("[{}]", items.join(", ")))
+        }
+        Value::Mapping(map) => {
+            let entries = map
+                .iter()
+                .map(|(k, v)| {
+                    Ok(format!(
+                        "{}: {}",
+                        to_flow_yaml(k, quote_strings)?,
+                        to_flow_yaml(v, quote_strings)?
+                    ))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok(format!("{{{}}}", entries.join(", ")))
+        }
+        Value::String(s) if quote_strings => double_quoted(s),
+        Value::String(s) => {
+            let yaml_str = serde_yaml::to_string(value).context("Failed to serialize YAML")?;
+            let yaml_str = yaml_str.trim_end_matches('\n');
+            // Block scalars and plain scalars containing flow indicators are
+            // not valid inside a flow collection, so fall back to double quotes
+            let plain = !yaml_str.starts_with(['"', '\'']);
+            if yaml_str.contains('\n')
+                || (plain && yaml_str.contains([',', '[', ']', '{', '}', ':', '#']))
+            {
+                double_quoted(s)
+            } else {
+                Ok(yaml_str.to_string())
+            }
+        }
+        _ => {
+            let yaml_str = serde_yaml::to_string(value).context("Failed to serialize YAML")?;
+            Ok(yaml_str.trim_end_matches('\n').to_string())
+        }
+    }
+}
+
+/// Re-indent serde_yaml's two-space block output to the requested width
+///
+/// serde_yaml always emits two spaces per nesting level, with each `- `
+/// sequence marker occupying one level. Both are widened to `indent`
+/// columns, and block scalar (`|`/`>`) content is shifted along with its
+/// parent. YAML forbids tab indentation and block scalar indentation
+/// indicators are a single digit, so the width is clamped to 1..=9.
+fn reindent_yaml(yaml: &str, indent: usize) -> String {
+    let indent = indent.clamp(1, 9);
+    if indent == 2 {
+        return yaml.to_string();
+    }
+
+    let mut output = String::new();
+    // Original and new column of the content of an open block scalar
+    let mut block: Option<(usize, usize)> = None;
+
+    for line in yaml.lines() {
+        let leading = line.len() - line.trim_start_matches(' ').len();
+
+        if let Some((orig_col, new_col)) = block {
+            if line.trim().is_empty() {
+                output.push('\n');
+                continue;
+            }
+            if leading >= orig_col {
+                output.push_str(&" ".repeat(new_col));
+                output.push_str(&line[orig_col..]);
+                output.push('\n');
+                continue;
+            }
+            block = None;
+        }
+
+        let mut rest = &line[leading..];
+        let mut new_line = " ".repeat(leading / 2 * indent);
+        let mut items = 0;
+        while let Some(stripped) = rest.strip_prefix("- ") {
+            new_line.push('-');
+            new_line.push_str(&" ".repeat(indent - 1));
+            rest = stripped;
+            items += 1;
+        }
+        let orig_col = leading + 2 * items;
+        let new_col = leading / 2 * indent + indent * items;
+
+        // A block scalar header is either the whole node (sequence item or
+        // top-level document) or follows a `key: `
+        let header_start = if is_block_scalar_header(rest) {
+            Some(0)
+        } else {
+            rest.rfind(": ")
+                .map(|pos| pos + 2)
+                .filter(|&pos| is_block_scalar_header(&rest[pos..]))
+        };
+
+        match header_start {
+            Some(start) => {
+                new_line.push_str(&rest[..start]);
+                // Indentation indicators are relative to the parent node
+                for c in rest[start..].chars() {
+                    if c.is_ascii_digit() {
+                        new_line.push_str(&indent.to_string());
+                    } else {
+                        new_line.push(c);
+                    }
+                }
+                block = if start > 0 || items == 0 {
+                    Some((orig_col + 2, new_col + indent))
+                } else {
+                    Some((orig_col, new_col))
+                };
+            }
+            None => new_line.push_str(rest),
+        }
+
+        output.push_str(&new_line);
+        output.push('\n');
+    }
+
+    output
+}
+
+/// Check whether text is a YAML block scalar header such as `|`, `|-` or `>2+`
+fn is_block_scalar_header(text: &str) -> bool {
+    let mut chars = text.chars();
+    if !matches!(chars.next(), Some('|') | Some('>')) {
+        return false;
+    }
+    let indicators: Vec<char> = chars.collect();
+    indicators.len() <= 2
+        && indicators
+            .iter()
+            .all(|c| matches!(c, '-' | '+' | '1'..='9'))
+}
+
 /// Format as JSON
 fn format_json(value: &Value, options: &OutputOptions) -> Result<String> {
     // Convert YAML value to JSON value
     let json_value = yaml_to_json(value.clone());
 
-    let output = if options.pretty_print {
-        serde_json::to_string_pretty(&json_value).context("Failed to serialize JSON")?
+    let indent = match options.indent {
+        Indent::Spaces(0) => None,
+        Indent::Spaces(n) => Some(" ".repeat(n)),
+        Indent::Tab => Some("\t".to_string()),
+    };
+
+    let output = if let Some(indent) = indent.filter(|_| options.pretty_print && !options.compact) {
+        let mut buffer = Vec::new();
+        let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+        let mut serializer = serde_json::Serializer::with_formatter(&mut buffer, formatter);
+        json_value
+            .serialize(&mut serializer)
+            .context("Failed to serialize JSON")?;
+        String::from_utf8(buffer).context("Failed to serialize JSON")?
     } else {
         serde_json::to_string(&json_value).context("Failed to serialize JSON")?
     };
 
+    let output = if options.ascii_output {
+        escape_non_ascii(&output)
+    } else {
+        output
+    };
+
+    // Apply colors if requested
+    let output = if options.colors {
+        colorize_json(&output)
+    } else {
+        output
+    };
+
+    // JSON text sequences start each record with the RS control character
+    if options.seq {
+        return Ok(format!("\x1e{}", output));
+    }
+
     Ok(output)
 }
 
+/// Replace every non-ASCII character with a `\uXXXX` escape
+///
+/// Non-ASCII characters can only appear inside JSON strings, so the
+/// serialized text is rewritten directly. Characters outside the Basic
+/// Multilingual Plane become UTF-16 surrogate pairs.
+fn escape_non_ascii(json: &str) -> String {
+    let mut escaped = String::with_capacity(json.len());
+    for c in json.chars() {
+        if c.is_ascii() {
+            escaped.push(c);
+        } else {
+            let mut units = [0u16; 2];
+            for unit in c.encode_utf16(&mut units) {
+                escaped.push_str(&format!("\\u{:04x}", unit));
+            }
+        }
+    }
+    escaped
+}
+
 /// Format as TOML
-fn format_toml(value: &Value, _options: &OutputOptions) -> Result<String> {
+///
+/// A TOML document is always a table, so other top-level values are
+/// rejected up front. Arrays are written inline; arrays of tables become
+/// `[[name]]` sections.
+fn format_toml(value: &Value, options: &OutputOptions) -> Result<String> {
+    if !matches!(value, Value::Mapping(_)) {
+        return Err(anyhow!(
+            "TOML output requires an object at the top level, got {}; \
+             wrap the result in an object (e.g. {{\"value\": .}}) or choose another output format",
+            helpers::value_type(value)
+        ));
+    }
+
     // Convert YAML value to TOML value
     let toml_value = yaml_to_toml(value.clone())?;
 
-    let output = toml::to_string_pretty(&toml_value).context("Failed to serialize TOML")?;
+    let output = toml::to_string(&toml_value).context("Failed to serialize TOML")?;
+
+    // Keys are already sorted by `format_output` when requested; otherwise
+    // the table keeps the order of the input
+    let width = match options.indent {
+        Indent::Spaces(n) => n,
+        Indent::Tab => 1,
+    };
+    if width == 0 {
+        return Ok(output);
+    }
+    let unit = match options.indent {
+        Indent::Spaces(_) => " ".repeat(width),
+        Indent::Tab => "\t".to_string(),
+    };
+    let mut document: toml_edit::DocumentMut =
+        output.parse().context("Failed to serialize TOML")?;
+    indent_toml_table(document.as_table_mut(), 0, 0, &unit);
+    Ok(document.to_string())
+}
+
+/// Indent the headers and keys of nested TOML tables by their depth
+///
+/// Top-level tables stay at column 0; each table nested inside another
+/// one (`[a.b]`, `[[a.b]]`) is shifted one more `unit` along with its
+/// keys. Only whitespace before headers and keys changes, so multi-line
+/// strings keep their content.
+fn indent_toml_table(table: &mut toml_edit::Table, keys: usize, tables: usize, unit: &str) {
+    for (mut key, item) in table.iter_mut() {
+        match item {
+            toml_edit::Item::Value(_) => {
+                key.leaf_decor_mut().set_prefix(unit.repeat(keys));
+            }
+            toml_edit::Item::Table(child) => indent_toml_child(child, tables, unit),
+            toml_edit::Item::ArrayOfTables(children) => {
+                for child in children.iter_mut() {
+                    indent_toml_child(child, tables, unit);
+                }
+            }
+            toml_edit::Item::None => {}
+        }
+    }
+}
+
+/// Indent a table's header and keys to `depth`, and its subtables one deeper
+fn indent_toml_child(table: &mut toml_edit::Table, depth: usize, unit: &str) {
+    let decor = table.decor_mut();
+    let blank = decor
+        .prefix()
+        .and_then(|prefix| prefix.as_str())
+        .unwrap_or_default()
+        .to_string();
+    decor.set_prefix(format!("{}{}", blank, unit.repeat(depth)));
+    indent_toml_table(table, depth, depth + 1, unit);
+}
+
+/// Format as CSV (RFC 4180 quoting)
+fn format_csv(value: &Value, options: &OutputOptions) -> Result<String> {
+    format_delimited(value, "CSV", ',', options.csv_headers, |cell| {
+        if cell.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", cell.replace('"', "\"\""))
+        } else {
+            cell.to_string()
+        }
+    })
+}
+
+/// Format as TSV, escaping backslashes, tabs and line breaks as jq does
+fn format_tsv(value: &Value, options: &OutputOptions) -> Result<String> {
+    format_delimited(value, "TSV", '\t', options.csv_headers, |cell| {
+        let mut escaped = String::with_capacity(cell.len());
+        for c in cell.chars() {
+            match c {
+                '\\' => escaped.push_str("\\\\"),
+                '\t' => escaped.push_str("\\t"),
+                '\n' => escaped.push_str("\\n"),
+                '\r' => escaped.push_str("\\r"),
+                c => escaped.push(c),
+            }
+        }
+        escaped
+    })
+}
 
+/// Format tabular data as delimiter-separated rows
+///
+/// Accepts an array of arrays (each inner array is a row) or an array of
+/// objects. For objects the columns are the union of every row's keys, in
+/// order of first appearance, named in a header row when `headers` is set;
+/// each object fills the cells for its keys and leaves the rest empty.
+fn format_delimited(
+    value: &Value,
+    name: &str,
+    delimiter: char,
+    headers: bool,
+    quote: impl Fn(&str) -> String,
+) -> Result<String> {
+    let rows = match value {
+        Value::Sequence(rows) => rows,
+        _ => {
+            return Err(anyhow!(
+                "{} output requires an array of arrays or an array of objects, got {}",
+                name,
+                helpers::value_type(value)
+            ));
+        }
+    };
+
+    let mut table: Vec<Vec<String>> = Vec::new();
+    match rows.first() {
+        None => {}
+        Some(Value::Mapping(_)) => {
+            let mut maps = Vec::with_capacity(rows.len());
+            let mut columns: Vec<&Value> = Vec::new();
+            for row in rows {
+                let Value::Mapping(map) = row else {
+                    return Err(anyhow!(
+                        "{} output rows must all be objects, got {}",
+                        name,
+                        helpers::value_type(row)
+                    ));
+                };
+                for key in map.keys() {
+                    if !columns.contains(&key) {
+                        columns.push(key);
+                    }
+                }
+                maps.push(map);
+            }
+
+            if headers {
+                table.push(
+                    columns
+                        .iter()
+                        .map(|h| cell_text(h, name))
+                        .collect::<Result<_>>()?,
+                );
+            }
+            for map in maps {
+                table.push(
+                    columns
+                        .iter()
+                        .map(|h| map.get(h).map_or(Ok(String::new()), |v| cell_text(v, name)))
+                        .collect::<Result<_>>()?,
+                );
+            }
+        }
+        Some(Value::Sequence(_)) => {
+            for row in rows {
+                let Value::Sequence(cells) = row else {
+                    return Err(anyhow!(
+                        "{} output rows must all be arrays, got {}",
+                        name,
+                        helpers::value_type(row)
+                    ));
+                };
+                table.push(
+                    cells
+                        .iter()
+                        .map(|v| cell_text(v, name))
+                        .collect::<Result<_>>()?,
+                );
+            }
+        }
+        Some(other) => {
+            return Err(anyhow!(
+                "{} output requires an array of arrays or an array of objects, got an array of {}",
+                name,
+                helpers::value_type(other)
+            ));
+        }
+    }
+
+    let mut output = String::new();
+    for row in table {
+        let cells: Vec<String> = row.iter().map(|cell| quote(cell)).collect();
+        output.push_str(&cells.join(&delimiter.to_string()));
+        output.push('\n');
+    }
     Ok(output)
 }
 
+/// Render a scalar as the text of a CSV/TSV field
+fn cell_text(value: &Value, name: &str) -> Result<String> {
+    match value {
+        Value::Null => Ok(String::new()),
+        Value::Bool(b) => Ok(b.to_string()),
+        Value::Number(n) => Ok(n.to_string()),
+        Value::String(s) => Ok(s.clone()),
+        _ => Err(anyhow!(
+            "Cannot write {} as a {} field",
+            helpers::value_type(value),
+            name
+        )),
+    }
+}
+
 /// Convert YAML value to JSON value
 fn yaml_to_json(value: Value) -> serde_json::Value {
     match value {
@@ -109,6 +770,8 @@ fn yaml_to_json(value: Value) -> serde_json::Value {
         Value::Number(n) => {
             if let Some(i) = n.as_i64() {
                 serde_json::Value::Number(i.into())
+            } else if let Some(u) = n.as_u64() {
+                serde_json::Value::Number(u.into())
             } else if let Some(f) = n.as_f64() {
                 serde_json::Number::from_f64(f)
                     .map(serde_json::Value::Number)
@@ -118,6 +781,7 @@ fn yaml_to_json(value: Value) -> serde_json::Value {
             }
         }
         Value::String(s) => serde_json::Value::String(s),
+        Value::Tagged(tagged) => yaml_to_json(tagged.value),
         Value::Sequence(arr) => {
             serde_json::Value::Array(arr.into_iter().map(yaml_to_json).collect())
         }
@@ -135,18 +799,25 @@ fn yaml_to_json(value: Value) -> serde_json::Value {
             }
             serde_json::Value::Object(obj)
         }
-        _ => serde_json::Value::Null,
     }
 }
 
 /// Convert YAML value to TOML value
+///
+/// TOML has no null: null-valued keys are left out of their table, and a
+/// null anywhere else (such as inside an array) is an error.
 fn yaml_to_toml(value: Value) -> Result<toml::Value> {
     match value {
-        Value::Null => Ok(toml::Value::String("null".to_string())),
+        Value::Null => Err(anyhow!("TOML cannot represent null values inside arrays")),
         Value::Bool(b) => Ok(toml::Value::Boolean(b)),
         Value::Number(n) => {
             if let Some(i) = n.as_i64() {
                 Ok(toml::Value::Integer(i))
+            } else if n.is_u64() {
+                Err(anyhow!(
+                    "TOML integers are 64-bit signed, {} is too large",
+                    n
+                ))
             } else if let Some(f) = n.as_f64() {
                 Ok(toml::Value::Float(f))
             } else {
@@ -154,6 +825,13 @@ fn yaml_to_toml(value: Value) -> Result<toml::Value> {
             }
         }
         Value::String(s) => Ok(toml::Value::String(s)),
+        Value::Tagged(tagged) => match tagged.value {
+            Value::String(s) if tagged.tag == DATETIME_TAG => s
+                .parse::<toml::value::Datetime>()
+                .map(toml::Value::Datetime)
+                .map_err(|e| anyhow!("Invalid TOML datetime '{}': {}", s, e)),
+            value => yaml_to_toml(value),
+        },
         Value::Sequence(arr) => {
             let values: Result<Vec<_>> = arr.into_iter().map(yaml_to_toml).collect();
             Ok(toml::Value::Array(values?))
@@ -168,64 +846,212 @@ fn yaml_to_toml(value: Value) -> Result<toml::Value> {
                         .trim()
                         .to_string(),
                 };
+                if v.is_null() {
+                    continue;
+                }
                 table.insert(key, yaml_to_toml(v)?);
             }
             Ok(toml::Value::Table(table))
         }
-        _ => Err(anyhow!("Unsupported value type for TOML")),
     }
 }
 
 /// Apply colors to YAML output
+///
+/// Each line is split into its structural parts (sequence markers, key,
+/// value) the way serde_yaml emits them, so a value containing `:` or `#`
+/// is never mistaken for a key or comment. Scalars are colored by type:
+/// keys cyan, strings green, numbers yellow, booleans magenta and nulls
+/// grey. Block scalar (`|`/`>`) content is colored as a string.
 fn colorize_yaml(yaml: &str) -> String {
     use colored::Colorize;
 
     let mut result = String::new();
+    // Column that the content of an open block scalar must be indented past
+    let mut block_parent: Option<usize> = None;
+
     for line in yaml.lines() {
-        let colored_line = if line.starts_with("---") || line.starts_with("...") {
-            line.dimmed().to_string()
+        let indent = line.len() - line.trim_start_matches(' ').len();
+
+        if let Some(parent) = block_parent {
+            if line.trim().is_empty() || indent > parent {
+                result.push_str(&line.green().to_string());
+                result.push('\n');
+                continue;
+            }
+            block_parent = None;
+        }
+
+        if line.starts_with("---") || line.starts_with("...") {
+            result.push_str(&line.dimmed().to_string());
         } else if line.trim_start().starts_with('#') {
-            line.bright_black().to_string()
-        } else if line.contains(':') {
-            // Key: value line
-            let parts: Vec<&str> = line.splitn(2, ':').collect();
-            if parts.len() == 2 {
-                format!("{}:{}", parts[0].cyan(), parts[1])
-            } else {
-                line.to_string()
-            }
-        } else if line.trim().starts_with('-') {
-            // Array item
-            let trimmed = line.trim_start();
-            let indent = &line[..line.len() - trimmed.len()];
-            format!("{}{}", indent, trimmed.bright_yellow())
-        } else if line.trim() == "true" || line.trim() == "false" {
-            line.bright_magenta().to_string()
-        } else if line.trim() == "null" {
-            line.bright_black().to_string()
+            result.push_str(&line.bright_black().to_string());
         } else {
-            line.to_string()
-        };
-        result.push_str(&colored_line);
+            let mut rest = &line[indent..];
+            let mut parent = indent;
+            result.push_str(&line[..indent]);
+
+            // Sequence markers, possibly several on one line (`- - x`)
+            while rest == "-" || rest.starts_with("- ") {
+                parent = line.len() - rest.len();
+                result.push_str(&"-".bright_yellow().to_string());
+                let after = rest[1..].trim_start_matches(' ');
+                result.push_str(&rest[1..rest.len() - after.len()]);
+                rest = after;
+            }
+
+            let value = match split_yaml_key(rest) {
+                Some((key, value)) => {
+                    parent = line.len() - rest.len();
+                    result.push_str(&key.cyan().to_string());
+                    result.push(':');
+                    value
+                }
+                None => rest,
+            };
+
+            let scalar = value.trim_start_matches(' ');
+            result.push_str(&value[..value.len() - scalar.len()]);
+            if is_block_scalar_header(scalar) {
+                block_parent = Some(parent);
+            }
+            result.push_str(&colorize_yaml_scalar(scalar));
+        }
         result.push('\n');
     }
     result
 }
 
+/// Apply colors to JSON output
+///
+/// Uses the same palette as YAML: keys cyan, strings green, numbers yellow,
+/// booleans magenta and null grey. Punctuation and whitespace are left as is.
+fn colorize_json(json: &str) -> String {
+    use colored::Colorize;
+
+    let mut result = String::with_capacity(json.len());
+    let mut rest = json;
+
+    while let Some(c) = rest.chars().next() {
+        let len = match c {
+            '"' => {
+                // Scan to the closing quote, skipping escaped characters
+                let bytes = rest.as_bytes();
+                let mut i = 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+                let len = (i + 1).min(rest.len());
+                let is_key = rest[len..].trim_start().starts_with(':');
+                let token = &rest[..len];
+                result.push_str(&if is_key {
+                    token.cyan().to_string()
+                } else {
+                    token.green().to_string()
+                });
+                len
+            }
+            '-' | '0'..='9' => {
+                let len = rest
+                    .find(|c: char| !matches!(c, '0'..='9' | '-' | '+' | '.' | 'e' | 'E'))
+                    .unwrap_or(rest.len());
+                result.push_str(&rest[..len].yellow().to_string());
+                len
+            }
+            't' | 'f' | 'n' => {
+                let len = rest
+                    .find(|c: char| !c.is_ascii_alphabetic())
+                    .unwrap_or(rest.len());
+                let word = &rest[..len];
+                result.push_str(&if word == "null" {
+                    word.bright_black().to_string()
+                } else {
+                    word.bright_magenta().to_string()
+                });
+                len
+            }
+            _ => {
+                result.push(c);
+                c.len_utf8()
+            }
+        };
+        rest = &rest[len..];
+    }
+    result
+}
+
+/// Split `key: value` (or `key:`) into the key and the text after the colon
+fn split_yaml_key(text: &str) -> Option<(&str, &str)> {
+    let key_end = match text.chars().next()? {
+        quote @ ('"' | '\'') => {
+            // Find the closing quote, skipping escapes (`\"` or `''`)
+            let bytes = text.as_bytes();
+            let mut i = 1;
+            loop {
+                match bytes.get(i)? {
+                    b'\\' if quote == '"' => i += 2,
+                    b'\'' if quote == '\'' && bytes.get(i + 1) == Some(&b'\'') => i += 2,
+                    &b if b == quote as u8 => break i + 1,
+                    _ => i += 1,
+                }
+            }
+        }
+        // A plain key ends at the first `: `; plain values never contain one
+        _ => text
+            .find(": ")
+            .or_else(|| text.strip_suffix(':').map(str::len))?,
+    };
+
+    let after = &text[key_end..];
+    if after == ":" || after.starts_with(": ") {
+        Some((&text[..key_end], &after[1..]))
+    } else {
+        None
+    }
+}
+
+/// Color a single YAML scalar by its type
+fn colorize_yaml_scalar(scalar: &str) -> String {
+    use colored::Colorize;
+
+    if let Some(tagged) = scalar.strip_prefix('!') {
+        // Tagged value: dim the tag and color the value after it
+        return match tagged.split_once(' ') {
+            Some((tag, value)) => format!(
+                "{} {}",
+                format!("!{}", tag).dimmed(),
+                colorize_yaml_scalar(value)
+            ),
+            None => scalar.dimmed().to_string(),
+        };
+    }
+
+    match scalar {
+        "" | "{}" | "[]" => scalar.to_string(),
+        "null" | "~" => scalar.bright_black().to_string(),
+        "true" | "false" => scalar.bright_magenta().to_string(),
+        ".inf" | "-.inf" | ".nan" => scalar.yellow().to_string(),
+        _ if scalar.starts_with(['{', '[']) => scalar.to_string(),
+        _ if is_yaml_number(scalar) => scalar.yellow().to_string(),
+        _ => scalar.green().to_string(),
+    }
+}
+
+/// Whether a plain scalar reads as a number
+fn is_yaml_number(scalar: &str) -> bool {
+    let digits = scalar.trim_start_matches(['-', '+']);
+    digits.starts_with(|c: char| c.is_ascii_digit()) && scalar.parse::<f64>().is_ok()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use colored::Colorize;
 
     #[test]
     fn test_format_yaml_simple() {
         let value = serde_yaml::from_str("name: test\nvalue: 42").unwrap();
-        let options = OutputOptions {
-            indent: 2,
-            pretty_print: false,
-            unwrap_scalar: false,
-            no_doc: false,
-            colors: false,
-        };
+        let options = OutputOptions::default();
         let output = format_yaml(&value, &options).unwrap();
         assert!(output.contains("name: test"));
         assert!(output.contains("value: 42"));
@@ -235,11 +1061,8 @@ mod tests {
     fn test_format_json() {
         let value = serde_yaml::from_str("name: test\nvalue: 42").unwrap();
         let options = OutputOptions {
-            indent: 2,
             pretty_print: true,
-            unwrap_scalar: false,
-            no_doc: false,
-            colors: false,
+            ..Default::default()
         };
         let output = format_json(&value, &options).unwrap();
         assert!(output.contains("\"name\": \"test\""));
@@ -250,13 +1073,643 @@ mod tests {
     fn test_unwrap_scalar() {
         let value = Value::String("hello".to_string());
         let options = OutputOptions {
-            indent: 2,
-            pretty_print: false,
             unwrap_scalar: true,
-            no_doc: false,
-            colors: false,
+            ..Default::default()
         };
         let output = format_yaml(&value, &options).unwrap();
         assert_eq!(output.trim(), "hello");
     }
+
+    fn sorted_options() -> OutputOptions {
+        OutputOptions {
+            sort_keys: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_sort_keys_yaml() {
+        let value =
+            serde_yaml::from_str("b: 1\na:\n  z: 1\n  y: 2\nc:\n  - q: 1\n    p: 2").unwrap();
+        let output = format_output(&value, crate::OutputFormat::Yaml, sorted_options()).unwrap();
+        assert_eq!(
+            output,
+            "---\na:\n  y: 2\n  z: 1\nb: 1\nc:\n- p: 2\n  q: 1\n"
+        );
+    }
+
+    #[test]
+    fn test_sort_keys_json() {
+        let value =
+            serde_yaml::from_str("b: 1\na:\n  z: 1\n  y: 2\nc:\n  - q: 1\n    p: 2").unwrap();
+        let output = format_output(&value, crate::OutputFormat::Json, sorted_options()).unwrap();
+        assert_eq!(output, r#"{"a":{"y":2,"z":1},"b":1,"c":[{"p":2,"q":1}]}"#);
+    }
+
+    #[test]
+    fn test_sort_keys_toml() {
+        let value = serde_yaml::from_str("b: 1\na: 2\nt:\n  z: 1\n  y: 2").unwrap();
+        let output = format_output(&value, crate::OutputFormat::Toml, sorted_options()).unwrap();
+        assert_eq!(output, "a = 2\nb = 1\n\n[t]\ny = 2\nz = 1\n");
+    }
+
+    #[test]
+    fn test_sort_keys_toml_nested() {
+        let value = serde_yaml::from_str("z: {b: 1, a: {y: 1, x: 2}}\nm: [{k: 1, j: 2}]").unwrap();
+        let output = format_output(&value, crate::OutputFormat::Toml, sorted_options()).unwrap();
+        assert_eq!(
+            output,
+            "[[m]]\nj = 2\nk = 1\n\n[z]\nb = 1\n\n  [z.a]\n  x = 2\n  y = 1\n"
+        );
+        let output =
+            format_output(&value, crate::OutputFormat::Toml, OutputOptions::default()).unwrap();
+        assert_eq!(
+            output,
+            "[z]\nb = 1\n\n  [z.a]\n  y = 1\n  x = 2\n\n[[m]]\nk = 1\nj = 2\n"
+        );
+    }
+
+    #[test]
+    fn test_toml_indent_option() {
+        let value = serde_yaml::from_str("a:\n  b:\n    - c: 1\n      d: [{e: 2}]").unwrap();
+        let options = OutputOptions {
+            indent: Indent::Spaces(4),
+            ..Default::default()
+        };
+        let output = format_toml(&value, &options).unwrap();
+        assert_eq!(
+            output,
+            "    [[a.b]]\n    c = 1\n\n        [[a.b.d]]\n        e = 2\n"
+        );
+        let reparsed: toml::Value = toml::from_str(&output).unwrap();
+        assert_eq!(reparsed["a"]["b"][0]["d"][0]["e"].as_integer(), Some(2));
+
+        let options = OutputOptions {
+            indent: Indent::Spaces(0),
+            ..Default::default()
+        };
+        let output = format_toml(&value, &options).unwrap();
+        assert_eq!(output, "[[a.b]]\nc = 1\n\n[[a.b.d]]\ne = 2\n");
+    }
+
+    #[test]
+    fn test_toml_indent_keeps_multiline_strings() {
+        let value = serde_yaml::from_str("a:\n  b:\n    s: \"l1\\nl2\\n\"").unwrap();
+        let output = format_toml(&value, &OutputOptions::default()).unwrap();
+        let reparsed: toml::Value = toml::from_str(&output).unwrap();
+        assert_eq!(reparsed["a"]["b"]["s"].as_str(), Some("l1\nl2\n"));
+    }
+
+    #[test]
+    fn test_insertion_order_preserved_without_sort_keys() {
+        let value = serde_yaml::from_str("b: 1\na: 2").unwrap();
+        let output =
+            format_output(&value, crate::OutputFormat::Json, OutputOptions::default()).unwrap();
+        assert_eq!(output, r#"{"b":1,"a":2}"#);
+    }
+
+    #[test]
+    fn test_csv_array_of_arrays() {
+        let value = serde_yaml::from_str("[[1, a, true], [2, b, null]]").unwrap();
+        let output = format_csv(&value, &OutputOptions::default()).unwrap();
+        assert_eq!(output, "1,a,true\n2,b,\n");
+    }
+
+    #[test]
+    fn test_csv_array_of_objects_with_differing_keys() {
+        let value =
+            serde_yaml::from_str("[{name: a, age: 1}, {age: 2, extra: x}, {name: c}]").unwrap();
+        let output = format_csv(&value, &OutputOptions::default()).unwrap();
+        assert_eq!(output, "name,age,extra\na,1,\n,2,x\nc,,\n");
+    }
+
+    #[test]
+    fn test_csv_columns_aligned_across_key_orders() {
+        let value = serde_yaml::from_str("[{b: 1}, {a: 2, b: 3}, {c: 4, a: 5}]").unwrap();
+        let output = format_csv(&value, &OutputOptions::default()).unwrap();
+        assert_eq!(output, "b,a,c\n1,,\n3,2,\n,5,4\n");
+    }
+
+    #[test]
+    fn test_csv_without_headers() {
+        let value = serde_yaml::from_str("[{name: a}, {age: 2, name: b}]").unwrap();
+        let options = OutputOptions {
+            csv_headers: false,
+            ..OutputOptions::default()
+        };
+        let output = format_csv(&value, &options).unwrap();
+        assert_eq!(output, "a,\nb,2\n");
+        let output = format_tsv(&value, &options).unwrap();
+        assert_eq!(output, "a\t\nb\t2\n");
+    }
+
+    #[test]
+    fn test_csv_quoting() {
+        let value = Value::Sequence(vec![Value::Sequence(vec![
+            Value::String("a,b".to_string()),
+            Value::String("say \"hi\"".to_string()),
+            Value::String("two\nlines".to_string()),
+            Value::String("plain".to_string()),
+        ])]);
+        let output = format_csv(&value, &OutputOptions::default()).unwrap();
+        assert_eq!(output, "\"a,b\",\"say \"\"hi\"\"\",\"two\nlines\",plain\n");
+    }
+
+    #[test]
+    fn test_tsv_array_of_objects() {
+        let value = serde_yaml::from_str("[{name: a, note: 'x,y'}, {name: b, note: z}]").unwrap();
+        let output = format_tsv(&value, &OutputOptions::default()).unwrap();
+        assert_eq!(output, "name\tnote\na\tx,y\nb\tz\n");
+    }
+
+    #[test]
+    fn test_tsv_escapes_control_characters() {
+        let value =
+            serde_yaml::from_str(r#"[["a\tb", "line1\nline2"], ["c\rd", 'back\slash']]"#).unwrap();
+        let output = format_tsv(&value, &OutputOptions::default()).unwrap();
+        assert_eq!(output, "a\\tb\tline1\\nline2\nc\\rd\tback\\\\slash\n");
+        assert_eq!(output.lines().count(), 2);
+    }
+
+    #[test]
+    fn test_csv_rejects_non_tabular() {
+        let value = serde_yaml::from_str("a: 1").unwrap();
+        let err = format_csv(&value, &OutputOptions::default()).unwrap_err();
+        assert!(err.to_string().contains("CSV output requires an array"));
+
+        let value = serde_yaml::from_str("[1, 2]").unwrap();
+        assert!(format_csv(&value, &OutputOptions::default()).is_err());
+
+        let value = serde_yaml::from_str("[[1, [2]]]").unwrap();
+        let err = format_csv(&value, &OutputOptions::default()).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Cannot write array as a CSV field")
+        );
+    }
+
+    #[test]
+    fn test_json_indent_four_spaces() {
+        let value = serde_yaml::from_str("a:\n  b: [1]").unwrap();
+        let options = OutputOptions {
+            pretty_print: true,
+            indent: Indent::Spaces(4),
+            ..Default::default()
+        };
+        let output = format_json(&value, &options).unwrap();
+        assert_eq!(
+            output,
+            "{\n    \"a\": {\n        \"b\": [\n            1\n        ]\n    }\n}"
+        );
+    }
+
+    #[test]
+    fn test_json_indent_tab() {
+        let value = serde_yaml::from_str("a:\n  b: 1").unwrap();
+        let options = OutputOptions {
+            pretty_print: true,
+            indent: Indent::Tab,
+            ..Default::default()
+        };
+        let output = format_json(&value, &options).unwrap();
+        assert_eq!(output, "{\n\t\"a\": {\n\t\t\"b\": 1\n\t}\n}");
+    }
+
+    #[test]
+    fn test_yaml_indent_four_spaces() {
+        let value = serde_yaml::from_str(
+            "a:\n  b: [1, [2, 3]]\n  c:\n    - x: 1\n      y: 2\n  s: \"l1\\nl2\"",
+        )
+        .unwrap();
+        let options = OutputOptions {
+            indent: Indent::Spaces(4),
+            no_doc: true,
+            ..Default::default()
+        };
+        let output = format_yaml(&value, &options).unwrap();
+        assert_eq!(
+            output,
+            "a:\n    b:\n    -   1\n    -   -   2\n        -   3\n    c:\n    -   x: 1\n        y: 2\n    s: |-\n        l1\n        l2\n"
+        );
+        let reparsed: Value = serde_yaml::from_str(&output).unwrap();
+        assert_eq!(reparsed, value);
+    }
+
+    #[test]
+    fn test_yaml_indent_block_scalar_with_indicator() {
+        let value = serde_yaml::from_str("- k: \" lead\\nx\"\n- \"a\\nb\"").unwrap();
+        let options = OutputOptions {
+            indent: Indent::Spaces(4),
+            no_doc: true,
+            ..Default::default()
+        };
+        let output = format_yaml(&value, &options).unwrap();
+        let reparsed: Value = serde_yaml::from_str(&output).unwrap();
+        assert_eq!(reparsed, value);
+    }
+
+    #[test]
+    fn test_yaml_tab_keeps_space_indentation() {
+        // YAML forbids tabs for indentation, so --tab only affects JSON
+        let value = serde_yaml::from_str("a:\n  b: 1").unwrap();
+        let options = OutputOptions {
+            indent: Indent::Tab,
+            no_doc: true,
+            ..Default::default()
+        };
+        let output = format_yaml(&value, &options).unwrap();
+        assert_eq!(output, "a:\n  b: 1\n");
+    }
+
+    #[test]
+    fn test_compact_json_overrides_pretty_print() {
+        let value = serde_yaml::from_str("a: 1\nb: 2").unwrap();
+        let options = OutputOptions {
+            pretty_print: true,
+            compact: true,
+            ..Default::default()
+        };
+        let output = format_json(&value, &options).unwrap();
+        assert_eq!(output, r#"{"a":1,"b":2}"#);
+    }
+
+    #[test]
+    fn test_compact_yaml_flow_style() {
+        let value = serde_yaml::from_str(
+            "a: 1\nb: [x, \"y, z\"]\nc:\n  d: null\n  e: \"l1\\nl2\"\nf: {}\ng: 'true'",
+        )
+        .unwrap();
+        let options = OutputOptions {
+            compact: true,
+            no_doc: true,
+            ..Default::default()
+        };
+        let output = format_yaml(&value, &options).unwrap();
+        assert_eq!(
+            output,
+            "{a: 1, b: [x, \"y, z\"], c: {d: null, e: \"l1\\nl2\"}, f: {}, g: 'true'}\n"
+        );
+        let reparsed: Value = serde_yaml::from_str(&output).unwrap();
+        assert_eq!(reparsed, value);
+    }
+
+    #[test]
+    fn test_integral_floats_render_as_integers() {
+        let options = OutputOptions {
+            no_doc: true,
+            ..Default::default()
+        };
+        for (input, expected) in [("1", "1"), ("1.0", "1"), ("-3.0", "-3"), ("1.5", "1.5")] {
+            let value: Value = serde_yaml::from_str(input).unwrap();
+            let json = format_output(&value, crate::OutputFormat::Json, options.clone()).unwrap();
+            let yaml = format_output(&value, crate::OutputFormat::Yaml, options.clone()).unwrap();
+            assert_eq!(json.trim(), expected);
+            assert_eq!(yaml.trim(), expected);
+        }
+    }
+
+    #[test]
+    fn test_floats_use_shortest_representation() {
+        let value = Value::Number(serde_yaml::Number::from(0.1 + 0.2));
+        let json = format_output(&value, crate::OutputFormat::Json, OutputOptions::default());
+        assert_eq!(json.unwrap().trim(), "0.30000000000000004");
+    }
+
+    #[test]
+    fn test_nested_integral_floats_normalized() {
+        let value: Value = serde_yaml::from_str("{a: [2.0, 2.5], b: 1e20}").unwrap();
+        let json = format_output(&value, crate::OutputFormat::Json, OutputOptions::default());
+        assert_eq!(json.unwrap().trim(), r#"{"a":[2,2.5],"b":1e+20}"#);
+    }
+
+    #[test]
+    fn test_json_indent_zero_is_single_line() {
+        let value = serde_yaml::from_str("a:\n  b: [1, 2]").unwrap();
+        let options = OutputOptions {
+            pretty_print: true,
+            indent: Indent::Spaces(0),
+            ..Default::default()
+        };
+        let output = format_json(&value, &options).unwrap();
+        assert_eq!(output, r#"{"a":{"b":[1,2]}}"#);
+    }
+
+    #[test]
+    fn test_yaml_indent_zero_is_flow_style() {
+        let value = serde_yaml::from_str("a:\n  b: [1, 2]").unwrap();
+        let options = OutputOptions {
+            indent: Indent::Spaces(0),
+            no_doc: true,
+            ..Default::default()
+        };
+        let output = format_yaml(&value, &options).unwrap();
+        assert_eq!(output, "{a: {b: [1, 2]}}\n");
+    }
+
+    #[test]
+    fn test_toml_ignores_indent() {
+        let value = serde_yaml::from_str("a:\n  b: 1").unwrap();
+        for indent in [Indent::Tab, Indent::Spaces(0)] {
+            let options = OutputOptions {
+                indent,
+                ..Default::default()
+            };
+            assert!(format_toml(&value, &options).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_toml_rejects_non_table_top_level() {
+        for input in ["[1, 2, 3]", "42", "text"] {
+            let value: Value = serde_yaml::from_str(input).unwrap();
+            let err = format_toml(&value, &OutputOptions::default()).unwrap_err();
+            assert!(
+                err.to_string()
+                    .contains("requires an object at the top level")
+            );
+        }
+    }
+
+    #[test]
+    fn test_toml_nested_tables_and_arrays_of_tables() {
+        let value: Value = serde_yaml::from_str(
+            "name: x\ntags: [a, b]\nmatrix: [[1, 2], [3]]\nserver:\n  http:\n    port: 80\nplugins:\n  - name: p1\n    opts:\n      - level: 1\n  - name: p2",
+        )
+        .unwrap();
+        let output = format_toml(&value, &OutputOptions::default()).unwrap();
+        assert_eq!(
+            output,
+            "name = \"x\"\ntags = [\"a\", \"b\"]\nmatrix = [[1, 2], [3]]\n\n  [server.http]\n  port = 80\n\n[[plugins]]\nname = \"p1\"\n\n  [[plugins.opts]]\n  level = 1\n\n[[plugins]]\nname = \"p2\"\n"
+        );
+        let reparsed: toml::Value = toml::from_str(&output).unwrap();
+        assert_eq!(
+            reparsed["plugins"][0]["opts"][0]["level"].as_integer(),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_toml_omits_null_keys() {
+        let value: Value =
+            serde_yaml::from_str(r#"{"a": 1, "b": null, "c": {"d": null}}"#).unwrap();
+        let output = format_toml(&value, &OutputOptions::default()).unwrap();
+        assert_eq!(output, "a = 1\n\n[c]\n");
+    }
+
+    #[test]
+    fn test_restore_datetimes_by_position() {
+        let original = crate::parser::input::InputParser::parse(
+            "a = 2020-01-01\nb = [2020-01-02]",
+            crate::parser::input::InputFormat::Toml,
+        )
+        .unwrap();
+        let value: Value = serde_yaml::from_str(
+            "{a: '2020-01-01', b: ['2020-01-02', '2020-01-01'], c: '2020-01-01'}",
+        )
+        .unwrap();
+        let restored = restore_datetimes(value, &original);
+        assert!(matches!(restored["a"], Value::Tagged(_)));
+        assert!(matches!(restored["b"][0], Value::Tagged(_)));
+        assert_eq!(restored["b"][1], "2020-01-01");
+        assert_eq!(restored["c"], "2020-01-01");
+        // A changed value is not restored
+        let restored = restore_datetimes(
+            serde_yaml::from_str("{a: '2021-01-01'}").unwrap(),
+            &original,
+        );
+        assert_eq!(restored["a"], "2021-01-01");
+    }
+
+    #[test]
+    fn test_toml_rejects_integer_beyond_i64() {
+        let value: Value = serde_yaml::from_str("a: 18446744073709551615").unwrap();
+        let err = format_toml(&value, &OutputOptions::default()).unwrap_err();
+        assert!(err.to_string().contains("too large"), "{}", err);
+    }
+
+    #[test]
+    fn test_toml_rejects_null_in_array() {
+        let value: Value = serde_yaml::from_str("a: [1, null]").unwrap();
+        assert!(format_toml(&value, &OutputOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_yaml_flow_style_nested() {
+        let value = serde_yaml::from_str("a: 1\nb:\n  c: [1, 2]\n  d:\n    - e: x").unwrap();
+        let options = OutputOptions {
+            flow: true,
+            no_doc: true,
+            ..Default::default()
+        };
+        let output = format_yaml(&value, &options).unwrap();
+        assert_eq!(output, "{a: 1, b: {c: [1, 2], d: [{e: x}]}}\n");
+    }
+
+    #[test]
+    fn test_yaml_block_style_by_default() {
+        let value = serde_yaml::from_str("a: 1\nb: [1, 2]").unwrap();
+        let options = OutputOptions {
+            no_doc: true,
+            ..Default::default()
+        };
+        let output = format_yaml(&value, &options).unwrap();
+        assert_eq!(output, "a: 1\nb:\n- 1\n- 2\n");
+    }
+
+    #[test]
+    fn test_yaml_quote_strings() {
+        let value = serde_yaml::from_str(
+            "a: 'no'\nb: ['0123', 1, true, null]\nc:\n  d: [{e: \"l1\\nl2\"}, []]\n",
+        )
+        .unwrap();
+        let options = OutputOptions {
+            no_doc: true,
+            quote_strings: true,
+            ..Default::default()
+        };
+        let output = format_yaml(&value, &options).unwrap();
+        assert_eq!(
+            output,
+            "\"a\": \"no\"\n\"b\":\n- \"0123\"\n- 1\n- true\n- null\n\"c\":\n  \"d\":\n  - \"e\": \"l1\\nl2\"\n  - []\n"
+        );
+        let reparsed: Value = serde_yaml::from_str(&output).unwrap();
+        assert_eq!(reparsed, value);
+        assert_eq!(reparsed["a"], Value::String("no".into()));
+    }
+
+    #[test]
+    fn test_yaml_quote_strings_flow_and_scalar() {
+        let value = serde_yaml::from_str("{a: 'yes', b: [x, 2]}").unwrap();
+        let options = OutputOptions {
+            no_doc: true,
+            flow: true,
+            quote_strings: true,
+            ..Default::default()
+        };
+        let output = format_yaml(&value, &options).unwrap();
+        assert_eq!(output, "{\"a\": \"yes\", \"b\": [\"x\", 2]}\n");
+
+        // Quoting wins over unwrapping a top-level string
+        let options = OutputOptions {
+            no_doc: true,
+            unwrap_scalar: true,
+            quote_strings: true,
+            ..Default::default()
+        };
+        let output = format_yaml(&Value::String("no".into()), &options).unwrap();
+        assert_eq!(output, "\"no\"\n");
+    }
+
+    #[test]
+    fn test_yaml_quote_strings_escapes_unicode_line_breaks() {
+        let value = Value::Sequence(vec![
+            Value::String("a\u{85}b".into()),
+            Value::String("c\u{2028}d\u{2029}e".into()),
+        ]);
+        let options = OutputOptions {
+            no_doc: true,
+            quote_strings: true,
+            ..Default::default()
+        };
+        let output = format_yaml(&value, &options).unwrap();
+        assert_eq!(output, "- \"a\\Nb\"\n- \"c\\Ld\\Pe\"\n");
+        let reparsed: Value = serde_yaml::from_str(&output).unwrap();
+        assert_eq!(reparsed, value);
+    }
+
+    #[test]
+    fn test_yaml_quote_strings_reindents() {
+        let value = serde_yaml::from_str("a: [[x]]").unwrap();
+        let options = OutputOptions {
+            indent: Indent::Spaces(4),
+            no_doc: true,
+            quote_strings: true,
+            ..Default::default()
+        };
+        let output = format_yaml(&value, &options).unwrap();
+        assert_eq!(output, "\"a\":\n-   -   \"x\"\n");
+    }
+
+    fn strip_ansi(text: &str) -> String {
+        regex::Regex::new(r"\x1b\[[0-9;]*m")
+            .unwrap()
+            .replace_all(text, "")
+            .to_string()
+    }
+
+    #[test]
+    fn test_colorize_yaml_value_with_colon() {
+        colored::control::set_override(true);
+        let yaml = "url: http://x:8080\nnote: 'a: b # c'\nport: 8080\nok: true\nnone: null\n";
+        let colored = colorize_yaml(yaml);
+        assert_eq!(strip_ansi(&colored), yaml);
+        assert!(colored.contains(&format!("{}:", "url".cyan())));
+        assert!(colored.contains(&"http://x:8080".green().to_string()));
+        assert!(colored.contains(&"'a: b # c'".green().to_string()));
+        assert!(colored.contains(&"8080".yellow().to_string()));
+        assert!(colored.contains(&"true".bright_magenta().to_string()));
+        assert!(colored.contains(&"null".bright_black().to_string()));
+    }
+
+    #[test]
+    fn test_colorize_yaml_sequences_and_block_scalars() {
+        colored::control::set_override(true);
+        let yaml = "items:\n- name: a\n  text: |-\n    key: not a key\n    more\n  n: 1\n- - x\n";
+        let colored = colorize_yaml(yaml);
+        assert_eq!(strip_ansi(&colored), yaml);
+        assert!(colored.contains(&"    key: not a key".green().to_string()));
+        assert!(colored.contains(&format!("{}:", "n".cyan())));
+        assert!(colored.contains(&"x".green().to_string()));
+    }
+
+    #[test]
+    fn test_split_yaml_key() {
+        assert_eq!(split_yaml_key("a: 1"), Some(("a", " 1")));
+        assert_eq!(split_yaml_key("a:"), Some(("a", "")));
+        assert_eq!(split_yaml_key("\"a: b\": 1"), Some(("\"a: b\"", " 1")));
+        assert_eq!(split_yaml_key("'it''s': x"), Some(("'it''s'", " x")));
+        assert_eq!(split_yaml_key("http://x:8080"), None);
+        assert_eq!(split_yaml_key("'a: b'"), None);
+    }
+
+    #[test]
+    fn test_colorize_json() {
+        colored::control::set_override(true);
+        let json = r#"{"url":"http://x:8080","q":"say \"hi\"","n":-1.5e3,"ok":false,"none":null,"a":[1,true]}"#;
+        let colored = colorize_json(json);
+        assert_eq!(strip_ansi(&colored), json);
+        assert!(colored.contains(&"\"url\"".cyan().to_string()));
+        assert!(colored.contains(&"\"http://x:8080\"".green().to_string()));
+        assert!(colored.contains(&r#""say \"hi\"""#.green().to_string()));
+        assert!(colored.contains(&"-1.5e3".yellow().to_string()));
+        assert!(colored.contains(&"false".bright_magenta().to_string()));
+        assert!(colored.contains(&"null".bright_black().to_string()));
+    }
+
+    #[test]
+    fn test_format_json_seq_prefixes_record_separator() {
+        let value: Value = serde_yaml::from_str("a: 1").unwrap();
+        let options = OutputOptions {
+            seq: true,
+            ..Default::default()
+        };
+        assert_eq!(format_json(&value, &options).unwrap(), "\x1e{\"a\":1}");
+    }
+
+    #[test]
+    fn test_format_json_ascii_output() {
+        let value: Value = serde_yaml::from_str("{café: 'naïve 😀'}").unwrap();
+        let options = OutputOptions {
+            ascii_output: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_json(&value, &options).unwrap(),
+            r#"{"caf\u00e9":"na\u00efve \ud83d\ude00"}"#
+        );
+    }
+
+    #[test]
+    fn test_format_json_colors_only_when_requested() {
+        colored::control::set_override(true);
+        let value: Value = serde_yaml::from_str("a: 1").unwrap();
+        let plain = format_json(&value, &OutputOptions::default()).unwrap();
+        assert_eq!(plain, r#"{"a":1}"#);
+
+        let options = OutputOptions {
+            colors: true,
+            pretty_print: true,
+            ..Default::default()
+        };
+        let colored = format_json(&value, &options).unwrap();
+        assert_ne!(colored, strip_ansi(&colored));
+        assert_eq!(strip_ansi(&colored), "{\n  \"a\": 1\n}");
+    }
+
+    #[test]
+    fn test_yaml_document_separator() {
+        let value: Value = serde_yaml::from_str("a: 1").unwrap();
+        let with_doc = format_yaml(&value, &OutputOptions::default()).unwrap();
+        assert_eq!(with_doc, "---\na: 1\n");
+
+        let options = OutputOptions {
+            no_doc: true,
+            ..Default::default()
+        };
+        assert_eq!(format_yaml(&value, &options).unwrap(), "a: 1\n");
+    }
+
+    #[test]
+    fn test_yaml_null_document_has_separator() {
+        let with_doc = format_yaml(&Value::Null, &OutputOptions::default()).unwrap();
+        assert_eq!(with_doc, "---\nnull\n");
+    }
+
+    #[test]
+    fn test_strip_document_markers() {
+        assert_eq!(
+            strip_document_markers("--- !tag\na: |\n  ---\n  x\n...\n"),
+            "!tag\na: |\n  ---\n  x\n"
+        );
+    }
 }
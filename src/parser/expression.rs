@@ -5,6 +5,7 @@
 #![allow(dead_code)]
 
 use anyhow::{Context, Result, anyhow};
+use std::cell::{Cell, RefCell};
 use std::iter::Peekable;
 use std::str::Chars;
 
@@ -29,6 +30,12 @@ pub enum Expression {
         index: isize,
     },
 
+    /// Index by a computed key or position (.[expr])
+    DynamicIndex {
+        target: Box<Expression>,
+        index: Box<Expression>,
+    },
+
     /// Array/Object iterator (.[])
     Iterator { target: Box<Expression> },
 
@@ -38,7 +45,7 @@ pub enum Expression {
         right: Box<Expression>,
     },
 
-    /// Comma (,) - collect multiple results
+    /// Comma (,) - outputs of the left side, then of the right side
     Comma {
         left: Box<Expression>,
         right: Box<Expression>,
@@ -56,6 +63,13 @@ pub enum Expression {
         value: Box<Expression>,
     },
 
+    /// Arithmetic update assignment (+=, -=, *=, /=, %=, //=)
+    ArithmeticUpdate {
+        target: Box<Expression>,
+        op: String,
+        value: Box<Expression>,
+    },
+
     /// Addition (+)
     Add {
         left: Box<Expression>,
@@ -140,9 +154,15 @@ pub enum Expression {
     /// Select filter
     Select { condition: Box<Expression> },
 
+    /// Type-selecting filter (values, numbers, strings, ...)
+    TypeFilter { name: String },
+
     /// Keys function
     Keys { target: Box<Expression> },
 
+    /// Keys function preserving insertion order
+    KeysUnsorted { target: Box<Expression> },
+
     /// Length function
     Length { target: Box<Expression> },
 
@@ -155,6 +175,9 @@ pub enum Expression {
         key: Box<Expression>,
     },
 
+    /// In function (whether the input is a key of the container)
+    In { container: Box<Expression> },
+
     /// Sort function
     Sort { target: Box<Expression> },
 
@@ -164,21 +187,45 @@ pub enum Expression {
     /// Unique function
     Unique { target: Box<Expression> },
 
+    /// Unique by key expression
+    UniqueBy {
+        target: Box<Expression>,
+        key_expr: Box<Expression>,
+    },
+
     /// Flatten function
     Flatten { target: Box<Expression> },
 
+    /// Sort by key expression(s)
+    SortBy {
+        target: Box<Expression>,
+        key_expr: Box<Expression>,
+    },
+
     /// Group by function
     GroupBy {
         target: Box<Expression>,
         key_expr: Box<Expression>,
     },
 
+    /// Count by function (`{key, count}` per group)
+    CountBy {
+        target: Box<Expression>,
+        key_expr: Box<Expression>,
+    },
+
     /// Map function
     Map {
         target: Box<Expression>,
         expr: Box<Expression>,
     },
 
+    /// Map values function (keeps object/array shape)
+    MapValues {
+        target: Box<Expression>,
+        expr: Box<Expression>,
+    },
+
     /// Filter function
     Filter {
         target: Box<Expression>,
@@ -188,12 +235,71 @@ pub enum Expression {
     /// Recurse function (..)
     Recurse,
 
+    /// Bottom-up recursive transformation (walk(f))
+    Walk { f: Box<Expression> },
+
+    /// Recurse with a custom descent (recurse(f))
+    RecurseF {
+        f: Box<Expression>,
+        cond: Option<Box<Expression>>,
+    },
+
+    /// Now function (current Unix timestamp)
+    Now,
+
+    /// Todate function (timestamp to ISO 8601 string)
+    ToDate,
+
+    /// Fromdate function (ISO 8601 string to timestamp)
+    FromDate,
+
+    /// Strftime function (format a timestamp)
+    Strftime { format: Box<Expression> },
+
+    /// Gmtime function (timestamp to broken down time)
+    Gmtime,
+
+    /// Mktime function (broken down time to timestamp)
+    Mktime,
+
+    /// Strptime function (parse a string into broken down time)
+    Strptime { format: Box<Expression> },
+
+    /// Tostream function (value to [path, leaf] events)
+    ToStream,
+
+    /// Fromstream function (reassemble values from events)
+    FromStream { expr: Box<Expression> },
+
     /// Parenthesized expression
     Group { expr: Box<Expression> },
 
     /// Variable reference ($name)
     Variable { name: String },
 
+    /// Source location of a `$__loc__` reference
+    Loc { file: String, line: usize },
+
+    /// Function definition (`def name(params): body; rest`), visible to its
+    /// own body and to `rest`
+    FunctionDef {
+        name: String,
+        params: Vec<String>,
+        body: Box<Expression>,
+        rest: Box<Expression>,
+    },
+
+    /// Call of a user-defined function
+    FunctionCall { name: String, args: Vec<Expression> },
+
+    /// Variable binding (`source as $name | body`, or a destructuring
+    /// pattern in place of `$name`)
+    Bind {
+        source: Box<Expression>,
+        pattern: Pattern,
+        body: Box<Expression>,
+    },
+
     /// Array constructor
     Array { elements: Vec<Expression> },
 
@@ -254,6 +360,7 @@ pub enum Expression {
     Range {
         start: Box<Expression>,
         end: Box<Expression>,
+        by: Option<Box<Expression>>,
     },
 
     /// Limit function
@@ -283,8 +390,8 @@ pub enum Expression {
     /// Debug function
     Debug { expr: Box<Expression> },
 
-    /// Env function - read environment variable
-    Env { name: Box<Expression> },
+    /// Env function - read one environment variable, or all of them as an object
+    Env { name: Option<Box<Expression>> },
 
     /// Split function
     Split {
@@ -358,6 +465,31 @@ pub enum Expression {
     /// Add function (sum all elements)
     AddOp,
 
+    /// Explode function (string to codepoints)
+    Explode,
+
+    /// Implode function (codepoints to string)
+    Implode,
+
+    /// Ascii function (codepoint to one-character string)
+    Ascii,
+
+    /// Abs function (absolute value)
+    Abs,
+
+    /// Signum function (-1, 0 or 1)
+    Signum,
+
+    /// Unary math function on a number (log, exp, sin, ...)
+    MathFn { name: String },
+
+    /// Binary math function of two numbers (atan2)
+    MathFn2 {
+        name: String,
+        left: Box<Expression>,
+        right: Box<Expression>,
+    },
+
     /// Min function
     Min { target: Box<Expression> },
 
@@ -410,12 +542,14 @@ pub enum Expression {
     Test {
         target: Box<Expression>,
         pattern: Box<Expression>,
+        flags: Option<Box<Expression>>,
     },
 
     /// Match function (regex)
     Match {
         target: Box<Expression>,
         pattern: Box<Expression>,
+        flags: Option<Box<Expression>>,
     },
 
     /// Capture function (regex)
@@ -430,10 +564,11 @@ pub enum Expression {
         pattern: Box<Expression>,
     },
 
-    /// Splits function
+    /// Splits function (regex split, streaming the pieces)
     Splits {
         target: Box<Expression>,
         pattern: Box<Expression>,
+        flags: Option<Box<Expression>>,
     },
 
     /// Sub function
@@ -441,6 +576,7 @@ pub enum Expression {
         target: Box<Expression>,
         pattern: Box<Expression>,
         replacement: Box<Expression>,
+        flags: Option<Box<Expression>>,
     },
 
     /// Gsub function
@@ -448,39 +584,326 @@ pub enum Expression {
         target: Box<Expression>,
         pattern: Box<Expression>,
         replacement: Box<Expression>,
+        flags: Option<Box<Expression>>,
     },
+
+    /// String interpolation ("a \(.b) c") - literal and interpolated parts
+    Interpolation { parts: Vec<Expression> },
+}
+
+/// The left-hand side of an `as` binding
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    /// `$name` - binds the whole value
+    Variable(String),
+    /// `[p0, p1, ...]` - destructures array elements by position
+    Array(Vec<Pattern>),
+    /// `{key: p, $name, ...}` - destructures object values by key
+    Object(Vec<(Expression, Pattern)>),
 }
 
+/// Default limit on how deeply expressions may nest
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
 /// Parser for jq-like expressions
-pub struct ExpressionParser;
+pub struct ExpressionParser {
+    /// Maximum nesting depth before parsing fails
+    max_depth: usize,
+    /// Current nesting depth
+    depth: Cell<usize>,
+    /// User-defined functions in scope, as name and arity
+    functions: RefCell<Vec<(String, usize)>>,
+    /// Length of the input being parsed, in characters
+    source_len: Cell<usize>,
+    /// Character offsets of the newlines in the input being parsed
+    newlines: RefCell<Vec<usize>>,
+}
 
 impl ExpressionParser {
     /// Create a new expression parser
     pub fn new() -> Self {
-        Self
+        Self::with_max_depth(DEFAULT_MAX_DEPTH)
+    }
+
+    /// Create a parser that rejects expressions nesting deeper than
+    /// `max_depth` (groups, arrays, objects, unary operators and so on)
+    pub fn with_max_depth(max_depth: usize) -> Self {
+        Self {
+            max_depth,
+            depth: Cell::new(0),
+            functions: RefCell::new(Vec::new()),
+            source_len: Cell::new(0),
+            newlines: RefCell::new(Vec::new()),
+        }
     }
 
     /// Parse an expression string into an AST
+    ///
+    /// Errors report the character offset where parsing stopped, followed by
+    /// the offending line of the input with a caret under that position.
     pub fn parse(&self, input: &str) -> Result<Expression> {
+        self.source_len.set(input.chars().count());
+        *self.newlines.borrow_mut() = input
+            .chars()
+            .enumerate()
+            .filter(|&(_, c)| c == '\n')
+            .map(|(i, _)| i)
+            .collect();
+
         let mut chars = input.chars().peekable();
-        self.parse_expression(&mut chars)
+        let result = self.parse_expression(&mut chars).and_then(|expr| {
+            self.skip_whitespace(&mut chars);
+            match self.peek_char(&mut chars) {
+                None => Ok(expr),
+                Some(c) => Err(anyhow!("Unexpected '{}' after expression", c)),
+            }
+        });
+
+        result.map_err(|err| {
+            // Whatever is left unconsumed starts at the error position
+            let offset = input.chars().count() - chars.count();
+            anyhow!(
+                "{:#} at offset {}\n{}",
+                err,
+                offset,
+                Self::error_snippet(input, offset)
+            )
+        })
+    }
+
+    /// The line of `input` containing `offset`, with a caret under it
+    fn error_snippet(input: &str, offset: usize) -> String {
+        let mut line_start = 0;
+        for (i, c) in input.chars().enumerate() {
+            if i >= offset {
+                break;
+            }
+            if c == '\n' {
+                line_start = i + 1;
+            }
+        }
+        let line: String = input
+            .chars()
+            .skip(line_start)
+            .take_while(|&c| c != '\n')
+            .collect();
+        format!("  {}\n  {}^", line, " ".repeat(offset - line_start))
     }
 
-    /// Parse the main expression (handles pipes)
+    /// Parse the main expression (handles pipes and leading `def`s)
     fn parse_expression(&self, chars: &mut Peekable<Chars>) -> Result<Expression> {
-        let left = self.parse_or(chars)?;
+        if self.next_keyword_is(chars, "def") {
+            return self.parse_function_def(chars);
+        }
 
-        self.skip_whitespace(chars);
+        // Pipes associate to the right; the segments are collected in a
+        // loop so a long chain doesn't recurse once per `|`
+        let mut segments = vec![self.parse_comma(chars)?];
 
-        if self.peek_char(chars) == Some('|') {
-            // Check it's not ||
-            if self.peek_chars(chars, 2).as_deref() != Some("||") {
+        loop {
+            self.skip_whitespace(chars);
+            // Check it's | and not ||
+            if self.peek_char(chars) == Some('|')
+                && self.peek_chars(chars, 2).as_deref() != Some("||")
+            {
                 chars.next(); // consume |
-                let right = self.parse_expression(chars)?;
-                return Ok(Expression::Pipe {
+                // A definition takes in the rest of the pipeline
+                if self.next_keyword_is(chars, "def") {
+                    segments.push(self.parse_function_def(chars)?);
+                    break;
+                }
+                segments.push(self.parse_comma(chars)?);
+            } else {
+                break;
+            }
+        }
+
+        let mut expr = segments.pop().unwrap();
+        while let Some(left) = segments.pop() {
+            expr = Expression::Pipe {
+                left: Box::new(left),
+                right: Box::new(expr),
+            };
+        }
+        Ok(expr)
+    }
+
+    /// Parse the rest of `source as pattern | body`
+    ///
+    /// The body takes in the rest of the pipeline, so `1 + 2 as $x | $x`
+    /// binds `$x` to 2 and adds its result to 1, as in jq.
+    fn parse_binding(&self, source: Expression, chars: &mut Peekable<Chars>) -> Result<Expression> {
+        self.consume_keyword(chars, "as")?;
+        let pattern = self.parse_pattern(chars)?;
+        self.skip_whitespace(chars);
+        if self.peek_char(chars) != Some('|') || self.peek_chars(chars, 2).as_deref() == Some("||")
+        {
+            return Err(anyhow!("Expected | after as pattern"));
+        }
+        chars.next();
+        let body = self.parse_expression(chars)?;
+
+        Ok(Expression::Bind {
+            source: Box::new(source),
+            pattern,
+            body: Box::new(body),
+        })
+    }
+
+    /// Parse a binding pattern: `$name`, `[p, ...]` or `{key: p, ...}`
+    ///
+    /// Object pattern keys are written as in object construction, and
+    /// `{$name}` binds `$name` to `.name`; `{$name: p}` does both.
+    fn parse_pattern(&self, chars: &mut Peekable<Chars>) -> Result<Pattern> {
+        self.skip_whitespace(chars);
+        match self.peek_char(chars) {
+            Some('$') => {
+                chars.next();
+                let name = self
+                    .parse_identifier(chars)
+                    .map_err(|_| anyhow!("Expected variable name after $"))?;
+                Ok(Pattern::Variable(name))
+            }
+            Some('[') => {
+                chars.next();
+                let mut elements = vec![self.parse_pattern(chars)?];
+                loop {
+                    self.skip_whitespace(chars);
+                    match chars.next() {
+                        Some(',') => elements.push(self.parse_pattern(chars)?),
+                        Some(']') => return Ok(Pattern::Array(elements)),
+                        _ => return Err(anyhow!("Expected , or ] in array pattern")),
+                    }
+                }
+            }
+            Some('{') => {
+                chars.next();
+                let mut entries = Vec::new();
+                loop {
+                    let (key, shorthand) = self.parse_object_key(chars)?;
+                    self.skip_whitespace(chars);
+                    let variable = match shorthand {
+                        Some(Expression::Variable { name }) => Some(Pattern::Variable(name)),
+                        _ => None,
+                    };
+                    if self.peek_char(chars) == Some(':') {
+                        chars.next();
+                        if let Some(variable) = variable {
+                            entries.push((key.clone(), variable));
+                        }
+                        entries.push((key, self.parse_pattern(chars)?));
+                    } else {
+                        let variable = variable
+                            .ok_or_else(|| anyhow!("Expected : after object pattern key"))?;
+                        entries.push((key, variable));
+                    }
+
+                    self.skip_whitespace(chars);
+                    match chars.next() {
+                        Some(',') => continue,
+                        Some('}') => return Ok(Pattern::Object(entries)),
+                        _ => return Err(anyhow!("Expected , or }} in object pattern")),
+                    }
+                }
+            }
+            _ => Err(anyhow!("Expected $name, [...] or {{...}} pattern after as")),
+        }
+    }
+
+    /// Parse `def name: body; rest` or `def name(f; $x): body; rest`
+    ///
+    /// The function is in scope for its own body, so it may recurse, and for
+    /// the rest of the expression. Parameters are filters, called by name
+    /// inside the body; a `$x` parameter is also bound as a variable to each
+    /// output of its argument, and is kept with its `$` in `params`.
+    fn parse_function_def(&self, chars: &mut Peekable<Chars>) -> Result<Expression> {
+        self.consume_keyword(chars, "def")?;
+        self.skip_whitespace(chars);
+        let name = self.parse_identifier(chars)?;
+        self.skip_whitespace(chars);
+
+        let mut params = Vec::new();
+        if self.peek_char(chars) == Some('(') {
+            chars.next();
+            loop {
+                self.skip_whitespace(chars);
+                let sigil = if self.peek_char(chars) == Some('$') {
+                    chars.next();
+                    "$"
+                } else {
+                    ""
+                };
+                params.push(format!("{}{}", sigil, self.parse_identifier(chars)?));
+                self.skip_whitespace(chars);
+                match chars.next() {
+                    Some(';') => continue,
+                    Some(')') => break,
+                    _ => return Err(anyhow!("Expected ; or ) in parameters of {}", name)),
+                }
+            }
+            self.skip_whitespace(chars);
+        }
+        if chars.next() != Some(':') {
+            return Err(anyhow!("Expected : after def {}", name));
+        }
+
+        let signature = (name.clone(), params.len());
+        let in_body = params
+            .iter()
+            .map(|param| (param.trim_start_matches('$').to_string(), 0));
+        let body = self.in_scope(std::iter::once(signature.clone()).chain(in_body), || {
+            self.parse_expression(chars)
+        })?;
+        self.skip_whitespace(chars);
+        if chars.next() != Some(';') {
+            return Err(anyhow!("Expected ; after the body of {}", name));
+        }
+        let rest = self.in_scope([signature], || self.parse_expression(chars))?;
+
+        Ok(Expression::FunctionDef {
+            name,
+            params,
+            body: Box::new(body),
+            rest: Box::new(rest),
+        })
+    }
+
+    /// Run `parse` with `functions` added to the scope
+    fn in_scope<T>(
+        &self,
+        functions: impl IntoIterator<Item = (String, usize)>,
+        parse: impl FnOnce() -> Result<T>,
+    ) -> Result<T> {
+        let scope = self.functions.borrow().len();
+        self.functions.borrow_mut().extend(functions);
+        let result = parse();
+        self.functions.borrow_mut().truncate(scope);
+        result
+    }
+
+    /// Whether a user-defined function `name` of `arity` is in scope
+    fn is_defined(&self, name: &str, arity: usize) -> bool {
+        self.functions
+            .borrow()
+            .iter()
+            .any(|(n, a)| n == name && *a == arity)
+    }
+
+    /// Parse comma expressions (,)
+    fn parse_comma(&self, chars: &mut Peekable<Chars>) -> Result<Expression> {
+        let mut left = self.parse_or(chars)?;
+
+        loop {
+            self.skip_whitespace(chars);
+            if self.peek_char(chars) == Some(',') {
+                chars.next();
+                let right = self.parse_or(chars)?;
+                left = Expression::Comma {
                     left: Box::new(left),
                     right: Box::new(right),
-                });
+                };
+            } else {
+                break;
             }
         }
 
@@ -696,16 +1119,47 @@ impl ExpressionParser {
     }
 
     /// Parse assignment expressions (=, |=, +=, etc.)
+    ///
+    /// The right-hand side extends over arithmetic, `//` and boolean
+    /// operators, so `.a |= . + 1` updates `.a` with `. + 1`.
     fn parse_assignment(&self, chars: &mut Peekable<Chars>) -> Result<Expression> {
-        let left = self.parse_comma(chars)?;
+        let left = self.parse_unary(chars)?;
+
+        if self.next_keyword_is(chars, "as") {
+            return self.parse_binding(left, chars);
+        }
 
         self.skip_whitespace(chars);
 
         // Check for various assignment operators
+        let arithmetic_op = if self.peek_chars(chars, 3).as_deref() == Some("//=") {
+            Some("//")
+        } else {
+            match self.peek_chars(chars, 2).as_deref() {
+                Some("+=") => Some("+"),
+                Some("-=") => Some("-"),
+                Some("*=") => Some("*"),
+                Some("/=") => Some("/"),
+                Some("%=") => Some("%"),
+                _ => None,
+            }
+        };
+        if let Some(op) = arithmetic_op {
+            for _ in 0..=op.len() {
+                chars.next();
+            }
+            let value = self.parse_or(chars)?;
+            return Ok(Expression::ArithmeticUpdate {
+                target: Box::new(left),
+                op: op.to_string(),
+                value: Box::new(value),
+            });
+        }
+
         if self.peek_chars(chars, 2).as_deref() == Some("|=") {
             chars.next();
             chars.next();
-            let value = self.parse_assignment(chars)?;
+            let value = self.parse_or(chars)?;
             return Ok(Expression::Update {
                 target: Box::new(left),
                 value: Box::new(value),
@@ -721,7 +1175,7 @@ impl ExpressionParser {
         match self.peek_char(chars) {
             Some('=') => {
                 chars.next();
-                let value = self.parse_assignment(chars)?;
+                let value = self.parse_or(chars)?;
                 Ok(Expression::Assign {
                     target: Box::new(left),
                     value: Box::new(value),
@@ -731,29 +1185,25 @@ impl ExpressionParser {
         }
     }
 
-    /// Parse comma expressions (,)
-    fn parse_comma(&self, chars: &mut Peekable<Chars>) -> Result<Expression> {
-        let mut elements = vec![self.parse_unary(chars)?];
-
-        loop {
-            self.skip_whitespace(chars);
-            if self.peek_char(chars) == Some(',') {
-                chars.next();
-                elements.push(self.parse_unary(chars)?);
-            } else {
-                break;
-            }
-        }
-
-        if elements.len() == 1 {
-            Ok(elements.into_iter().next().unwrap())
-        } else {
-            Ok(Expression::Array { elements })
+    /// Parse unary expressions (!, -)
+    ///
+    /// Every level of nesting passes through here, so this is where the
+    /// depth limit is enforced before the stack can overflow.
+    fn parse_unary(&self, chars: &mut Peekable<Chars>) -> Result<Expression> {
+        let depth = self.depth.get() + 1;
+        if depth > self.max_depth {
+            return Err(anyhow!(
+                "Expression nests too deeply (maximum depth is {})",
+                self.max_depth
+            ));
         }
+        self.depth.set(depth);
+        let result = self.parse_unary_operand(chars);
+        self.depth.set(depth - 1);
+        result
     }
 
-    /// Parse unary expressions (!, -)
-    fn parse_unary(&self, chars: &mut Peekable<Chars>) -> Result<Expression> {
+    fn parse_unary_operand(&self, chars: &mut Peekable<Chars>) -> Result<Expression> {
         self.skip_whitespace(chars);
 
         match self.peek_char(chars) {
@@ -793,6 +1243,7 @@ impl ExpressionParser {
             Some('{') => self.parse_object_constructor(chars),
             Some('(') => self.parse_group(chars),
             Some('$') => self.parse_variable(chars),
+            Some('@') => self.parse_format(chars),
             Some(c) if c.is_ascii_digit() => self.parse_number_literal(chars),
             Some(c) if c.is_alphabetic() || c == '_' => self.parse_identifier_or_function(chars),
             Some(_) => Err(anyhow!("Unexpected character in expression")),
@@ -804,8 +1255,6 @@ impl ExpressionParser {
     fn parse_dot_expression(&self, chars: &mut Peekable<Chars>) -> Result<Expression> {
         chars.next(); // consume .
 
-        self.skip_whitespace(chars);
-
         // Check for recursive descent (..)
         if self.peek_char(chars) == Some('.') {
             chars.next();
@@ -815,69 +1264,26 @@ impl ExpressionParser {
         // Check for just . (identity)
         if self
             .peek_char(chars)
-            .map(|c| !c.is_alphanumeric() && c != '_' && c != '[')
+            .map(|c| !c.is_alphanumeric() && !matches!(c, '_' | '[' | '"'))
             .unwrap_or(true)
         {
             return Ok(Expression::Identity);
         }
 
-        // Check for array access .[index]
-        if self.peek_char(chars) == Some('[') {
-            let mut expr = self.parse_bracket_access(Expression::Identity, chars)?;
-
-            // Parse chained access (.[0][1], .[0].field)
-            loop {
-                self.skip_whitespace(chars);
-                match self.peek_char(chars) {
-                    Some('.') => {
-                        chars.next();
-                        if self.peek_char(chars) == Some('[') {
-                            expr = self.parse_bracket_access(expr, chars)?;
-                        } else {
-                            let field = self.parse_field_name(chars)?;
-                            expr = Expression::FieldAccess {
-                                target: Box::new(expr),
-                                field,
-                            };
-                        }
-                    }
-                    Some('[') => {
-                        expr = self.parse_bracket_access(expr, chars)?;
-                    }
-                    _ => break,
-                }
+        // Parse the first access (.field, ."field" or .[...])
+        let expr = if self.peek_char(chars) == Some('[') {
+            self.parse_bracket_access(Expression::Identity, chars)?
+        } else {
+            let field = self.parse_dotted_field(chars)?;
+            Expression::FieldAccess {
+                target: Box::new(Expression::Identity),
+                field,
             }
-
-            return Ok(expr);
-        }
-
-        // Parse field name
-        let field = self.parse_field_name(chars)?;
-        let mut expr = Expression::FieldAccess {
-            target: Box::new(Expression::Identity),
-            field,
         };
+        let expr = self.parse_optional(expr, chars);
 
-        // Parse chained access (.field.subfield[0])
-        loop {
-            self.skip_whitespace(chars);
-            match self.peek_char(chars) {
-                Some('.') => {
-                    chars.next();
-                    let field = self.parse_field_name(chars)?;
-                    expr = Expression::FieldAccess {
-                        target: Box::new(expr),
-                        field,
-                    };
-                }
-                Some('[') => {
-                    expr = self.parse_bracket_access(expr, chars)?;
-                }
-                _ => break,
-            }
-        }
-
-        Ok(expr)
+        // Parse chained access (.field.subfield[0], .[0][1], .[0].field)
+        self.parse_postfix_access(expr, chars)
     }
 
     /// Parse bracket access ([index], ["key"], [:], etc.)
@@ -898,23 +1304,20 @@ impl ExpressionParser {
         }
 
         // Check for string key ["field"]
-        if let Some(c) = self.peek_char(chars) {
-            if c == '"' || c == '\'' {
-                let key = self.parse_string_literal(chars)?;
-                self.skip_whitespace(chars);
-                if self.peek_char(chars) != Some(']') {
-                    return Err(anyhow!("Expected ] after string key"));
-                }
-                chars.next();
-
-                // Convert to field access
-                if let Expression::Literal(serde_yaml::Value::String(s)) = key {
+        if matches!(self.peek_char(chars), Some('"') | Some('\'')) {
+            let mut lookahead = chars.clone();
+            if let Ok(Expression::Literal(serde_yaml::Value::String(field))) =
+                self.parse_string_literal(&mut lookahead)
+            {
+                self.skip_whitespace(&mut lookahead);
+                if self.peek_char(&mut lookahead) == Some(']') {
+                    lookahead.next();
+                    *chars = lookahead;
                     return Ok(Expression::FieldAccess {
                         target: Box::new(target),
-                        field: s,
+                        field,
                     });
                 }
-                return Err(anyhow!("String key must be a string literal"));
             }
         }
 
@@ -938,6 +1341,20 @@ impl ExpressionParser {
             });
         }
 
+        // Anything other than an integer index or slice is a computed index
+        if !self.starts_integer_index(chars) {
+            let index = self.parse_expression(chars)?;
+            self.skip_whitespace(chars);
+            if self.peek_char(chars) != Some(']') {
+                return Err(anyhow!("Expected ] after index expression"));
+            }
+            chars.next();
+            return Ok(Expression::DynamicIndex {
+                target: Box::new(target),
+                index: Box::new(index),
+            });
+        }
+
         // Parse index or start of slice
         let start = self.parse_signed_integer(chars)?;
         self.skip_whitespace(chars);
@@ -973,6 +1390,32 @@ impl ExpressionParser {
         })
     }
 
+    /// Wrap a term in a try if it is followed by `?` (`.foo?`, `.[0]?`,
+    /// `tonumber?`)
+    fn parse_optional(&self, expr: Expression, chars: &mut Peekable<Chars>) -> Expression {
+        if self.peek_char(chars) == Some('?') {
+            chars.next();
+            Expression::Try {
+                expr: Box::new(expr),
+                catch: None,
+            }
+        } else {
+            expr
+        }
+    }
+
+    /// Parse the field after a dot: a plain name or a quoted string
+    /// (`."weird.key"`) for names with special characters
+    fn parse_dotted_field(&self, chars: &mut Peekable<Chars>) -> Result<String> {
+        if self.peek_char(chars) != Some('"') {
+            return self.parse_field_name(chars);
+        }
+        match self.parse_string_literal(chars)? {
+            Expression::Literal(serde_yaml::Value::String(field)) => Ok(field),
+            _ => Err(anyhow!("Expected a quoted field name after .")),
+        }
+    }
+
     /// Parse a field name (identifier after dot)
     fn parse_field_name(&self, chars: &mut Peekable<Chars>) -> Result<String> {
         let mut name = String::new();
@@ -1028,13 +1471,33 @@ impl ExpressionParser {
     fn parse_string_literal(&self, chars: &mut Peekable<Chars>) -> Result<Expression> {
         let quote = chars.next().unwrap();
         let mut value = String::new();
+        let mut parts = vec![];
 
         while let Some(c) = chars.next() {
             if c == quote {
-                return Ok(Expression::Literal(serde_yaml::Value::String(value)));
+                if parts.is_empty() {
+                    return Ok(Expression::Literal(serde_yaml::Value::String(value)));
+                }
+                if !value.is_empty() {
+                    parts.push(Expression::Literal(serde_yaml::Value::String(value)));
+                }
+                return Ok(Expression::Interpolation { parts });
             }
             if c == '\\' {
                 match chars.next() {
+                    Some('(') => {
+                        // `\(expr)` interpolates the value of expr
+                        if !value.is_empty() {
+                            parts.push(Expression::Literal(serde_yaml::Value::String(
+                                std::mem::take(&mut value),
+                            )));
+                        }
+                        parts.push(self.parse_expression(chars)?);
+                        self.skip_whitespace(chars);
+                        if chars.next() != Some(')') {
+                            return Err(anyhow!("Expected ) to close string interpolation"));
+                        }
+                    }
                     Some('n') => value.push('\n'),
                     Some('t') => value.push('\t'),
                     Some('r') => value.push('\r'),
@@ -1096,35 +1559,32 @@ impl ExpressionParser {
             return Ok(Expression::Array { elements: vec![] });
         }
 
-        let mut elements = vec![];
-        loop {
-            // Parse element without comma handling at the top level
-            // We need to parse at a level that doesn't include comma
-            // parse_unary is the level before comma
-            elements.push(self.parse_unary(chars)?);
-            self.skip_whitespace(chars);
-
-            match self.peek_char(chars) {
-                Some(',') => {
-                    chars.next();
-                    self.skip_whitespace(chars);
-                    if self.peek_char(chars) == Some(']') {
-                        break;
-                    }
-                }
-                Some(']') => break,
-                _ => return Err(anyhow!("Expected , or ] in array constructor")),
-            }
-        }
+        // The contents are a full expression; a top-level comma separates
+        // the elements, and each element contributes all of its outputs
+        let contents = self.parse_expression(chars)?;
+        self.skip_whitespace(chars);
 
         if self.peek_char(chars) != Some(']') {
             return Err(anyhow!("Expected ] to close array constructor"));
         }
         chars.next();
 
+        let mut elements = vec![];
+        Self::split_comma(contents, &mut elements);
         Ok(Expression::Array { elements })
     }
 
+    /// Flatten a chain of top-level commas into its operands
+    fn split_comma(expr: Expression, out: &mut Vec<Expression>) {
+        match expr {
+            Expression::Comma { left, right } => {
+                Self::split_comma(*left, out);
+                Self::split_comma(*right, out);
+            }
+            other => out.push(other),
+        }
+    }
+
     /// Parse object constructor
     fn parse_object_constructor(&self, chars: &mut Peekable<Chars>) -> Result<Expression> {
         chars.next(); // consume {
@@ -1137,17 +1597,16 @@ impl ExpressionParser {
 
         let mut fields = vec![];
         loop {
-            // Parse key at unary level to avoid comma handling
-            let key = self.parse_unary(chars)?;
+            let (key, shorthand) = self.parse_object_key(chars)?;
             self.skip_whitespace(chars);
 
-            if self.peek_char(chars) != Some(':') {
-                return Err(anyhow!("Expected : after object key"));
-            }
-            chars.next();
-
-            // Parse value at unary level
-            let value = self.parse_unary(chars)?;
+            let value = if self.peek_char(chars) == Some(':') {
+                chars.next();
+                // Parse value at unary level to avoid comma handling
+                self.parse_unary(chars)?
+            } else {
+                shorthand.ok_or_else(|| anyhow!("Expected : after object key"))?
+            };
             fields.push((key, value));
 
             self.skip_whitespace(chars);
@@ -1172,6 +1631,49 @@ impl ExpressionParser {
         Ok(Expression::Object { fields })
     }
 
+    /// Parse an object key, along with the value it stands for on its own
+    ///
+    /// `name` and `"name"` are literal keys, `$name` is the key `name`, and
+    /// `(expr)` is a computed key. Without a `: value`, `{name}` and
+    /// `{"name"}` mean `{name: .name}` and `{$name}` means `{name: $name}`.
+    fn parse_object_key(
+        &self,
+        chars: &mut Peekable<Chars>,
+    ) -> Result<(Expression, Option<Expression>)> {
+        self.skip_whitespace(chars);
+
+        match self.peek_char(chars) {
+            Some('$') => {
+                chars.next();
+                let name = self
+                    .parse_identifier(chars)
+                    .map_err(|_| anyhow!("Expected variable name after $"))?;
+                let key = Expression::Literal(serde_yaml::Value::String(name.clone()));
+                Ok((key, Some(Expression::Variable { name })))
+            }
+            Some('"') | Some('\'') => {
+                let key = self.parse_string_literal(chars)?;
+                let shorthand = Expression::DynamicIndex {
+                    target: Box::new(Expression::Identity),
+                    index: Box::new(key.clone()),
+                };
+                Ok((key, Some(shorthand)))
+            }
+            Some('(') => Ok((self.parse_group(chars)?, None)),
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                let name = self.parse_identifier(chars)?;
+                let key = Expression::Literal(serde_yaml::Value::String(name.clone()));
+                let shorthand = Expression::FieldAccess {
+                    target: Box::new(Expression::Identity),
+                    field: name,
+                };
+                Ok((key, Some(shorthand)))
+            }
+            // Other literals (numbers) are kept as YAML keys
+            _ => Ok((self.parse_unary(chars)?, None)),
+        }
+    }
+
     /// Parse group expression
     fn parse_group(&self, chars: &mut Peekable<Chars>) -> Result<Expression> {
         chars.next(); // consume (
@@ -1184,28 +1686,57 @@ impl ExpressionParser {
         chars.next();
 
         // Check for chained access after group
-        let mut result = expr;
+        let result = self.parse_postfix_access(expr, chars)?;
+
+        Ok(Expression::Group {
+            expr: Box::new(result),
+        })
+    }
+
+    /// Parse chained access (.field, [index]) following a term
+    fn parse_postfix_access(
+        &self,
+        mut expr: Expression,
+        chars: &mut Peekable<Chars>,
+    ) -> Result<Expression> {
         loop {
             self.skip_whitespace(chars);
             match self.peek_char(chars) {
                 Some('.') => {
                     chars.next();
-                    let field = self.parse_field_name(chars)?;
-                    result = Expression::FieldAccess {
-                        target: Box::new(result),
-                        field,
-                    };
+                    if self.peek_char(chars) == Some('[') {
+                        expr = self.parse_bracket_access(expr, chars)?;
+                    } else {
+                        let field = self.parse_dotted_field(chars)?;
+                        expr = Expression::FieldAccess {
+                            target: Box::new(expr),
+                            field,
+                        };
+                    }
+                    expr = self.parse_optional(expr, chars);
                 }
                 Some('[') => {
-                    result = self.parse_bracket_access(result, chars)?;
+                    expr = self.parse_bracket_access(expr, chars)?;
+                    expr = self.parse_optional(expr, chars);
                 }
+                // `f?` suppresses the errors of any term (`tonumber?`, `(.a | f)?`)
+                Some('?') => expr = self.parse_optional(expr, chars),
                 _ => break,
             }
         }
 
-        Ok(Expression::Group {
-            expr: Box::new(result),
-        })
+        Ok(expr)
+    }
+
+    /// Check whether a bracket holds a plain integer index or slice start
+    /// (`[1]`, `[-1]`, `[1:]`) rather than an expression such as `[1 + 1]`
+    fn starts_integer_index(&self, chars: &mut Peekable<Chars>) -> bool {
+        let mut lookahead = chars.clone();
+        if self.parse_signed_integer(&mut lookahead).is_err() {
+            return false;
+        }
+        self.skip_whitespace(&mut lookahead);
+        matches!(self.peek_char(&mut lookahead), Some(']') | Some(':'))
     }
 
     /// Parse variable reference
@@ -1226,7 +1757,37 @@ impl ExpressionParser {
             return Err(anyhow!("Expected variable name after $"));
         }
 
-        Ok(Expression::Variable { name })
+        if name == "__loc__" {
+            let loc = Expression::Loc {
+                file: "<stdin>".to_string(),
+                line: self.current_line(chars),
+            };
+            return self.parse_postfix_access(loc, chars);
+        }
+
+        self.parse_postfix_access(Expression::Variable { name }, chars)
+    }
+
+    /// The 1-based line of the input that parsing has reached
+    fn current_line(&self, chars: &Peekable<Chars>) -> usize {
+        let offset = self.source_len.get() - chars.clone().count();
+        self.newlines
+            .borrow()
+            .partition_point(|&newline| newline < offset)
+            + 1
+    }
+
+    /// Parse a format string filter (@name)
+    fn parse_format(&self, chars: &mut Peekable<Chars>) -> Result<Expression> {
+        chars.next(); // consume @
+        let fmt = self
+            .parse_identifier(chars)
+            .map_err(|_| anyhow!("Expected format name after @"))?;
+
+        Ok(Expression::Format {
+            target: Box::new(Expression::Identity),
+            fmt,
+        })
     }
 
     /// Parse identifier or function call
@@ -1238,6 +1799,7 @@ impl ExpressionParser {
             "true" => return Ok(Expression::Literal(serde_yaml::Value::Bool(true))),
             "false" => return Ok(Expression::Literal(serde_yaml::Value::Bool(false))),
             "null" => return Ok(Expression::Literal(serde_yaml::Value::Null)),
+            "if" => return self.parse_if(chars),
             _ => {}
         }
 
@@ -1245,12 +1807,19 @@ impl ExpressionParser {
 
         // Check for function call with parentheses
         if self.peek_char(chars) == Some('(') {
-            return self.parse_function_call(name, chars);
+            let call = self.parse_function_call(name, chars)?;
+            return self.parse_postfix_access(call, chars);
+        }
+
+        // User-defined functions shadow the built-ins
+        if self.is_defined(&name, 0) {
+            let call = Expression::FunctionCall { name, args: vec![] };
+            return self.parse_postfix_access(call, chars);
         }
 
         // Check if it's a built-in function without parentheses (e.g., "keys", "length")
         match self.parse_bare_function(&name) {
-            Some(expr) => Ok(expr),
+            Some(expr) => self.parse_postfix_access(expr, chars),
             None => {
                 // It's just an identifier - treat as field access on identity
                 Ok(Expression::FieldAccess {
@@ -1261,18 +1830,96 @@ impl ExpressionParser {
         }
     }
 
+    /// Parse the rest of `if c then a (elif c then b)* (else d)? end`
+    ///
+    /// `elif` chains become nested conditionals and a missing `else` branch
+    /// passes the input through unchanged.
+    fn parse_if(&self, chars: &mut Peekable<Chars>) -> Result<Expression> {
+        let condition = self.parse_expression(chars)?;
+        self.expect_keyword(chars, "then")?;
+        let then_branch = self.parse_expression(chars)?;
+
+        let else_branch = if self.next_keyword_is(chars, "elif") {
+            self.expect_keyword(chars, "elif")?;
+            return Ok(Expression::IfThenElse {
+                condition: Box::new(condition),
+                then_branch: Box::new(then_branch),
+                else_branch: Box::new(self.parse_if(chars)?),
+            });
+        } else if self.next_keyword_is(chars, "else") {
+            self.expect_keyword(chars, "else")?;
+            self.parse_expression(chars)?
+        } else {
+            Expression::Identity
+        };
+        self.expect_keyword(chars, "end")?;
+
+        let expr = Expression::IfThenElse {
+            condition: Box::new(condition),
+            then_branch: Box::new(then_branch),
+            else_branch: Box::new(else_branch),
+        };
+        self.parse_postfix_access(expr, chars)
+    }
+
+    /// Check whether the next word is `keyword`
+    fn next_keyword_is(&self, chars: &mut Peekable<Chars>, keyword: &str) -> bool {
+        self.skip_whitespace(chars);
+        self.peek_keyword(chars, keyword)
+            && chars
+                .clone()
+                .nth(keyword.len())
+                .is_none_or(|c| !c.is_alphanumeric() && c != '_')
+    }
+
+    /// Consume the word `keyword` or fail
+    fn expect_keyword(&self, chars: &mut Peekable<Chars>, keyword: &str) -> Result<()> {
+        if !self.next_keyword_is(chars, keyword) {
+            return Err(anyhow!("Expected {}", keyword));
+        }
+        self.consume_keyword(chars, keyword)
+    }
+
+    /// Split the arguments of `name(f)` or `name(target; f)` into a target
+    /// and a filter, defaulting the target to the input
+    fn target_and_filter(name: &str, args: Vec<Expression>) -> Result<(Expression, Expression)> {
+        let mut args = args.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(expr), None, None) => Ok((Expression::Identity, expr)),
+            (Some(target), Some(expr), None) => Ok((target, expr)),
+            _ => Err(anyhow!("{} takes 1 or 2 arguments", name)),
+        }
+    }
+
     /// Parse a bare function name (without parentheses)
     fn parse_bare_function(&self, name: &str) -> Option<Expression> {
         match name {
             "keys" => Some(Expression::Keys {
                 target: Box::new(Expression::Identity),
             }),
+            "keys_unsorted" => Some(Expression::KeysUnsorted {
+                target: Box::new(Expression::Identity),
+            }),
             "length" => Some(Expression::Length {
                 target: Box::new(Expression::Identity),
             }),
             "type" => Some(Expression::Type {
                 target: Box::new(Expression::Identity),
             }),
+            "first" => Some(Expression::IndexAccess {
+                target: Box::new(Expression::Identity),
+                index: 0,
+            }),
+            "last" => Some(Expression::IndexAccess {
+                target: Box::new(Expression::Identity),
+                index: -1,
+            }),
+            "min" => Some(Expression::Min {
+                target: Box::new(Expression::Identity),
+            }),
+            "max" => Some(Expression::Max {
+                target: Box::new(Expression::Identity),
+            }),
             "sort" => Some(Expression::Sort {
                 target: Box::new(Expression::Identity),
             }),
@@ -1286,7 +1933,34 @@ impl ExpressionParser {
                 target: Box::new(Expression::Identity),
             }),
             "add" => Some(Expression::AddOp),
+            "tostring" => Some(Expression::ToString {
+                target: Box::new(Expression::Identity),
+            }),
+            "tonumber" => Some(Expression::ToNumber {
+                target: Box::new(Expression::Identity),
+            }),
+            "explode" => Some(Expression::Explode),
+            "implode" => Some(Expression::Implode),
+            "ascii" => Some(Expression::Ascii),
+            "abs" => Some(Expression::Abs),
+            "signum" => Some(Expression::Signum),
+            "log" | "log2" | "log10" | "exp" | "exp2" | "exp10" | "sin" | "cos" | "tan"
+            | "asin" | "acos" | "atan" => Some(Expression::MathFn {
+                name: name.to_string(),
+            }),
+            "env" => Some(Expression::Env { name: None }),
             "recurse" | ".." => Some(Expression::Recurse),
+            "empty" => Some(Expression::Empty),
+            "tostream" => Some(Expression::ToStream),
+            "now" => Some(Expression::Now),
+            "todate" | "todateiso8601" => Some(Expression::ToDate),
+            "fromdate" | "fromdateiso8601" => Some(Expression::FromDate),
+            "gmtime" => Some(Expression::Gmtime),
+            "mktime" => Some(Expression::Mktime),
+            "values" | "nulls" | "booleans" | "numbers" | "strings" | "arrays" | "objects"
+            | "iterables" | "scalars" => Some(Expression::TypeFilter {
+                name: name.to_string(),
+            }),
             _ => None,
         }
     }
@@ -1322,13 +1996,15 @@ impl ExpressionParser {
                 args.push(self.parse_expression(chars)?);
                 self.skip_whitespace(chars);
 
+                // Arguments are separated by `;` as in jq, since `,` is an
+                // operator inside each argument
                 match self.peek_char(chars) {
-                    Some(',') => {
+                    Some(';') => {
                         chars.next();
                         self.skip_whitespace(chars);
                     }
                     Some(')') => break,
-                    _ => return Err(anyhow!("Expected , or ) in function call")),
+                    _ => return Err(anyhow!("Expected ; or ) in function call")),
                 }
             }
         }
@@ -1338,6 +2014,10 @@ impl ExpressionParser {
         }
         chars.next();
 
+        if self.is_defined(&name, args.len()) {
+            return Ok(Expression::FunctionCall { name, args });
+        }
+
         // Handle built-in functions
         match name.as_str() {
             "select" => {
@@ -1361,6 +2041,45 @@ impl ExpressionParser {
                     Err(anyhow!("keys takes 0 or 1 arguments"))
                 }
             }
+            "tostring" => {
+                if args.is_empty() {
+                    Ok(Expression::ToString {
+                        target: Box::new(Expression::Identity),
+                    })
+                } else if args.len() == 1 {
+                    Ok(Expression::ToString {
+                        target: Box::new(args.into_iter().next().unwrap()),
+                    })
+                } else {
+                    Err(anyhow!("tostring takes 0 or 1 arguments"))
+                }
+            }
+            "tonumber" => {
+                if args.is_empty() {
+                    Ok(Expression::ToNumber {
+                        target: Box::new(Expression::Identity),
+                    })
+                } else if args.len() == 1 {
+                    Ok(Expression::ToNumber {
+                        target: Box::new(args.into_iter().next().unwrap()),
+                    })
+                } else {
+                    Err(anyhow!("tonumber takes 0 or 1 arguments"))
+                }
+            }
+            "keys_unsorted" => {
+                if args.is_empty() {
+                    Ok(Expression::KeysUnsorted {
+                        target: Box::new(Expression::Identity),
+                    })
+                } else if args.len() == 1 {
+                    Ok(Expression::KeysUnsorted {
+                        target: Box::new(args.into_iter().next().unwrap()),
+                    })
+                } else {
+                    Err(anyhow!("keys_unsorted takes 0 or 1 arguments"))
+                }
+            }
             "length" => {
                 if args.is_empty() {
                     Ok(Expression::Length {
@@ -1388,13 +2107,63 @@ impl ExpressionParser {
                 }
             }
             "has" => {
-                if args.len() != 2 {
-                    return Err(anyhow!("has requires exactly 2 arguments"));
+                let (target, key) = Self::target_and_filter(&name, args)?;
+                Ok(Expression::Has {
+                    target: Box::new(target),
+                    key: Box::new(key),
+                })
+            }
+            "contains" => {
+                if args.len() != 1 {
+                    return Err(anyhow!("contains requires exactly 1 argument"));
                 }
+                Ok(Expression::Contains {
+                    target: Box::new(Expression::Identity),
+                    value: Box::new(args.into_iter().next().unwrap()),
+                })
+            }
+            "split" => {
                 let mut args = args.into_iter();
-                Ok(Expression::Has {
-                    target: Box::new(args.next().unwrap()),
-                    key: Box::new(args.next().unwrap()),
+                match (args.next(), args.next(), args.next()) {
+                    (Some(separator), None, None) => Ok(Expression::Split {
+                        target: Box::new(Expression::Identity),
+                        separator: Box::new(separator),
+                    }),
+                    // split(re; flags) is [splits(re; flags)]
+                    (Some(pattern), Some(flags), None) => Ok(Expression::Array {
+                        elements: vec![Expression::Splits {
+                            target: Box::new(Expression::Identity),
+                            pattern: Box::new(pattern),
+                            flags: Some(Box::new(flags)),
+                        }],
+                    }),
+                    _ => Err(anyhow!("split takes 1 or 2 arguments")),
+                }
+            }
+            "startswith" => {
+                if args.len() != 1 {
+                    return Err(anyhow!("startswith requires exactly 1 argument"));
+                }
+                Ok(Expression::StartsWith {
+                    target: Box::new(Expression::Identity),
+                    prefix: Box::new(args.into_iter().next().unwrap()),
+                })
+            }
+            "endswith" => {
+                if args.len() != 1 {
+                    return Err(anyhow!("endswith requires exactly 1 argument"));
+                }
+                Ok(Expression::EndsWith {
+                    target: Box::new(Expression::Identity),
+                    suffix: Box::new(args.into_iter().next().unwrap()),
+                })
+            }
+            "in" => {
+                if args.len() != 1 {
+                    return Err(anyhow!("in requires exactly 1 argument"));
+                }
+                Ok(Expression::In {
+                    container: Box::new(args.into_iter().next().unwrap()),
                 })
             }
             "sort" => {
@@ -1449,60 +2218,245 @@ impl ExpressionParser {
                     Err(anyhow!("flatten takes 0 or 1 arguments"))
                 }
             }
-            "group_by" => {
-                if args.len() != 2 {
-                    return Err(anyhow!("group_by requires exactly 2 arguments"));
+            "unique_by" => {
+                if args.len() != 1 {
+                    return Err(anyhow!("unique_by requires exactly 1 argument"));
                 }
-                let mut args = args.into_iter();
+                Ok(Expression::UniqueBy {
+                    target: Box::new(Expression::Identity),
+                    key_expr: Box::new(args.into_iter().next().unwrap()),
+                })
+            }
+            "sort_by" => {
+                if args.len() != 1 {
+                    return Err(anyhow!("sort_by requires exactly 1 argument"));
+                }
+                Ok(Expression::SortBy {
+                    target: Box::new(Expression::Identity),
+                    key_expr: Box::new(args.into_iter().next().unwrap()),
+                })
+            }
+            "group_by" => {
+                let (target, key_expr) = Self::target_and_filter(&name, args)?;
                 Ok(Expression::GroupBy {
-                    target: Box::new(args.next().unwrap()),
-                    key_expr: Box::new(args.next().unwrap()),
+                    target: Box::new(target),
+                    key_expr: Box::new(key_expr),
+                })
+            }
+            "strftime" => {
+                if args.len() != 1 {
+                    return Err(anyhow!("strftime requires exactly 1 argument"));
+                }
+                Ok(Expression::Strftime {
+                    format: Box::new(args.into_iter().next().unwrap()),
+                })
+            }
+            "strptime" => {
+                if args.len() != 1 {
+                    return Err(anyhow!("strptime requires exactly 1 argument"));
+                }
+                Ok(Expression::Strptime {
+                    format: Box::new(args.into_iter().next().unwrap()),
+                })
+            }
+            "count_by" => {
+                let (target, key_expr) = Self::target_and_filter(&name, args)?;
+                Ok(Expression::CountBy {
+                    target: Box::new(target),
+                    key_expr: Box::new(key_expr),
                 })
             }
             "map" => {
-                if args.len() != 2 {
-                    return Err(anyhow!("map requires exactly 2 arguments"));
+                let (target, expr) = Self::target_and_filter(&name, args)?;
+                Ok(Expression::Map {
+                    target: Box::new(target),
+                    expr: Box::new(expr),
+                })
+            }
+            "recurse" => {
+                let mut args = args.into_iter();
+                match (args.next(), args.next(), args.next()) {
+                    (None, None, None) => Ok(Expression::Recurse),
+                    (Some(f), cond, None) => Ok(Expression::RecurseF {
+                        f: Box::new(f),
+                        cond: cond.map(Box::new),
+                    }),
+                    _ => Err(anyhow!("recurse takes 0, 1 or 2 arguments")),
                 }
+            }
+            "test" => {
                 let mut args = args.into_iter();
-                Ok(Expression::Map {
-                    target: Box::new(args.next().unwrap()),
-                    expr: Box::new(args.next().unwrap()),
+                match (args.next(), args.next(), args.next()) {
+                    (Some(pattern), flags, None) => Ok(Expression::Test {
+                        target: Box::new(Expression::Identity),
+                        pattern: Box::new(pattern),
+                        flags: flags.map(Box::new),
+                    }),
+                    _ => Err(anyhow!("test takes 1 or 2 arguments")),
+                }
+            }
+            "match" => {
+                let mut args = args.into_iter();
+                match (args.next(), args.next(), args.next()) {
+                    (Some(pattern), flags, None) => Ok(Expression::Match {
+                        target: Box::new(Expression::Identity),
+                        pattern: Box::new(pattern),
+                        flags: flags.map(Box::new),
+                    }),
+                    _ => Err(anyhow!("match takes 1 or 2 arguments")),
+                }
+            }
+            "sub" | "gsub" => {
+                let mut args = args.into_iter();
+                let (pattern, replacement, flags) =
+                    match (args.next(), args.next(), args.next(), args.next()) {
+                        (Some(pattern), Some(replacement), flags, None) => (
+                            Box::new(pattern),
+                            Box::new(replacement),
+                            flags.map(Box::new),
+                        ),
+                        _ => return Err(anyhow!("{} takes 2 or 3 arguments", name)),
+                    };
+                let target = Box::new(Expression::Identity);
+                Ok(if name == "sub" {
+                    Expression::Sub {
+                        target,
+                        pattern,
+                        replacement,
+                        flags,
+                    }
+                } else {
+                    Expression::Gsub {
+                        target,
+                        pattern,
+                        replacement,
+                        flags,
+                    }
                 })
             }
-            "filter" => {
-                if args.len() != 2 {
-                    return Err(anyhow!("filter requires exactly 2 arguments"));
+            "splits" => {
+                let mut args = args.into_iter();
+                match (args.next(), args.next(), args.next()) {
+                    (Some(pattern), flags, None) => Ok(Expression::Splits {
+                        target: Box::new(Expression::Identity),
+                        pattern: Box::new(pattern),
+                        flags: flags.map(Box::new),
+                    }),
+                    _ => Err(anyhow!("splits takes 1 or 2 arguments")),
+                }
+            }
+            "range" => {
+                let mut args = args.into_iter();
+                match (args.next(), args.next(), args.next(), args.next()) {
+                    (Some(end), None, None, None) => Ok(Expression::Range {
+                        start: Box::new(Expression::Literal(serde_yaml::Value::Number(0.into()))),
+                        end: Box::new(end),
+                        by: None,
+                    }),
+                    (Some(start), Some(end), by, None) => Ok(Expression::Range {
+                        start: Box::new(start),
+                        end: Box::new(end),
+                        by: by.map(Box::new),
+                    }),
+                    _ => Err(anyhow!("range takes 1, 2 or 3 arguments")),
                 }
+            }
+            "atan2" => {
                 let mut args = args.into_iter();
+                match (args.next(), args.next(), args.next()) {
+                    (Some(left), Some(right), None) => Ok(Expression::MathFn2 {
+                        name: name.to_string(),
+                        left: Box::new(left),
+                        right: Box::new(right),
+                    }),
+                    _ => Err(anyhow!("{} requires exactly 2 arguments", name)),
+                }
+            }
+            "path" => {
+                if args.len() != 1 {
+                    return Err(anyhow!("path requires exactly 1 argument"));
+                }
+                Ok(Expression::Path {
+                    expr: Box::new(args.into_iter().next().unwrap()),
+                })
+            }
+            "fromstream" => {
+                if args.len() != 1 {
+                    return Err(anyhow!("fromstream requires exactly 1 argument"));
+                }
+                Ok(Expression::FromStream {
+                    expr: Box::new(args.into_iter().next().unwrap()),
+                })
+            }
+            "walk" => {
+                if args.len() != 1 {
+                    return Err(anyhow!("walk requires exactly 1 argument"));
+                }
+                Ok(Expression::Walk {
+                    f: Box::new(args.into_iter().next().unwrap()),
+                })
+            }
+            "map_values" => {
+                if args.len() != 1 {
+                    return Err(anyhow!("map_values requires exactly 1 argument"));
+                }
+                Ok(Expression::MapValues {
+                    target: Box::new(Expression::Identity),
+                    expr: Box::new(args.into_iter().next().unwrap()),
+                })
+            }
+            "filter" => {
+                let (target, expr) = Self::target_and_filter(&name, args)?;
                 Ok(Expression::Filter {
-                    target: Box::new(args.next().unwrap()),
-                    expr: Box::new(args.next().unwrap()),
+                    target: Box::new(target),
+                    expr: Box::new(expr),
                 })
             }
-            "first" => {
-                if args.is_empty() {
-                    Ok(Expression::First {
-                        expr: Box::new(Expression::Identity),
-                    })
-                } else if args.len() == 1 {
-                    Ok(Expression::First {
-                        expr: Box::new(args.into_iter().next().unwrap()),
-                    })
+            "first" | "last" => {
+                if args.len() != 1 {
+                    return Err(anyhow!("{} takes 0 or 1 arguments", name));
+                }
+                let expr = Box::new(args.into_iter().next().unwrap());
+                if name == "first" {
+                    Ok(Expression::First { expr })
                 } else {
-                    Err(anyhow!("first takes 0 or 1 arguments"))
+                    Ok(Expression::Last { expr })
                 }
             }
-            "last" => {
-                if args.is_empty() {
-                    Ok(Expression::Last {
-                        expr: Box::new(Expression::Identity),
-                    })
-                } else if args.len() == 1 {
-                    Ok(Expression::Last {
-                        expr: Box::new(args.into_iter().next().unwrap()),
-                    })
+            "limit" => {
+                let mut args = args.into_iter();
+                match (args.next(), args.next(), args.next()) {
+                    (Some(n), Some(expr), None) => Ok(Expression::Limit {
+                        n: Box::new(n),
+                        expr: Box::new(expr),
+                    }),
+                    _ => Err(anyhow!("limit requires exactly 2 arguments")),
+                }
+            }
+            "nth" => {
+                let mut args = args.into_iter();
+                match (args.next(), args.next(), args.next()) {
+                    (Some(n), None, None) => Ok(Expression::DynamicIndex {
+                        target: Box::new(Expression::Identity),
+                        index: Box::new(n),
+                    }),
+                    (Some(n), Some(expr), None) => Ok(Expression::Nth {
+                        n: Box::new(n),
+                        expr: Box::new(expr),
+                    }),
+                    _ => Err(anyhow!("nth takes 1 or 2 arguments")),
+                }
+            }
+            "min_by" | "max_by" => {
+                if args.len() != 1 {
+                    return Err(anyhow!("{} requires exactly 1 argument", name));
+                }
+                let target = Box::new(Expression::Identity);
+                let key = Box::new(args.into_iter().next().unwrap());
+                if name == "min_by" {
+                    Ok(Expression::MinBy { target, key })
                 } else {
-                    Err(anyhow!("last takes 0 or 1 arguments"))
+                    Ok(Expression::MaxBy { target, key })
                 }
             }
             "add" => {
@@ -1517,7 +2471,7 @@ impl ExpressionParser {
                     return Err(anyhow!("env requires exactly 1 argument"));
                 }
                 Ok(Expression::Env {
-                    name: Box::new(args.into_iter().next().unwrap()),
+                    name: Some(Box::new(args.into_iter().next().unwrap())),
                 })
             }
             _ => Err(anyhow!("Unknown function: {}", name)),
@@ -1635,6 +2589,66 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_error_reports_position() {
+        let parser = ExpressionParser::new();
+        let err = parser.parse(".a..").unwrap_err().to_string();
+        assert!(err.contains("at offset 3"), "{}", err);
+        assert!(err.ends_with("  .a..\n     ^"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_error_points_at_line() {
+        let parser = ExpressionParser::new();
+        let err = parser.parse(".a |\n.b + * 1").unwrap_err().to_string();
+        assert!(err.contains("Unexpected character"), "{}", err);
+        assert!(err.contains("at offset 10"), "{}", err);
+        assert!(err.ends_with("  .b + * 1\n       ^"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_rejects_trailing_input() {
+        let parser = ExpressionParser::new();
+        let err = parser.parse(".a )").unwrap_err().to_string();
+        assert!(err.contains("Unexpected ')'"), "{}", err);
+        assert!(err.contains("at offset 3"), "{}", err);
+        assert!(parser.parse(".a b").is_err());
+        assert!(parser.parse("  .a  ").is_ok());
+    }
+
+    #[test]
+    fn test_parse_depth_limit() {
+        // A small limit keeps the test within the test thread's stack
+        let parser = ExpressionParser::with_max_depth(32);
+        let deep = format!("{}1{}", "(".repeat(10_000), ")".repeat(10_000));
+        let err = parser.parse(&deep).unwrap_err().to_string();
+        assert!(err.contains("nests too deeply"), "{}", err);
+
+        let deep = format!("{}1{}", "[".repeat(10_000), "]".repeat(10_000));
+        assert!(parser.parse(&deep).is_err());
+        assert!(parser.parse(&"!".repeat(10_000)).is_err());
+
+        // The parser is reusable after hitting the limit
+        assert!(parser.parse("((1))").is_ok());
+    }
+
+    #[test]
+    fn test_parse_long_pipe_chain_without_recursion() {
+        let parser = ExpressionParser::with_max_depth(32);
+        let chain = vec!["."; 10_000].join(" | ");
+        assert!(matches!(
+            parser.parse(&chain).unwrap(),
+            Expression::Pipe { .. }
+        ));
+    }
+
+    #[test]
+    fn test_parse_custom_max_depth() {
+        let parser = ExpressionParser::with_max_depth(4);
+        assert!(parser.parse("((1))").is_ok());
+        assert!(parser.parse("((((1))))").is_err());
+    }
+
     #[test]
     fn test_parse_pipe() {
         let parser = ExpressionParser::new();
@@ -1708,6 +2722,148 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_string_interpolation() {
+        let parser = ExpressionParser::new();
+        let expr = parser.parse(r#""a \(.b) c""#).unwrap();
+        assert_eq!(
+            expr,
+            Expression::Interpolation {
+                parts: vec![
+                    Expression::Literal(serde_yaml::Value::String("a ".to_string())),
+                    Expression::FieldAccess {
+                        target: Box::new(Expression::Identity),
+                        field: "b".to_string(),
+                    },
+                    Expression::Literal(serde_yaml::Value::String(" c".to_string())),
+                ],
+            }
+        );
+        assert!(parser.parse(r#""\(.a""#).is_err());
+    }
+
+    #[test]
+    fn test_parse_comma() {
+        let parser = ExpressionParser::new();
+        let num = |n: i64| Box::new(Expression::Literal(serde_yaml::Value::Number(n.into())));
+        let expr = parser.parse("1, 2, 3").unwrap();
+        assert_eq!(
+            expr,
+            Expression::Comma {
+                left: Box::new(Expression::Comma {
+                    left: num(1),
+                    right: num(2),
+                }),
+                right: num(3),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_comma_binds_looser_than_operators() {
+        let parser = ExpressionParser::new();
+        let expr = parser.parse("1 + 1, 3 | .").unwrap();
+        let Expression::Pipe { left, .. } = expr else {
+            panic!("expected pipe, got {:?}", expr);
+        };
+        assert!(matches!(*left, Expression::Comma { .. }));
+    }
+
+    #[test]
+    fn test_parse_function_def() {
+        let parser = ExpressionParser::new();
+        let expr = parser.parse("def inc: . + 1; inc").unwrap();
+        let Expression::FunctionDef {
+            name, params, rest, ..
+        } = expr
+        else {
+            panic!("expected def, got {:?}", expr);
+        };
+        assert_eq!(name, "inc");
+        assert!(params.is_empty());
+        assert_eq!(
+            *rest,
+            Expression::FunctionCall {
+                name: "inc".to_string(),
+                args: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_function_params_scoped_to_body() {
+        let parser = ExpressionParser::new();
+        let expr = parser.parse("def app(f): f; app(.x), f").unwrap();
+        let Expression::FunctionDef { body, rest, .. } = expr else {
+            panic!("expected def, got {:?}", expr);
+        };
+        assert!(matches!(*body, Expression::FunctionCall { .. }));
+        // Outside the body `f` is a field name again
+        let Expression::Comma { left, right } = *rest else {
+            panic!("expected comma, got {:?}", rest);
+        };
+        assert!(matches!(*left, Expression::FunctionCall { .. }));
+        assert!(matches!(*right, Expression::FieldAccess { .. }));
+    }
+
+    #[test]
+    fn test_parse_function_value_params() {
+        let parser = ExpressionParser::new();
+        let expr = parser.parse("def f($a; g): $a, a, g; f(1; 2)").unwrap();
+        let Expression::FunctionDef { params, .. } = expr else {
+            panic!("expected def, got {:?}", expr);
+        };
+        assert_eq!(params, vec!["$a".to_string(), "g".to_string()]);
+    }
+
+    #[test]
+    fn test_parse_function_def_after_pipe() {
+        let parser = ExpressionParser::new();
+        let expr = parser.parse(".a | def f: 1; f | f").unwrap();
+        let Expression::Pipe { right, .. } = expr else {
+            panic!("expected pipe, got {:?}", expr);
+        };
+        let Expression::FunctionDef { rest, .. } = *right else {
+            panic!("expected def, got {:?}", right);
+        };
+        assert!(matches!(*rest, Expression::Pipe { .. }));
+    }
+
+    #[test]
+    fn test_parse_loc() {
+        let parser = ExpressionParser::new();
+        let expr = parser.parse("1 |\n\n  $__loc__").unwrap();
+        let Expression::Pipe { right, .. } = expr else {
+            panic!("expected pipe, got {:?}", expr);
+        };
+        assert_eq!(
+            *right,
+            Expression::Loc {
+                file: "<stdin>".to_string(),
+                line: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_function_def_errors() {
+        let parser = ExpressionParser::new();
+        assert!(parser.parse("def inc . + 1; inc").is_err());
+        assert!(parser.parse("def inc: . + 1").is_err());
+        assert!(parser.parse("def f(a b): a; f(1)").is_err());
+    }
+
+    #[test]
+    fn test_parse_array_of_expressions() {
+        let parser = ExpressionParser::new();
+        let expr = parser.parse("[.[] | .x, 1 + 1]").unwrap();
+        let Expression::Array { elements } = expr else {
+            panic!("expected array, got {:?}", expr);
+        };
+        assert_eq!(elements.len(), 1);
+        assert!(matches!(elements[0], Expression::Pipe { .. }));
+    }
+
     #[test]
     fn test_parse_object() {
         let parser = ExpressionParser::new();
@@ -1729,6 +2885,50 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_object_bare_key() {
+        let parser = ExpressionParser::new();
+        let expr = parser.parse("{name: 1, if: 2}").unwrap();
+        let Expression::Object { fields } = expr else {
+            panic!("expected object, got {:?}", expr);
+        };
+        assert_eq!(
+            fields[0].0,
+            Expression::Literal(serde_yaml::Value::String("name".to_string()))
+        );
+        assert_eq!(
+            fields[1].0,
+            Expression::Literal(serde_yaml::Value::String("if".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_object_shorthands() {
+        let parser = ExpressionParser::new();
+        let expr = parser.parse("{name, $x}").unwrap();
+        assert_eq!(
+            expr,
+            Expression::Object {
+                fields: vec![
+                    (
+                        Expression::Literal(serde_yaml::Value::String("name".to_string())),
+                        Expression::FieldAccess {
+                            target: Box::new(Expression::Identity),
+                            field: "name".to_string(),
+                        },
+                    ),
+                    (
+                        Expression::Literal(serde_yaml::Value::String("x".to_string())),
+                        Expression::Variable {
+                            name: "x".to_string()
+                        },
+                    ),
+                ],
+            }
+        );
+        assert!(parser.parse("{(.k)}").is_err());
+    }
+
     #[test]
     fn test_parse_select() {
         let parser = ExpressionParser::new();
@@ -1859,12 +3059,306 @@ mod tests {
     fn test_parse_empty() {
         let parser = ExpressionParser::new();
         let expr = parser.parse("empty").unwrap();
+        assert_eq!(expr, Expression::Empty);
+    }
+
+    #[test]
+    fn test_parse_env_object_field() {
+        let parser = ExpressionParser::new();
+        let expr = parser.parse("$ENV.HOME").unwrap();
+        assert_eq!(
+            expr,
+            Expression::FieldAccess {
+                target: Box::new(Expression::Variable {
+                    name: "ENV".to_string(),
+                }),
+                field: "HOME".to_string(),
+            }
+        );
+
+        let expr = parser.parse("env.HOME").unwrap();
         assert_eq!(
             expr,
             Expression::FieldAccess {
+                target: Box::new(Expression::Env { name: None }),
+                field: "HOME".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_optional_access() {
+        let parser = ExpressionParser::new();
+        let expr = parser.parse(".foo?").unwrap();
+        assert_eq!(
+            expr,
+            Expression::Try {
+                expr: Box::new(Expression::FieldAccess {
+                    target: Box::new(Expression::Identity),
+                    field: "foo".to_string(),
+                }),
+                catch: None,
+            }
+        );
+
+        let expr = parser.parse(".[0]?").unwrap();
+        assert!(matches!(expr, Expression::Try { catch: None, .. }));
+    }
+
+    #[test]
+    fn test_parse_optional_term() {
+        let parser = ExpressionParser::new();
+        let expr = parser.parse("tonumber?").unwrap();
+        assert_eq!(
+            expr,
+            Expression::Try {
+                expr: Box::new(Expression::ToNumber {
+                    target: Box::new(Expression::Identity),
+                }),
+                catch: None,
+            }
+        );
+
+        let expr = parser.parse("(.a | tonumber)?").unwrap();
+        let Expression::Group { expr } = expr else {
+            panic!("expected group, got {:?}", expr);
+        };
+        assert!(matches!(*expr, Expression::Try { catch: None, .. }));
+
+        for input in ["tostring(.a)?", "$x?"] {
+            let expr = parser.parse(input).unwrap();
+            assert!(
+                matches!(expr, Expression::Try { catch: None, .. }),
+                "{} parsed as {:?}",
+                input,
+                expr
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_map_single_argument() {
+        let parser = ExpressionParser::new();
+        let expr = parser.parse("map(.x)").unwrap();
+        assert_eq!(
+            expr,
+            Expression::Map {
+                target: Box::new(Expression::Identity),
+                expr: Box::new(Expression::FieldAccess {
+                    target: Box::new(Expression::Identity),
+                    field: "x".to_string(),
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_function_arguments_separated_by_semicolon() {
+        let parser = ExpressionParser::new();
+        let expr = parser.parse("map(.items; .x)").unwrap();
+        assert!(matches!(expr, Expression::Map { .. }));
+        assert!(parser.parse("map(.items; .x; .y)").is_err());
+    }
+
+    #[test]
+    fn test_parse_single_argument_filters_default_to_input() {
+        let parser = ExpressionParser::new();
+        match parser.parse("group_by(.k)").unwrap() {
+            Expression::GroupBy { target, .. } => assert_eq!(*target, Expression::Identity),
+            other => panic!("unexpected expression: {:?}", other),
+        }
+        match parser.parse("filter(.active)").unwrap() {
+            Expression::Filter { target, .. } => assert_eq!(*target, Expression::Identity),
+            other => panic!("unexpected expression: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_recurse_variants() {
+        let parser = ExpressionParser::new();
+        assert_eq!(parser.parse("recurse").unwrap(), Expression::Recurse);
+        assert!(matches!(
+            parser.parse("recurse(.a)").unwrap(),
+            Expression::RecurseF { cond: None, .. }
+        ));
+        assert!(matches!(
+            parser.parse("recurse(.a; . != null)").unwrap(),
+            Expression::RecurseF { cond: Some(_), .. }
+        ));
+    }
+
+    #[test]
+    fn test_parse_stream_functions() {
+        let parser = ExpressionParser::new();
+        assert_eq!(parser.parse("tostream").unwrap(), Expression::ToStream);
+        assert!(matches!(
+            parser.parse("fromstream(tostream)").unwrap(),
+            Expression::FromStream { .. }
+        ));
+        assert!(parser.parse("fromstream(.a; .b)").is_err());
+    }
+
+    #[test]
+    fn test_parse_splits() {
+        let parser = ExpressionParser::new();
+        assert!(matches!(
+            parser.parse(r#"splits("a")"#).unwrap(),
+            Expression::Splits { flags: None, .. }
+        ));
+        assert!(matches!(
+            parser.parse(r#"splits("a"; "i")"#).unwrap(),
+            Expression::Splits { flags: Some(_), .. }
+        ));
+        assert!(parser.parse("splits()").is_err());
+    }
+
+    #[test]
+    fn test_parse_format() {
+        let parser = ExpressionParser::new();
+        assert_eq!(
+            parser.parse("@uri").unwrap(),
+            Expression::Format {
+                target: Box::new(Expression::Identity),
+                fmt: "uri".to_string(),
+            }
+        );
+        assert!(parser.parse("@").is_err());
+    }
+
+    #[test]
+    fn test_parse_path() {
+        let parser = ExpressionParser::new();
+        assert!(matches!(
+            parser.parse("path(.a[0])").unwrap(),
+            Expression::Path { .. }
+        ));
+        assert!(parser.parse("path(.a; .b)").is_err());
+    }
+
+    #[test]
+    fn test_parse_assignment_rhs_includes_arithmetic() {
+        let parser = ExpressionParser::new();
+        let expected = Expression::Update {
+            target: Box::new(Expression::FieldAccess {
                 target: Box::new(Expression::Identity),
-                field: "empty".to_string(),
+                field: "a".to_string(),
+            }),
+            value: Box::new(Expression::Add {
+                left: Box::new(Expression::Identity),
+                right: Box::new(Expression::Literal(serde_yaml::Value::Number(1.into()))),
+            }),
+        };
+        assert_eq!(parser.parse(".a |= . + 1").unwrap(), expected);
+        assert!(matches!(
+            parser.parse(".a = .b // 1").unwrap(),
+            Expression::Assign { value, .. } if matches!(*value, Expression::Alternative { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_arithmetic_update() {
+        let parser = ExpressionParser::new();
+        for (expr, op) in [
+            (".a += 1", "+"),
+            (".a -= 1", "-"),
+            (".a *= 2", "*"),
+            (".a /= 2", "/"),
+            (".a %= 2", "%"),
+            (".a //= 1", "//"),
+        ] {
+            match parser.parse(expr).unwrap() {
+                Expression::ArithmeticUpdate { op: parsed, .. } => assert_eq!(parsed, op),
+                other => panic!("{} parsed as {:?}", expr, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_dynamic_index() {
+        let parser = ExpressionParser::new();
+        assert_eq!(
+            parser.parse(".[$k]").unwrap(),
+            Expression::DynamicIndex {
+                target: Box::new(Expression::Identity),
+                index: Box::new(Expression::Variable {
+                    name: "k".to_string()
+                }),
             }
         );
+        assert!(matches!(
+            parser.parse(".obj[.keyfield]").unwrap(),
+            Expression::DynamicIndex { .. }
+        ));
+        assert!(matches!(
+            parser.parse(r#".["a" + "b"]"#).unwrap(),
+            Expression::DynamicIndex { .. }
+        ));
+        assert!(matches!(
+            parser.parse(".[-1]").unwrap(),
+            Expression::IndexAccess { index: -1, .. }
+        ));
+        assert!(matches!(
+            parser.parse(r#".["key with spaces"]"#).unwrap(),
+            Expression::FieldAccess { .. }
+        ));
+    }
+
+    #[test]
+    fn test_parse_quoted_field_names() {
+        let parser = ExpressionParser::new();
+        let field = |target: Expression, name: &str| Expression::FieldAccess {
+            target: Box::new(target),
+            field: name.to_string(),
+        };
+        assert_eq!(
+            parser.parse(r#".["weird.key"]"#).unwrap(),
+            field(Expression::Identity, "weird.key")
+        );
+        assert_eq!(
+            parser.parse(r#"."weird.key""#).unwrap(),
+            field(Expression::Identity, "weird.key")
+        );
+        assert_eq!(
+            parser.parse(r#".foo["bar"]"#).unwrap(),
+            field(field(Expression::Identity, "foo"), "bar")
+        );
+        assert_eq!(
+            parser.parse(r#".foo."bar baz""#).unwrap(),
+            field(field(Expression::Identity, "foo"), "bar baz")
+        );
+        assert_eq!(
+            parser.parse(r#".["1st"]"#).unwrap(),
+            field(Expression::Identity, "1st")
+        );
+    }
+
+    #[test]
+    fn test_parse_if_then_else() {
+        let parser = ExpressionParser::new();
+        let expr = parser.parse("if . then 1 else 2 end").unwrap();
+        assert_eq!(
+            expr,
+            Expression::IfThenElse {
+                condition: Box::new(Expression::Identity),
+                then_branch: Box::new(Expression::Literal(serde_yaml::Value::from(1))),
+                else_branch: Box::new(Expression::Literal(serde_yaml::Value::from(2))),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_if_elif_without_else() {
+        let parser = ExpressionParser::new();
+        let expr = parser.parse("if .a then 1 elif .b then 2 end").unwrap();
+        match expr {
+            Expression::IfThenElse { else_branch, .. } => match *else_branch {
+                Expression::IfThenElse { else_branch, .. } => {
+                    assert_eq!(*else_branch, Expression::Identity)
+                }
+                other => panic!("unexpected else branch: {:?}", other),
+            },
+            other => panic!("unexpected expression: {:?}", other),
+        }
+        assert!(parser.parse("if . then 1").is_err());
     }
 }
@@ -1,7 +1,13 @@
 //! Input parsing for different data formats
 
 use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
 use serde_yaml::Value;
+use std::fmt;
+
+/// Tag carried by TOML datetimes so TOML output can restore their type
+pub const DATETIME_TAG: &str = "!datetime";
 
 /// Supported input formats
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -16,19 +22,42 @@ pub struct InputParser;
 
 impl InputParser {
     /// Parse input data into a YAML Value
-    pub fn parse(data: &str, format: InputFormat) -> Result<Value> {
+    ///
+    /// JSON and TOML values keep their types exactly. YAML plain scalars
+    /// are typed by the YAML 1.2 core schema (`123` is a number, `true` a
+    /// boolean, `1.10` the number 1.1), unless `yaml_strict_strings` is
+    /// set, in which case every YAML scalar is read as a string.
+    pub fn parse_with(data: &str, format: InputFormat, yaml_strict_strings: bool) -> Result<Value> {
         match format {
+            InputFormat::Yaml if yaml_strict_strings => Self::parse_yaml_strings(data),
             InputFormat::Yaml => Self::parse_yaml(data),
             InputFormat::Json => Self::parse_json(data),
             InputFormat::Toml => Self::parse_toml(data),
         }
     }
 
+    /// Parse input data into a YAML Value, typing YAML scalars as usual
+    pub fn parse(data: &str, format: InputFormat) -> Result<Value> {
+        Self::parse_with(data, format, false)
+    }
+
     /// Parse YAML input
     fn parse_yaml(data: &str) -> Result<Value> {
         serde_yaml::from_str(data).context("Failed to parse YAML")
     }
 
+    /// Parse YAML input with every scalar as a string (the failsafe schema)
+    ///
+    /// The document is read twice: once as usual to learn its shape, then
+    /// again following that shape, asking for the text of each scalar.
+    /// Values with a custom tag are kept as they are.
+    fn parse_yaml_strings(data: &str) -> Result<Value> {
+        let shape = Self::parse_yaml(data)?;
+        StrictStrings(&shape)
+            .deserialize(serde_yaml::Deserializer::from_str(data))
+            .context("Failed to parse YAML")
+    }
+
     /// Parse JSON input
     fn parse_json(data: &str) -> Result<Value> {
         let json_value: serde_json::Value =
@@ -50,6 +79,8 @@ impl InputParser {
             serde_json::Value::Number(n) => {
                 if let Some(i) = n.as_i64() {
                     Value::Number(i.into())
+                } else if let Some(u) = n.as_u64() {
+                    Value::Number(u.into())
                 } else if let Some(f) = n.as_f64() {
                     Value::Number(serde_yaml::Number::from(f))
                 } else {
@@ -77,7 +108,10 @@ impl InputParser {
             toml::Value::Integer(i) => Value::Number(i.into()),
             toml::Value::Float(f) => Value::Number(serde_yaml::Number::from(f)),
             toml::Value::Boolean(b) => Value::Bool(b),
-            toml::Value::Datetime(dt) => Value::String(dt.to_string()),
+            toml::Value::Datetime(dt) => Value::Tagged(Box::new(serde_yaml::value::TaggedValue {
+                tag: serde_yaml::value::Tag::new(DATETIME_TAG),
+                value: Value::String(dt.to_string()),
+            })),
             toml::Value::Array(arr) => {
                 Value::Sequence(arr.into_iter().map(Self::toml_to_yaml).collect())
             }
@@ -92,6 +126,60 @@ impl InputParser {
     }
 }
 
+/// Deserialize a YAML node with the shape of an already parsed value,
+/// reading every scalar as its text
+struct StrictStrings<'a>(&'a Value);
+
+impl<'de> DeserializeSeed<'de> for StrictStrings<'_> {
+    type Value = Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        match self.0 {
+            Value::Sequence(_) => deserializer.deserialize_seq(self),
+            Value::Mapping(_) => deserializer.deserialize_map(self),
+            Value::Tagged(_) => Value::deserialize(deserializer),
+            _ => String::deserialize(deserializer).map(Value::String),
+        }
+    }
+}
+
+impl<'de> Visitor<'de> for StrictStrings<'_> {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a YAML node")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let Value::Sequence(shape) = self.0 else {
+            return Err(de::Error::invalid_type(de::Unexpected::Seq, &self));
+        };
+        let mut items = Vec::with_capacity(shape.len());
+        for item in shape {
+            let value = seq
+                .next_element_seed(StrictStrings(item))?
+                .ok_or_else(|| de::Error::invalid_length(items.len(), &self))?;
+            items.push(value);
+        }
+        Ok(Value::Sequence(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let Value::Mapping(shape) = self.0 else {
+            return Err(de::Error::invalid_type(de::Unexpected::Map, &self));
+        };
+        let mut entries = serde_yaml::Mapping::with_capacity(shape.len());
+        for (key, value) in shape {
+            let key = map
+                .next_key_seed(StrictStrings(key))?
+                .ok_or_else(|| de::Error::invalid_length(entries.len(), &self))?;
+            let value = map.next_value_seed(StrictStrings(value))?;
+            entries.insert(key, value);
+        }
+        Ok(Value::Mapping(entries))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -132,4 +220,52 @@ value = 42"#;
         assert!(result["root"]["items"].is_sequence());
         assert_eq!(result["root"]["items"][0]["name"], "foo");
     }
+
+    #[test]
+    fn test_parse_toml_datetime_is_tagged() {
+        let value =
+            InputParser::parse("d = 2020-01-01\ns = \"2020-01-01\"", InputFormat::Toml).unwrap();
+        match &value["d"] {
+            Value::Tagged(tagged) => {
+                assert_eq!(tagged.tag, DATETIME_TAG);
+                assert_eq!(tagged.value, "2020-01-01");
+            }
+            other => panic!("expected tagged datetime, got {:?}", other),
+        }
+        assert_eq!(value["s"], "2020-01-01");
+    }
+
+    #[test]
+    fn test_parse_yaml_strict_strings() {
+        let yaml = "zip: 01234\nversion: 1.10\nok: yes\nflag: true\nn: [1, 2.50, null, ~, '']\nnested: {k: 0x1F}\n";
+        let value = InputParser::parse_with(yaml, InputFormat::Yaml, true).unwrap();
+        let expected: Value = serde_yaml::from_str(
+            "zip: '01234'\nversion: '1.10'\nok: 'yes'\nflag: 'true'\nn: ['1', '2.50', 'null', '~', '']\nnested: {k: '0x1F'}\n",
+        )
+        .unwrap();
+        assert_eq!(value, expected);
+
+        // Without the flag the same scalars are typed
+        let value = InputParser::parse(yaml, InputFormat::Yaml).unwrap();
+        assert_eq!(value["zip"], "01234");
+        assert_eq!(value["version"], 1.1);
+        assert_eq!(value["flag"], true);
+    }
+
+    #[test]
+    fn test_parse_yaml_strict_strings_follows_aliases_and_tags() {
+        let yaml = "base: &b {port: 80}\ncopy: *b\nt: !custom 5\n";
+        let value = InputParser::parse_with(yaml, InputFormat::Yaml, true).unwrap();
+        assert_eq!(value["copy"]["port"], "80");
+        assert!(matches!(value["t"], Value::Tagged(_)));
+    }
+
+    #[test]
+    fn test_parse_json_keeps_types() {
+        let json = r#"{"zip": "01234", "ok": "yes", "big": 18446744073709551615}"#;
+        let value = InputParser::parse_with(json, InputFormat::Json, true).unwrap();
+        assert_eq!(value["zip"], "01234");
+        assert_eq!(value["ok"], "yes");
+        assert_eq!(value["big"], 18446744073709551615u64);
+    }
 }
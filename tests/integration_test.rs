@@ -25,6 +25,66 @@ fn rq(args: &[&str]) -> Result<String, String> {
     }
 }
 
+/// Run rq with given arguments and extra environment variables
+fn rq_env(args: &[&str], vars: &[(&str, &str)]) -> Result<String, String> {
+    let output = Command::new(env!("CARGO_BIN_EXE_rq"))
+        .args(args)
+        .envs(vars.iter().copied())
+        .output()
+        .map_err(|e| format!("Failed to run rq: {}", e))?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).to_string())
+    }
+}
+
+/// Run rq with given arguments and return its exit code
+fn rq_status(args: &[&str]) -> Option<i32> {
+    Command::new(env!("CARGO_BIN_EXE_rq"))
+        .args(args)
+        .output()
+        .expect("Failed to run rq")
+        .status
+        .code()
+}
+
+// ==================== Parse Errors ====================
+
+#[test]
+fn test_parse_error_shows_position() {
+    let err = rq(&["-n", ".a + * 2"]).unwrap_err();
+    assert!(err.contains("at offset 5"), "{}", err);
+    assert!(err.contains(".a + * 2"), "{}", err);
+}
+
+#[test]
+fn test_deeply_nested_expression_fails_cleanly() {
+    let deep = format!("{}1{}", "[".repeat(10_000), "]".repeat(10_000));
+    let status = rq_status(&["-n", &deep]);
+    assert_eq!(status, Some(1));
+    let err = rq(&["-n", &deep]).unwrap_err();
+    assert!(err.contains("nests too deeply"), "{}", err);
+}
+
+#[test]
+fn test_deeply_nested_document_fails_cleanly() {
+    // The JSON parser's own nesting limit rejects this before `..` runs;
+    // the evaluator's depth guard is covered by the unit tests in recurse.rs
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("deep.json");
+    std::fs::write(
+        &file,
+        format!("{}{}", "[".repeat(10_000), "]".repeat(10_000)),
+    )
+    .unwrap();
+    let file = file.to_string_lossy();
+    assert_eq!(rq_status(&["..", &file]), Some(1));
+    let err = rq(&["..", &file]).unwrap_err();
+    assert!(err.contains("recursion limit exceeded"), "{}", err);
+}
+
 // ==================== Basic Field Access ====================
 
 #[test]
@@ -72,6 +132,15 @@ fn test_deep_nested_field_access() {
     assert_eq!(result.trim(), "found");
 }
 
+#[test]
+fn test_special_character_keys() {
+    let input = r#"{"weird.key": 1, "a b": {"1st": 2}}"#;
+    let result = rq(&["-n", &format!(r#"{} | .["weird.key"]"#, input)]).unwrap();
+    assert_eq!(result.trim(), "1");
+    let result = rq(&["-n", &format!(r#"{} | ."a b"["1st"]"#, input)]).unwrap();
+    assert_eq!(result.trim(), "2");
+}
+
 // ==================== Array Operations ====================
 
 #[test]
@@ -120,6 +189,39 @@ fn test_array_slice() {
     assert!(result.contains("3"));
 }
 
+#[test]
+fn test_sort_generator_hint() {
+    let result = rq(&["-n", "-o", "json", "-c", "[3, 1, 2] | [.[]] | sort"]).unwrap();
+    assert_eq!(result.trim(), "[1,2,3]");
+    let err = rq(&["-n", "[3, 1, 2] | .[] | sort"]).unwrap_err();
+    assert!(err.contains("[.[]] | sort"), "{}", err);
+}
+
+#[test]
+fn test_object_construction_shorthands() {
+    let result = rq(&[
+        "-n",
+        "-o",
+        "json",
+        "-c",
+        "--arg",
+        "x",
+        "1",
+        r#"{"k": "v"} | {k, $x, (.k): 2}"#,
+    ])
+    .unwrap();
+    assert_eq!(result.trim(), r#"{"k":"v","x":"1","v":2}"#);
+}
+
+#[test]
+fn test_object_construction_generators() {
+    let result = rq(&["-n", "-o", "json", "-c", "{a: (1,2)}"]).unwrap();
+    assert_eq!(result, "{\"a\":1}\n{\"a\":2}\n");
+
+    let result = rq(&["-n", "-o", "json", "-c", "[range(3)]"]).unwrap();
+    assert_eq!(result, "[0,1,2]\n");
+}
+
 // ==================== Piping ====================
 
 #[test]
@@ -154,6 +256,30 @@ fn test_select_filter() {
     assert!(result.contains("Alice"));
 }
 
+#[test]
+fn test_map_single_argument() {
+    let result = rq(&[
+        "-o",
+        "json",
+        ".users | map(.name)",
+        &fixture("sample.yaml").to_string_lossy(),
+    ])
+    .unwrap();
+    assert_eq!(result.trim(), r#"["Alice","Bob","Charlie"]"#);
+}
+
+#[test]
+fn test_map_select() {
+    let result = rq(&[
+        "-o",
+        "json",
+        ".users | map(select(.active) | .id)",
+        &fixture("sample.yaml").to_string_lossy(),
+    ])
+    .unwrap();
+    assert_eq!(result.trim(), "[1,3]");
+}
+
 // ==================== Format Conversion ====================
 
 #[test]
@@ -176,6 +302,311 @@ fn test_yaml_to_json() {
     assert!(result.contains("\"name\""));
 }
 
+#[test]
+fn test_sort_keys_flag() {
+    let result = rq(&[
+        "-S",
+        "-o",
+        "json",
+        ".author",
+        &fixture("sample.yaml").to_string_lossy(),
+    ])
+    .unwrap();
+    assert_eq!(
+        result.trim(),
+        r#"{"email":"alice@example.com","name":"Alice"}"#
+    );
+}
+
+#[test]
+fn test_csv_output() {
+    let result = rq(&[
+        "-o",
+        "csv",
+        ".users",
+        &fixture("sample.yaml").to_string_lossy(),
+    ])
+    .unwrap();
+    assert_eq!(
+        result,
+        "id,name,active\n1,Alice,true\n2,Bob,false\n3,Charlie,true\n"
+    );
+}
+
+#[test]
+fn test_csv_header_is_union_of_keys() {
+    let expr = r#"[{"name": "a", "age": 1}, {"age": 2, "city": "x"}, {"name": "c"}]"#;
+    let result = rq(&["-n", "-o", "csv", expr]).unwrap();
+    assert_eq!(result, "name,age,city\na,1,\n,2,x\nc,,\n");
+
+    let result = rq(&["-n", "-o", "csv", "--csv-headers=false", expr]).unwrap();
+    assert_eq!(result, "a,1,\n,2,x\nc,,\n");
+}
+
+#[test]
+fn test_tsv_output() {
+    let result = rq(&[
+        "-o",
+        "tsv",
+        ".items",
+        &fixture("sample.yaml").to_string_lossy(),
+    ])
+    .unwrap();
+    assert_eq!(result, "name\tprice\nitem1\t10\nitem2\t20\nitem3\t30\n");
+}
+
+#[test]
+fn test_csv_output_non_tabular_error() {
+    let err = rq(&[
+        "-o",
+        "csv",
+        ".author",
+        &fixture("sample.yaml").to_string_lossy(),
+    ])
+    .unwrap_err();
+    assert!(err.contains("CSV output requires an array"));
+}
+
+#[test]
+fn test_indent_yaml_output() {
+    let result = rq(&[
+        "-I",
+        "4",
+        ".author",
+        &fixture("sample.json").to_string_lossy(),
+    ])
+    .unwrap();
+    assert_eq!(result, "---\nname: Alice\nemail: alice@example.com\n");
+
+    let result = rq(&[
+        "-I",
+        "4",
+        ".users | .[0:1]",
+        &fixture("sample.yaml").to_string_lossy(),
+    ])
+    .unwrap();
+    assert_eq!(
+        result,
+        "---\n-   id: 1\n    name: Alice\n    active: true\n"
+    );
+}
+
+#[test]
+fn test_indent_json_output() {
+    let result = rq(&[
+        "-P",
+        "-o",
+        "json",
+        "-I",
+        "4",
+        ".author",
+        &fixture("sample.yaml").to_string_lossy(),
+    ])
+    .unwrap();
+    assert_eq!(
+        result.trim(),
+        "{\n    \"name\": \"Alice\",\n    \"email\": \"alice@example.com\"\n}"
+    );
+}
+
+#[test]
+fn test_tab_json_output() {
+    let result = rq(&[
+        "-P",
+        "-o",
+        "json",
+        "--tab",
+        ".author",
+        &fixture("sample.yaml").to_string_lossy(),
+    ])
+    .unwrap();
+    assert_eq!(
+        result.trim(),
+        "{\n\t\"name\": \"Alice\",\n\t\"email\": \"alice@example.com\"\n}"
+    );
+}
+
+#[test]
+fn test_flow_yaml_output() {
+    let sample = fixture("sample.yaml");
+    let sample = sample.to_string_lossy();
+    let flow = rq(&["-N", "--flow", ".author", &sample]).unwrap();
+    assert_eq!(flow, "{name: Alice, email: alice@example.com}\n");
+    let alias = rq(&["-N", "--yaml-flow", ".author", &sample]).unwrap();
+    assert_eq!(alias, flow);
+}
+
+#[test]
+fn test_yaml_quote_strings() {
+    let expr = r#"{a: "no", b: "0123", c: 1}"#;
+    let result = rq(&["-n", "--yaml-quote-strings", "-N", expr]).unwrap();
+    assert_eq!(result, "\"a\": \"no\"\n\"b\": \"0123\"\n\"c\": 1\n");
+    let result = rq(&["-n", "--yaml-quote-strings", "-N", r#""no""#]).unwrap();
+    assert_eq!(result, "\"no\"\n");
+    // Reading the output back keeps the strings as strings
+    let result = rq(&["-n", "--yaml-quote-strings", "-N", expr]).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("quoted.yaml");
+    std::fs::write(&path, result).unwrap();
+    let json = rq(&["-o", "json", "-c", ".", &path.to_string_lossy()]).unwrap();
+    assert_eq!(json.trim(), r#"{"a":"no","b":"0123","c":1}"#);
+}
+
+#[test]
+fn test_string_types_survive_conversion() {
+    let dir = tempfile::tempdir().unwrap();
+    let json_path = dir.path().join("zip.json");
+    std::fs::write(&json_path, r#"{"zip":"01234","ok":"yes"}"#).unwrap();
+    let yaml = rq(&["-N", ".", &json_path.to_string_lossy()]).unwrap();
+    let yaml_path = dir.path().join("zip.yaml");
+    std::fs::write(&yaml_path, &yaml).unwrap();
+    let json = rq(&["-o", "json", "-c", ".", &yaml_path.to_string_lossy()]).unwrap();
+    assert_eq!(json.trim(), r#"{"zip":"01234","ok":"yes"}"#);
+
+    // A bare YAML scalar is typed unless --yaml-strict-strings is given
+    std::fs::write(&yaml_path, "zip: 01234\nversion: 1.10\nflag: true\n").unwrap();
+    let yaml_path = yaml_path.to_string_lossy();
+    let json = rq(&["-o", "json", "-c", ".", &yaml_path]).unwrap();
+    assert_eq!(json.trim(), r#"{"zip":"01234","version":1.1,"flag":true}"#);
+    let json = rq(&["--yaml-strict-strings", "-o", "json", "-c", ".", &yaml_path]).unwrap();
+    assert_eq!(
+        json.trim(),
+        r#"{"zip":"01234","version":"1.10","flag":"true"}"#
+    );
+}
+
+#[test]
+fn test_large_integers_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let json_path = dir.path().join("big.json");
+    std::fs::write(
+        &json_path,
+        r#"{"a":9007199254740993,"b":18446744073709551615}"#,
+    )
+    .unwrap();
+    let json_path = json_path.to_string_lossy();
+    let json = rq(&["-o", "json", "-c", ".", &json_path]).unwrap();
+    assert_eq!(
+        json.trim(),
+        r#"{"a":9007199254740993,"b":18446744073709551615}"#
+    );
+    let json = rq(&[
+        "-o",
+        "json",
+        "-c",
+        "[.a + 0, .a * 2 / 2, .b - 1]",
+        &json_path,
+    ])
+    .unwrap();
+    assert_eq!(
+        json.trim(),
+        "[9007199254740993,9007199254740993,18446744073709551614]"
+    );
+
+    // Through YAML and back
+    let yaml = rq(&["-N", ".", &json_path]).unwrap();
+    let yaml_path = dir.path().join("big.yaml");
+    std::fs::write(&yaml_path, yaml).unwrap();
+    let json = rq(&["-o", "json", "-c", ".", &yaml_path.to_string_lossy()]).unwrap();
+    assert_eq!(
+        json.trim(),
+        r#"{"a":9007199254740993,"b":18446744073709551615}"#
+    );
+
+    let err = rq(&[".b + 1", &json_path]).unwrap_err();
+    assert!(err.contains("outside the 64-bit integer range"), "{err}");
+}
+
+#[test]
+fn test_no_colors_when_redirected() {
+    let sample = fixture("sample.yaml");
+    let sample = sample.to_string_lossy();
+    for format in ["yaml", "json"] {
+        let result = rq(&["-o", format, ".author", &sample]).unwrap();
+        assert!(!result.contains('\u{1b}'), "unexpected ANSI in {format}");
+    }
+}
+
+#[test]
+fn test_forced_yaml_colors() {
+    let sample = fixture("sample.yaml");
+    let sample = sample.to_string_lossy();
+    let colored = rq(&["-C", ".author", &sample]).unwrap();
+    assert!(colored.contains('\u{1b}'));
+
+    let plain = rq(&["-C", "-M", ".author", &sample]).unwrap();
+    assert!(!plain.contains('\u{1b}'));
+}
+
+#[test]
+fn test_no_color_env() {
+    let sample = fixture("sample.yaml");
+    let sample = sample.to_string_lossy();
+    let plain = rq_env(&[".author", &sample], &[("NO_COLOR", "1")]).unwrap();
+    assert!(!plain.contains('\u{1b}'));
+
+    // -C still forces colors
+    let colored = rq_env(&["-C", ".author", &sample], &[("NO_COLOR", "1")]).unwrap();
+    assert!(colored.contains('\u{1b}'));
+}
+
+#[test]
+fn test_json_colors() {
+    let sample = fixture("sample.yaml");
+    let sample = sample.to_string_lossy();
+    let colored = rq(&["-C", "-o", "json", ".author", &sample]).unwrap();
+    assert!(colored.contains('\u{1b}'));
+
+    let plain = rq(&["-C", "-M", "-o", "json", ".author", &sample]).unwrap();
+    assert_eq!(
+        plain.trim(),
+        r#"{"name":"Alice","email":"alice@example.com"}"#
+    );
+}
+
+#[test]
+fn test_tab_overrides_indent() {
+    let result = rq(&[
+        "-P",
+        "-o",
+        "json",
+        "-I",
+        "4",
+        "--tab",
+        ".author",
+        &fixture("sample.yaml").to_string_lossy(),
+    ])
+    .unwrap();
+    assert!(result.contains("\n\t\"name\""));
+}
+
+#[test]
+fn test_indent_zero_output() {
+    let sample = fixture("sample.yaml");
+    let sample = sample.to_string_lossy();
+    let json = rq(&["-P", "-o", "json", "-I", "0", ".author", &sample]).unwrap();
+    assert_eq!(
+        json.trim(),
+        r#"{"name":"Alice","email":"alice@example.com"}"#
+    );
+    let yaml = rq(&["-N", "-I", "0", ".author", &sample]).unwrap();
+    assert_eq!(yaml.trim(), "{name: Alice, email: alice@example.com}");
+}
+
+#[test]
+fn test_compact_output() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("input.json");
+    std::fs::write(&path, "{\n  \"a\": 1,\n  \"b\": 2\n}\n").unwrap();
+    let path = path.to_string_lossy();
+
+    let result = rq(&["-c", "-P", "-o", "json", ".", &path]).unwrap();
+    assert_eq!(result, "{\"a\":1,\"b\":2}\n");
+
+    let result = rq(&["-c", "-N", ".", &path]).unwrap();
+    assert_eq!(result, "{a: 1, b: 2}\n");
+}
+
 // ==================== Arithmetic ====================
 
 #[test]
@@ -266,66 +697,1250 @@ fn test_type_function() {
     assert_eq!(result.trim(), "string");
 }
 
-// ==================== Assignment ====================
+#[test]
+fn test_contains_function() {
+    let result = rq(&["-n", r#""foobar" | contains("oba")"#]).unwrap();
+    assert_eq!(result.trim(), "true");
+    let result = rq(&["-n", "[1, 2] | contains([2, 2])"]).unwrap();
+    assert_eq!(result.trim(), "true");
+    assert!(rq(&["-n", r#"[1] | contains("a")"#]).is_err());
+}
 
 #[test]
-fn test_assignment() {
-    let result = rq(&[".count = 100", &fixture("sample.yaml").to_string_lossy()]).unwrap();
-    assert!(result.contains("100"));
+fn test_splits_regex() {
+    let result = rq(&["-n", "-o", "json", "-c", r#""a1b2c" | [splits("[0-9]")]"#]).unwrap();
+    assert_eq!(result.trim(), r#"["a","b","c"]"#);
 }
 
-// ==================== Alternative Operator ====================
+#[test]
+fn test_test_regex_flags() {
+    let result = rq(&["-n", r#""ABC" | test("abc"; "i")"#]).unwrap();
+    assert_eq!(result.trim(), "true");
+    let result = rq(&["-n", r#""ABC" | test("abc")"#]).unwrap();
+    assert_eq!(result.trim(), "false");
+}
 
 #[test]
-fn test_alternative() {
-    let result = rq(&["null // \"default\"", "-n"]).unwrap();
-    assert_eq!(result.trim(), "default");
+fn test_match_regex_global() {
+    let result = rq(&[
+        "-n",
+        "-o",
+        "json",
+        r#""a1b22" | match("[0-9]+"; "g") | .offset"#,
+    ])
+    .unwrap();
+    assert_eq!(result, "1\n3\n");
 }
 
-// ==================== Help ====================
+#[test]
+fn test_base32_format_round_trip() {
+    let result = rq(&["-n", r#""hello" | @base32"#]).unwrap();
+    assert_eq!(result.trim(), "NBSWY3DP");
+    let result = rq(&["-n", r#""hello" | @base32 | @base32d"#]).unwrap();
+    assert_eq!(result.trim(), "hello");
+}
 
 #[test]
-fn test_help_shown_without_args() {
-    let output = Command::new(env!("CARGO_BIN_EXE_rq"))
-        .output()
-        .expect("Failed to run rq");
+fn test_gsub_named_captures() {
+    let result = rq(&[
+        "-n",
+        "-o",
+        "json",
+        r#""2020-01" | gsub("(?<y>[0-9]+)-(?<m>[0-9]+)"; "\(.m)/\(.y)")"#,
+    ])
+    .unwrap();
+    assert_eq!(result.trim(), r#""01/2020""#);
+}
 
-    let output_str = String::from_utf8_lossy(&output.stdout);
-    assert!(output_str.contains("Usage:"));
+#[test]
+fn test_gsub_multibyte_input() {
+    let result = rq(&["-n", "-o", "json", r#""héllo wörld" | gsub("[éö]"; "_")"#]).unwrap();
+    assert_eq!(result.trim(), r#""h_llo w_rld""#);
 }
 
-// ==================== Auto Format Detection ====================
+#[test]
+fn test_string_interpolation() {
+    let result = rq(&[r#""\(.name) has \([1, 2] | length) tags""#, "-n"]).unwrap();
+    assert_eq!(result.trim(), "null has 2 tags");
+}
 
 #[test]
-fn test_auto_detect_json() {
-    let result = rq(&[".name", "-r", &fixture("sample.json").to_string_lossy()]).unwrap();
-    assert_eq!(result.trim(), "test-project");
+fn test_uri_format() {
+    let result = rq(&["-n", r#""a b/ü" | @uri"#]).unwrap();
+    assert_eq!(result.trim(), "a%20b%2F%C3%BC");
 }
 
 #[test]
-fn test_auto_detect_yaml() {
-    let result = rq(&[".name", "-r", &fixture("sample.yaml").to_string_lossy()]).unwrap();
-    assert_eq!(result.trim(), "test-project");
+fn test_sh_format() {
+    let result = rq(&["-n", r#"["it's", "x y"] | @sh"#]).unwrap();
+    assert_eq!(result.trim(), r#"'it'\''s' 'x y'"#);
 }
 
-// ==================== Number Access ====================
+#[test]
+fn test_html_format() {
+    let result = rq(&["-n", r#""<a>&\"" | @html"#]).unwrap();
+    assert_eq!(result.trim(), "&lt;a&gt;&amp;&quot;");
+}
 
 #[test]
-fn test_number_field() {
-    let result = rq(&[".count", "-r", &fixture("sample.yaml").to_string_lossy()]).unwrap();
-    assert_eq!(result.trim(), "42");
+fn test_path_function() {
+    let result = rq(&["-n", "-o", "json", "-c", "path(.a[0].b)"]).unwrap();
+    assert_eq!(result.trim(), r#"["a",0,"b"]"#);
+
+    let result = rq(&["-n", "-o", "json", "-c", "[1, 2] | path(.[])"]).unwrap();
+    assert_eq!(result, "[0]\n[1]\n");
 }
 
 #[test]
-fn test_boolean_field() {
-    let result = rq(&[".active", "-r", &fixture("sample.yaml").to_string_lossy()]).unwrap();
-    assert_eq!(result.trim(), "true");
+fn test_lazy_first_limit_nth() {
+    let result = rq(&["-n", "first(range(1000000000) | select(. == 5))"]).unwrap();
+    assert_eq!(result, "5\n");
+
+    let result = rq(&["-n", "-o", "json", "-c", "[limit(3; range(1000000000))]"]).unwrap();
+    assert_eq!(result, "[0,1,2]\n");
+
+    let result = rq(&["-n", "nth(4; range(1000000000) | . * 2)"]).unwrap();
+    assert_eq!(result, "8\n");
 }
 
-// ==================== Null Handling ====================
+#[test]
+fn test_min_max_by() {
+    let path = fixture("sample.json");
+    let path = path.to_string_lossy();
+    let result = rq(&["-r", ".users | min_by(.id) | .name", &path]).unwrap();
+    assert_eq!(result, "Alice\n");
+    let result = rq(&["-r", ".users | max_by(.id) | .name", &path]).unwrap();
+    assert_eq!(result, "Charlie\n");
+    let result = rq(&["-n", "[3, 1, 2] | max"]).unwrap();
+    assert_eq!(result, "3\n");
+}
 
 #[test]
-fn test_null_field() {
-    let result = rq(&[".empty", "-r", &fixture("sample.yaml").to_string_lossy()]).unwrap();
-    assert_eq!(result.trim(), "null");
+fn test_tonumber_optional() {
+    let result = rq(&[
+        "-n",
+        "-c",
+        "-o",
+        "json",
+        r#"["1","x","3"] | map(tonumber?)"#,
+    ])
+    .unwrap();
+    assert_eq!(result, "[1,3]\n");
+    let result = rq(&[
+        "-n",
+        "-c",
+        "-o",
+        "json",
+        r#"["1","x","3"] | map(tonumber? // 0)"#,
+    ])
+    .unwrap();
+    assert_eq!(result, "[1,0,3]\n");
+}
+
+#[test]
+fn test_date_functions() {
+    assert_eq!(
+        rq(&["-n", "1425599621 | todate"]).unwrap(),
+        "2015-03-05T23:53:41Z\n"
+    );
+    assert_eq!(
+        rq(&["-n", r#""2015-03-05T23:53:41Z" | fromdate"#]).unwrap(),
+        "1425599621\n"
+    );
+    assert_eq!(
+        rq(&["-n", r#"0 | strftime("%Y-%m-%d")"#]).unwrap(),
+        "1970-01-01\n"
+    );
+    let err = rq(&["-n", r#""yesterday" | fromdate"#]).unwrap_err();
+    assert!(err.contains("does not match format"), "{}", err);
+}
+
+#[test]
+fn test_broken_down_time() {
+    assert_eq!(
+        rq(&["-n", "-o", "json", "-c", "1425599621 | gmtime"]).unwrap(),
+        "[2015,2,5,23,53,41,4,63]\n"
+    );
+    assert_eq!(
+        rq(&["-n", "1425599621 | gmtime | mktime"]).unwrap(),
+        "1425599621\n"
+    );
+    assert_eq!(
+        rq(&[
+            "-n",
+            r#""05 Mar 2015" | strptime("%d %b %Y") | mktime | todate"#
+        ])
+        .unwrap(),
+        "2015-03-05T00:00:00Z\n"
+    );
+}
+
+// ==================== Assignment ====================
+
+#[test]
+fn test_assignment() {
+    let result = rq(&[".count = 100", &fixture("sample.yaml").to_string_lossy()]).unwrap();
+    assert!(result.contains("100"));
+}
+
+#[test]
+fn test_update_assignment() {
+    let result = rq(&[
+        "-n",
+        "-o",
+        "json",
+        "-c",
+        r#"{"a": {"b": 1}, "items": [{"qty": 3}]} | .a.b |= . + 1 | .items[0].qty |= . * 2 | .new.x |= 7"#,
+    ])
+    .unwrap();
+    assert_eq!(
+        result.trim(),
+        r#"{"a":{"b":2},"items":[{"qty":6}],"new":{"x":7}}"#
+    );
+}
+
+#[test]
+fn test_arithmetic_update_assignment() {
+    let result = rq(&[
+        "-n",
+        "-o",
+        "json",
+        "-c",
+        r#"{"count": 1, "price": 10} | .count += 1 | .price *= 1.5 | .name //= "anon""#,
+    ])
+    .unwrap();
+    assert_eq!(result.trim(), r#"{"count":2,"price":15,"name":"anon"}"#);
+}
+
+#[test]
+fn test_nested_assignment() {
+    let result = rq(&["-n", "-o", "json", "-c", "{} | .a.b.c = 5"]).unwrap();
+    assert_eq!(result.trim(), r#"{"a":{"b":{"c":5}}}"#);
+
+    let result = rq(&["-n", "-o", "json", "-c", "[1, 2, 3] | .[1] = 9"]).unwrap();
+    assert_eq!(result.trim(), "[1,9,3]");
+}
+
+// ==================== Alternative Operator ====================
+
+#[test]
+fn test_alternative() {
+    let result = rq(&["null // \"default\"", "-n"]).unwrap();
+    assert_eq!(result.trim(), "default");
+}
+
+// ==================== Variables ====================
+
+#[test]
+fn test_as_binding() {
+    let result = rq(&["-n", "5 as $x | $x + 1"]).unwrap();
+    assert_eq!(result, "6\n");
+}
+
+#[test]
+fn test_as_binding_per_output() {
+    let input = fixture("sample.json");
+    let result = rq(&[
+        "-c",
+        "-o",
+        "json",
+        "[.users[] as $user | $user.name]",
+        input.to_str().unwrap(),
+    ])
+    .unwrap();
+    assert_eq!(result, "[\"Alice\",\"Bob\",\"Charlie\"]\n");
+}
+
+#[test]
+fn test_as_destructuring() {
+    let result = rq(&["-n", r#"{"a":1,"b":2} as {a:$a,b:$b} | $a+$b"#]).unwrap();
+    assert_eq!(result, "3\n");
+    let result = rq(&["-n", "-c", "-o", "json", "[1] as [$x, $y] | [$x, $y]"]).unwrap();
+    assert_eq!(result, "[1,null]\n");
+}
+
+#[test]
+fn test_arg_string_variable() {
+    let result = rq(&["-n", "--arg", "x", "hi", "$x"]).unwrap();
+    assert_eq!(result.trim(), "hi");
+}
+
+#[test]
+fn test_argjson_variable() {
+    let result = rq(&["-n", "--argjson", "n", "5", "$n + 1"]).unwrap();
+    assert_eq!(result.trim(), "6");
+}
+
+#[test]
+fn test_arg_value_stays_string() {
+    let result = rq(&["-n", "-o", "json", "--arg", "n", "5", "$n"]).unwrap();
+    assert_eq!(result.trim(), "\"5\"");
+}
+
+#[test]
+fn test_argjson_malformed() {
+    let err = rq(&["-n", "--argjson", "n", "{bad", "$n"]).unwrap_err();
+    assert!(err.contains("--argjson n"));
+}
+
+#[test]
+fn test_arg_as_dynamic_index() {
+    let result = rq(&[
+        "--arg",
+        "k",
+        "name",
+        ".[$k]",
+        &fixture("sample.yaml").to_string_lossy(),
+    ])
+    .unwrap();
+    let expected = rq(&[".name", &fixture("sample.yaml").to_string_lossy()]).unwrap();
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_args_positional() {
+    let result = rq(&[
+        "-n",
+        "-o",
+        "json",
+        "-c",
+        "$ARGS.positional",
+        "--args",
+        "a",
+        "b",
+        "c",
+    ])
+    .unwrap();
+    assert_eq!(result.trim(), r#"["a","b","c"]"#);
+}
+
+#[test]
+fn test_jsonargs_positional() {
+    let result = rq(&[
+        "-n",
+        "-o",
+        "json",
+        "-c",
+        "$ARGS.positional",
+        "--jsonargs",
+        "1",
+        r#"{"a":true}"#,
+    ])
+    .unwrap();
+    assert_eq!(result.trim(), r#"[1,{"a":true}]"#);
+}
+
+#[test]
+fn test_args_named() {
+    let result = rq(&[
+        "-n",
+        "-o",
+        "json",
+        "-c",
+        "--arg",
+        "s",
+        "x",
+        "--argjson",
+        "n",
+        "5",
+        "$ARGS",
+    ])
+    .unwrap();
+    assert_eq!(
+        result.trim(),
+        r#"{"positional":[],"named":{"s":"x","n":5}}"#
+    );
+}
+
+#[test]
+fn test_args_after_file() {
+    let result = rq(&[
+        "$ARGS.positional[0]",
+        &fixture("sample.yaml").to_string_lossy(),
+        "--args",
+        "first",
+    ])
+    .unwrap();
+    assert_eq!(result.trim(), "first");
+}
+
+#[test]
+fn test_jsonargs_malformed() {
+    let err = rq(&["-n", "$ARGS", "--jsonargs", "{bad"]).unwrap_err();
+    assert!(err.contains("--jsonargs"));
+}
+
+// ==================== Environment ====================
+
+#[test]
+fn test_env_variable_object() {
+    let result = rq_env(&["-n", "$ENV.RQ_TEST_VAR"], &[("RQ_TEST_VAR", "from-env")]).unwrap();
+    assert_eq!(result.trim(), "from-env");
+}
+
+#[test]
+fn test_env_object_field() {
+    let result = rq_env(&["-n", "env.RQ_TEST_VAR"], &[("RQ_TEST_VAR", "from-env")]).unwrap();
+    assert_eq!(result.trim(), "from-env");
+}
+
+#[test]
+fn test_env_function_single_name() {
+    let result = rq_env(
+        &["-n", "env(\"RQ_TEST_VAR\")"],
+        &[("RQ_TEST_VAR", "from-env")],
+    )
+    .unwrap();
+    assert_eq!(result.trim(), "from-env");
+}
+
+#[test]
+fn test_env_and_args_under_null_input() {
+    let result = rq_env(
+        &[
+            "-n",
+            "-o",
+            "json",
+            "-c",
+            "--arg",
+            "name",
+            "x",
+            "--argjson",
+            "n",
+            "2",
+            "[env.RQ_TEST_VAR, $ENV.RQ_TEST_VAR, $name, $n, env.HOME]",
+        ],
+        &[("RQ_TEST_VAR", "from-env"), ("HOME", "/home/rq")],
+    )
+    .unwrap();
+    assert_eq!(result.trim(), r#"["from-env","from-env","x",2,"/home/rq"]"#);
+}
+
+// ==================== Help ====================
+
+#[test]
+fn test_help_shown_without_args() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rq"))
+        .output()
+        .expect("Failed to run rq");
+
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    assert!(output_str.contains("Usage:"));
+}
+
+// ==================== Auto Format Detection ====================
+
+#[test]
+fn test_auto_detect_json() {
+    let result = rq(&[".name", "-r", &fixture("sample.json").to_string_lossy()]).unwrap();
+    assert_eq!(result.trim(), "test-project");
+}
+
+#[test]
+fn test_auto_detect_yaml() {
+    let result = rq(&[".name", "-r", &fixture("sample.yaml").to_string_lossy()]).unwrap();
+    assert_eq!(result.trim(), "test-project");
+}
+
+#[test]
+fn test_auto_detect_content_without_extension() {
+    let dir = tempfile::tempdir().unwrap();
+    let detect = |name: &str, content: &str, expr: &str| {
+        let path = dir.path().join(name);
+        std::fs::write(&path, content).unwrap();
+        rq(&["-o", "json", "-c", expr, &path.to_string_lossy()]).unwrap()
+    };
+
+    // Bare JSON values are JSON even where YAML would read them differently
+    let result = detect("number", "100000000000000000000\n", "[type, .]");
+    assert_eq!(result.trim(), r#"["number",1e+20]"#);
+    let result = detect("string", r#""\ud83d\ude00""#, "[type, explode]");
+    assert_eq!(result.trim(), r#"["string",[128512]]"#);
+
+    // A YAML document containing `=` stays YAML
+    let result = detect("yaml", "cmd: make CC=gcc\nenv:\n  - A=1\n", ".");
+    assert_eq!(result.trim(), r#"{"cmd":"make CC=gcc","env":["A=1"]}"#);
+
+    // TOML is recognized from its content
+    let result = detect(
+        "toml",
+        "# settings\ntitle = \"x\"\n\n[server]\nport = 80\n",
+        ".",
+    );
+    assert_eq!(result.trim(), r#"{"title":"x","server":{"port":80}}"#);
+    let result = detect("table", "[server]\nhost = \"h\"\n", ".server.host");
+    assert_eq!(result.trim(), r#""h""#);
+
+    // A bracketed line without key/value pairs is a YAML flow sequence
+    let result = detect("flow", "[a]\n", ".");
+    assert_eq!(result.trim(), r#"["a"]"#);
+    let result = detect("dotted", "[foo.bar]\n", ".");
+    assert_eq!(result.trim(), r#"["foo.bar"]"#);
+}
+
+// ==================== Number Access ====================
+
+#[test]
+fn test_number_field() {
+    let result = rq(&[".count", "-r", &fixture("sample.yaml").to_string_lossy()]).unwrap();
+    assert_eq!(result.trim(), "42");
+}
+
+#[test]
+fn test_boolean_field() {
+    let result = rq(&[".active", "-r", &fixture("sample.yaml").to_string_lossy()]).unwrap();
+    assert_eq!(result.trim(), "true");
+}
+
+// ==================== Null Handling ====================
+
+#[test]
+fn test_null_field() {
+    let result = rq(&[".empty", "-r", &fixture("sample.yaml").to_string_lossy()]).unwrap();
+    assert_eq!(result.trim(), "null");
+}
+
+// ==================== Number Rendering ====================
+
+#[test]
+fn test_integral_float_renders_as_integer() {
+    assert_eq!(rq(&["1.0", "-n"]).unwrap().trim(), "1");
+    assert_eq!(rq(&["1", "-n"]).unwrap().trim(), "1");
+    assert_eq!(rq(&["4 / 2", "-n", "-o", "json"]).unwrap().trim(), "2");
+    assert_eq!(rq(&["3 / 2", "-n", "-o", "json"]).unwrap().trim(), "1.5");
+}
+
+// ==================== TOML Output ====================
+
+#[test]
+fn test_toml_output_top_level_array_error() {
+    let err = rq(&["-o", "toml", "-n", "[1,2,3]"]).unwrap_err();
+    assert!(err.contains("TOML output requires an object at the top level"));
+}
+
+#[test]
+fn test_toml_output_nested_table() {
+    let result = rq(&[
+        "-o",
+        "toml",
+        ".nested",
+        &fixture("sample.json").to_string_lossy(),
+    ])
+    .unwrap();
+    assert_eq!(result, "[deep]\nvalue = \"found\"\n");
+}
+
+#[test]
+fn test_toml_output_omits_null_keys() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("input.json");
+    std::fs::write(&path, r#"{"a":1,"b":null}"#).unwrap();
+
+    let result = rq(&["-o", "toml", ".", &path.to_string_lossy()]).unwrap();
+    assert_eq!(result, "a = 1\n");
+}
+
+#[test]
+fn test_toml_datetimes_round_trip() {
+    let path = fixture("dates.toml");
+    let result = rq(&["-o", "toml", ".", &path.to_string_lossy()]).unwrap();
+
+    let original: toml::Value = toml::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    let round_tripped: toml::Value = toml::from_str(&result).unwrap();
+    assert_eq!(round_tripped, original);
+    assert!(round_tripped["local_date"].is_datetime());
+    assert!(round_tripped["schedule"]["starts"][0].is_datetime());
+    assert!(round_tripped["quoted"].is_str());
+}
+
+#[test]
+fn test_toml_datetimes_as_json_strings() {
+    let result = rq(&[
+        "-o",
+        "json",
+        ".offset",
+        &fixture("dates.toml").to_string_lossy(),
+    ])
+    .unwrap();
+    assert_eq!(result.trim(), "\"1979-05-27T07:32:00Z\"");
+}
+
+#[test]
+fn test_toml_datetimes_are_strings_in_expressions() {
+    let dates = fixture("dates.toml");
+    let dates = dates.to_string_lossy();
+    let run = |expr: &str| rq(&["-o", "json", "-c", expr, &dates]).unwrap();
+    assert_eq!(run(r#".local_date == "1979-05-27""#).trim(), "true");
+    assert_eq!(run(r#".local_date < .quoted"#).trim(), "true");
+    assert_eq!(run(".local_date | length").trim(), "10");
+    assert_eq!(run(".offset | fromdate").trim(), "296638320");
+    assert_eq!(
+        run(r#".local_time | split(":")"#).trim(),
+        r#"["07","32","00"]"#
+    );
+    assert_eq!(
+        run(r#"[(.offset | test("T")), (.local_date | startswith("1979"))]"#).trim(),
+        "[true,true]"
+    );
+    assert_eq!(run(r#".local_date + "!""#).trim(), r#""1979-05-27!""#);
+    assert_eq!(
+        run("[.schedule.starts[], .quoted] | sort").trim(),
+        r#"["2024-01-01","2024-03-01","2024-04-01"]"#
+    );
+}
+
+#[test]
+fn test_toml_datetimes_keep_type_when_left_in_place() {
+    let dates = fixture("dates.toml");
+    let dates = dates.to_string_lossy();
+    let result = rq(&["-o", "toml", r#".title = "x""#, &dates]).unwrap();
+    let edited: toml::Value = toml::from_str(&result).unwrap();
+    assert!(edited["offset"].is_datetime());
+    assert!(edited["schedule"]["starts"][0].is_datetime());
+    assert!(edited["quoted"].is_str());
+    // A datetime moved elsewhere is written as a string
+    let result = rq(&["-o", "toml", "{d: .offset}", &dates]).unwrap();
+    assert_eq!(result, "d = \"1979-05-27T07:32:00Z\"\n");
+}
+
+// ==================== Streaming Results ====================
+
+#[test]
+fn test_iterator_prints_each_value() {
+    let result = rq(&["-n", "[1, 2, 3] | .[]"]).unwrap();
+    assert_eq!(result, "1\n2\n3\n");
+}
+
+#[test]
+fn test_iterator_over_objects_prints_separate_documents() {
+    let result = rq(&[
+        "-o",
+        "json",
+        ".users[] | .name",
+        &fixture("sample.json").to_string_lossy(),
+    ])
+    .unwrap();
+    assert_eq!(result, "\"Alice\"\n\"Bob\"\n\"Charlie\"\n");
+
+    let result = rq(&[".items[]", &fixture("sample.yaml").to_string_lossy()]).unwrap();
+    assert_eq!(result.matches("---").count(), 3);
+}
+
+#[test]
+fn test_postfix_access_streams_per_element() {
+    let result = rq(&[
+        "-o",
+        "json",
+        ".users[].name",
+        &fixture("sample.json").to_string_lossy(),
+    ])
+    .unwrap();
+    assert_eq!(result, "\"Alice\"\n\"Bob\"\n\"Charlie\"\n");
+}
+
+#[test]
+fn test_comma_emits_each_value() {
+    assert_eq!(rq(&["-n", "1,2,3"]).unwrap(), "1\n2\n3\n");
+    assert_eq!(
+        rq(&["-n", "-o", "json", "-c", "[1,2],3"]).unwrap(),
+        "[1,2]\n3\n"
+    );
+    assert_eq!(
+        rq(&["-n", "-o", "json", "-c", "[1,2,3]"]).unwrap(),
+        "[1,2,3]\n"
+    );
+}
+
+#[test]
+fn test_empty_prints_nothing() {
+    assert_eq!(rq(&["-n", "empty"]).unwrap(), "");
+    assert_eq!(rq(&["-n"]).unwrap().trim(), "null");
+}
+
+#[test]
+fn test_seq_prefixes_each_json_record() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rq"))
+        .args(["--seq", "-n", "-o", "json", "[1, {\"a\": 2}] | .[]"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"\x1e1\n\x1e{\"a\":2}\n");
+}
+
+#[test]
+fn test_stream_input_mode() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("in.json");
+    std::fs::write(&file, r#"{"a": [1, 2]}"#).unwrap();
+    let result = rq(&["--stream", "-o", "json", "-c", ".", &file.to_string_lossy()]).unwrap();
+    assert_eq!(
+        result,
+        "[[\"a\",0],1]\n[[\"a\",1],2]\n[[\"a\",1]]\n[[\"a\"]]\n"
+    );
+}
+
+#[test]
+fn test_fromstream_tostream_round_trip() {
+    let result = rq(&[
+        "-o",
+        "json",
+        "-c",
+        "fromstream(tostream)",
+        &fixture("sample.json").to_string_lossy(),
+    ])
+    .unwrap();
+    let expected = rq(&[
+        "-o",
+        "json",
+        "-c",
+        ".",
+        &fixture("sample.json").to_string_lossy(),
+    ])
+    .unwrap();
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_ascii_output_escapes_non_ascii() {
+    let result = rq(&["-a", "-c", "-o", "json", "-n", r#""café""#]).unwrap();
+    assert_eq!(result.trim(), r#""caf\u00e9""#);
+}
+
+// ==================== Exit Status ====================
+
+#[test]
+fn test_exit_status_truthy_last_output() {
+    assert_eq!(rq_status(&["-e", "-n", "true"]), Some(0));
+    assert_eq!(rq_status(&["-e", "-n", "[]"]), Some(0));
+    assert_eq!(rq_status(&["-e", "-n", "[null, 1] | .[]"]), Some(0));
+}
+
+#[test]
+fn test_exit_status_null_or_false_last_output() {
+    assert_eq!(rq_status(&["-e", "-n", "null"]), Some(1));
+    assert_eq!(rq_status(&["-e", "-n", "false"]), Some(1));
+    assert_eq!(rq_status(&["-e", "-n", "[1, false] | .[]"]), Some(1));
+}
+
+#[test]
+fn test_exit_status_no_output() {
+    assert_eq!(rq_status(&["-e", "-n", "empty"]), Some(4));
+    assert_eq!(rq_status(&["-e", "-n", "[] | .[]"]), Some(4));
+}
+
+#[test]
+fn test_exit_status_ignored_without_flag() {
+    assert_eq!(rq_status(&["-n", "false"]), Some(0));
+    assert_eq!(rq_status(&["-n", "empty"]), Some(0));
+}
+
+// ==================== Function Definitions ====================
+
+#[test]
+fn test_inline_def() {
+    let result = rq(&[
+        "-n",
+        "-c",
+        "-o",
+        "json",
+        "def double: . * 2; [1,2,3] | map(double)",
+    ])
+    .unwrap();
+    assert_eq!(result, "[2,4,6]\n");
+}
+
+#[test]
+fn test_inline_def_with_value_param() {
+    let result = rq(&["-n", "def addn($n): . + $n; 1 | addn(2)"]).unwrap();
+    assert_eq!(result, "3\n");
+}
+
+#[test]
+fn test_recursive_def() {
+    let result = rq(&[
+        "-n",
+        "-c",
+        "-o",
+        "json",
+        "def down: if . > 0 then ., (. - 1 | down) else empty end; [3 | down]",
+    ])
+    .unwrap();
+    assert_eq!(result, "[3,2,1]\n");
+}
+
+#[test]
+fn test_unbounded_recursion_is_an_error() {
+    let err = rq(&["-n", "def f: 1 + f; f"]).unwrap_err();
+    assert!(err.contains("Maximum evaluation depth"), "{}", err);
+}
+
+// ==================== Expression Files ====================
+
+#[test]
+fn test_from_file_with_definition() {
+    let dir = tempfile::tempdir().unwrap();
+    let program = dir.path().join("inc.jq");
+    std::fs::write(&program, "def inc: . + 1;\n\ninc\n").unwrap();
+
+    let result = rq(&["-n", "-f", &program.to_string_lossy(), "-o", "json"]).unwrap();
+    assert_eq!(result, "1\n");
+}
+
+#[test]
+fn test_from_file_reads_first_positional_as_input() {
+    let dir = tempfile::tempdir().unwrap();
+    let program = dir.path().join("inc.jq");
+    let input = dir.path().join("input.json");
+    std::fs::write(&program, "def inc: . + 1;\nmap(inc)").unwrap();
+    std::fs::write(&input, "[1, 2]").unwrap();
+
+    let result = rq(&[
+        "-c",
+        "-o",
+        "json",
+        "--from-file",
+        &program.to_string_lossy(),
+        &input.to_string_lossy(),
+    ])
+    .unwrap();
+    assert_eq!(result, "[2,3]\n");
+}
+
+#[test]
+fn test_from_file_loc_reports_line() {
+    let dir = tempfile::tempdir().unwrap();
+    let program = dir.path().join("loc.jq");
+    std::fs::write(&program, ". as $x |\n$__loc__").unwrap();
+
+    let result = rq(&["-n", "-c", "-o", "json", "-f", &program.to_string_lossy()]).unwrap();
+    assert_eq!(result, "{\"file\":\"<stdin>\",\"line\":2}\n");
+}
+
+#[test]
+fn test_from_file_repeated_loads_definitions() {
+    let dir = tempfile::tempdir().unwrap();
+    let library = dir.path().join("lib.jq");
+    let program = dir.path().join("main.jq");
+    std::fs::write(&library, "def inc: . + 1;\ndef twice(f): f | f;\n").unwrap();
+    std::fs::write(&program, "twice(inc)").unwrap();
+
+    let result = rq(&[
+        "-n",
+        "-f",
+        &library.to_string_lossy(),
+        "-f",
+        &program.to_string_lossy(),
+    ])
+    .unwrap();
+    assert_eq!(result, "2\n");
+}
+
+// ==================== Multiple Input Files ====================
+
+#[test]
+fn test_multiple_json_files_evaluated_independently() {
+    let dir = tempfile::tempdir().unwrap();
+    let first = dir.path().join("a.json");
+    let second = dir.path().join("b.json");
+    std::fs::write(&first, r#"{"name": "a"}"#).unwrap();
+    std::fs::write(&second, r#"{"name": "b"}"#).unwrap();
+
+    let result = rq(&[
+        "-o",
+        "json",
+        ".name",
+        &first.to_string_lossy(),
+        &second.to_string_lossy(),
+    ])
+    .unwrap();
+    assert_eq!(result, "\"a\"\n\"b\"\n");
+}
+
+#[test]
+fn test_multiple_yaml_documents_separated() {
+    let dir = tempfile::tempdir().unwrap();
+    let first = dir.path().join("a.yaml");
+    let second = dir.path().join("b.yaml");
+    std::fs::write(&first, "a: 1\n").unwrap();
+    std::fs::write(&second, "b: 2\n").unwrap();
+    let first = first.to_string_lossy();
+    let second = second.to_string_lossy();
+
+    let result = rq(&[".", &first, &second]).unwrap();
+    assert_eq!(result, "---\na: 1\n---\nb: 2\n");
+
+    let result = rq(&["-N", ".", &first, &second]).unwrap();
+    assert_eq!(result, "a: 1\nb: 2\n");
+    assert!(!result.contains("---") && !result.contains("..."));
+}
+
+#[test]
+fn test_continue_skips_invalid_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let bad = dir.path().join("bad.json");
+    let good = dir.path().join("good.json");
+    std::fs::write(&bad, r#"{"name": "#).unwrap();
+    std::fs::write(&good, r#"{"name": "ok"}"#).unwrap();
+    let bad = bad.to_string_lossy();
+    let good = good.to_string_lossy();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rq"))
+        .args(["-k", "-o", "json", ".name", &bad, &good])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(5));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "\"ok\"\n");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("bad.json"), "stderr: {}", stderr);
+
+    // Without -k the first error aborts the run
+    let output = Command::new(env!("CARGO_BIN_EXE_rq"))
+        .args(["-o", "json", ".name", &bad, &good])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn test_continue_skips_evaluation_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let first = dir.path().join("a.json");
+    let second = dir.path().join("b.json");
+    std::fs::write(&first, r#"{"n": "x"}"#).unwrap();
+    std::fs::write(&second, r#"{"n": 1}"#).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rq"))
+        .args([
+            "--continue",
+            "-o",
+            "json",
+            ".n + 1",
+            &first.to_string_lossy(),
+            &second.to_string_lossy(),
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(5));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "2\n");
+}
+
+#[test]
+fn test_outputs_before_an_error_are_printed() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rq"))
+        .args(["-n", "-o", "json", r#"1, ("a" + 1)"#])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1\n");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Cannot add"), "stderr: {}", stderr);
+}
+
+#[test]
+fn test_continue_keeps_outputs_before_an_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let first = dir.path().join("a.json");
+    let second = dir.path().join("b.json");
+    std::fs::write(&first, r#"{"n": "x"}"#).unwrap();
+    std::fs::write(&second, r#"{"n": 1}"#).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rq"))
+        .args([
+            "-k",
+            "-o",
+            "json",
+            ".n, .n + 1",
+            &first.to_string_lossy(),
+            &second.to_string_lossy(),
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(5));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "\"x\"\n1\n2\n");
+}
+
+#[test]
+fn test_continue_without_errors_succeeds() {
+    let result = rq(&["-k", "-n", "1"]).unwrap();
+    assert_eq!(result.trim(), "1");
+}
+
+#[test]
+fn test_nul_output_separates_values() {
+    let dir = tempfile::tempdir().unwrap();
+    let first = dir.path().join("a.json");
+    let second = dir.path().join("b.json");
+    std::fs::write(&first, r#"{"name": "a"}"#).unwrap();
+    std::fs::write(&second, r#"{"name": "b"}"#).unwrap();
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_rq"))
+        .args([
+            "-0",
+            "-r",
+            ".name",
+            &first.to_string_lossy(),
+            &second.to_string_lossy(),
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"a\0b\0");
+}
+
+#[test]
+fn test_raw_output0() {
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_rq"))
+        .args([
+            "--raw-output0",
+            "-o",
+            "json",
+            "-n",
+            r#""a b", "c\nd", 1, {"e": null}"#,
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"a b\0c\nd\x001\0{\"e\":null}\0");
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("nul.json");
+    std::fs::write(&path, r#"{"name": "a\u0000b"}"#).unwrap();
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_rq"))
+        .args(["--raw-output0", ".name", &path.to_string_lossy()])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("containing NUL"));
+}
+
+#[test]
+fn test_multiple_files_of_different_formats() {
+    let result = rq(&[
+        "-r",
+        ".author.name",
+        &fixture("sample.yaml").to_string_lossy(),
+        &fixture("sample.json").to_string_lossy(),
+        &fixture("sample.toml").to_string_lossy(),
+    ])
+    .unwrap();
+    assert_eq!(result, "Alice\nAlice\nAlice\n");
+}
+
+// ==================== Raw Input and Slurp ====================
+
+#[test]
+fn test_raw_input_slurp_is_one_string() {
+    let lines = fixture("lines.txt");
+    let lines = lines.to_string_lossy();
+    let result = rq(&[
+        "-Rs",
+        r#"split("\n") | map(select(length > 0)) | length"#,
+        &lines,
+    ])
+    .unwrap();
+    assert_eq!(result.trim(), "4");
+    let result = rq(&["-R", "-s", "-o", "json", "-c", "[type, length]", &lines]).unwrap();
+    assert_eq!(result.trim(), r#"["string",48]"#);
+}
+
+#[test]
+fn test_raw_input_lines() {
+    let lines = fixture("lines.txt");
+    let result = rq(&["-R", "-o", "json", "-c", ".", &lines.to_string_lossy()]).unwrap();
+    assert_eq!(
+        result,
+        "\"first line\"\n\"second line\"\n\"\"\n\"fourth line\"\n\"\"\n\"sixth line\"\n"
+    );
+}
+
+#[test]
+fn test_slurp_collects_inputs() {
+    let result = rq(&[
+        "-s",
+        "-o",
+        "json",
+        "-c",
+        "map(.author.name)",
+        &fixture("sample.json").to_string_lossy(),
+        &fixture("sample.yaml").to_string_lossy(),
+    ])
+    .unwrap();
+    assert_eq!(result.trim(), r#"["Alice","Alice"]"#);
+}
+
+// ==================== In-place Editing ====================
+
+#[test]
+fn test_inplace_keeps_json_format() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sample.json");
+    std::fs::copy(fixture("sample.json"), &path).unwrap();
+
+    rq(&["-i", ".version = \"2.0.0\"", &path.to_string_lossy()]).unwrap();
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    let edited: serde_json::Value = serde_json::from_str(&content).unwrap();
+    assert_eq!(edited["version"], "2.0.0");
+    assert_eq!(edited["name"], "test-project");
+}
+
+#[test]
+fn test_inplace_keeps_toml_format() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sample.toml");
+    std::fs::copy(fixture("sample.toml"), &path).unwrap();
+
+    rq(&["-i", ".version = \"2.0.0\"", &path.to_string_lossy()]).unwrap();
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    let edited: toml::Value = toml::from_str(&content).unwrap();
+    assert_eq!(edited["version"].as_str(), Some("2.0.0"));
+    assert_eq!(edited["author"]["name"].as_str(), Some("Alice"));
+}
+
+#[test]
+fn test_inplace_failure_leaves_file_untouched() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sample.yaml");
+    std::fs::copy(fixture("sample.yaml"), &path).unwrap();
+    let original = std::fs::read_to_string(&path).unwrap();
+
+    assert!(rq(&["-i", ".name + 1", &path.to_string_lossy()]).is_err());
+
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), original);
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_inplace_preserves_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sample.yaml");
+    std::fs::copy(fixture("sample.yaml"), &path).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o640)).unwrap();
+
+    rq(&["-i", ".version = \"2.0.0\"", &path.to_string_lossy()]).unwrap();
+
+    let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o640);
+    assert!(std::fs::read_to_string(&path).unwrap().contains("2.0.0"));
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_inplace_follows_symlinks() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("sample.yaml");
+    let link = dir.path().join("link.yaml");
+    std::fs::copy(fixture("sample.yaml"), &target).unwrap();
+    std::os::unix::fs::symlink(&target, &link).unwrap();
+
+    rq(&["-i", ".version = \"2.0.0\"", &link.to_string_lossy()]).unwrap();
+
+    assert!(
+        std::fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink()
+    );
+    assert!(std::fs::read_to_string(&target).unwrap().contains("2.0.0"));
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+}
+
+#[test]
+fn test_inplace_never_writes_colors() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sample.yaml");
+    std::fs::copy(fixture("sample.yaml"), &path).unwrap();
+
+    rq(&["-C", "-i", ".version = \"2.0.0\"", &path.to_string_lossy()]).unwrap();
+
+    assert!(!std::fs::read_to_string(&path).unwrap().contains('\u{1b}'));
+}
+
+#[test]
+fn test_inplace_multiple_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let yaml = dir.path().join("a.yaml");
+    let json = dir.path().join("b.json");
+    std::fs::write(&yaml, "name: a\nversion: \"1\"\n").unwrap();
+    std::fs::write(&json, r#"{"name": "b", "version": "1"}"#).unwrap();
+
+    rq(&[
+        "-i",
+        ".version = \"2\"",
+        &yaml.to_string_lossy(),
+        &json.to_string_lossy(),
+    ])
+    .unwrap();
+
+    let edited: serde_yaml::Value =
+        serde_yaml::from_str(&std::fs::read_to_string(&yaml).unwrap()).unwrap();
+    assert_eq!(edited["name"], "a");
+    assert_eq!(edited["version"], "2");
+
+    let edited: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&json).unwrap()).unwrap();
+    assert_eq!(edited["name"], "b");
+    assert_eq!(edited["version"], "2");
+}
+
+#[test]
+fn test_inplace_requires_exactly_one_output() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("data.json");
+    std::fs::write(&path, r#"{"a": 1}"#).unwrap();
+    let file = path.to_string_lossy();
+
+    let err = rq(&["-i", "empty", &file]).unwrap_err();
+    assert!(err.contains("needs exactly one output, got 0"), "{}", err);
+    let err = rq(&["-i", ".a, .a", &file]).unwrap_err();
+    assert!(err.contains("needs exactly one output, got 2"), "{}", err);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), r#"{"a": 1}"#);
+}
+
+#[test]
+fn test_inplace_keep_going_skips_files_without_one_output() {
+    let dir = tempfile::tempdir().unwrap();
+    let first = dir.path().join("a.yaml");
+    let second = dir.path().join("b.yaml");
+    std::fs::write(&first, "n: [1, 2]\n").unwrap();
+    std::fs::write(&second, "n: [3]\n").unwrap();
+
+    let status = rq_status(&[
+        "-k",
+        "-i",
+        ".n[]",
+        &first.to_string_lossy(),
+        &second.to_string_lossy(),
+    ]);
+
+    assert_eq!(status, Some(5));
+    assert_eq!(std::fs::read_to_string(&first).unwrap(), "n: [1, 2]\n");
+    assert_eq!(std::fs::read_to_string(&second).unwrap().trim_end(), "3");
+}
+
+#[test]
+fn test_inplace_multiple_files_all_or_nothing_on_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let first = dir.path().join("a.yaml");
+    let second = dir.path().join("b.yaml");
+    std::fs::write(&first, "n: 1\n").unwrap();
+    std::fs::write(&second, "n: text\n").unwrap();
+
+    let result = rq(&[
+        "-i",
+        ".n = .n + 1",
+        &first.to_string_lossy(),
+        &second.to_string_lossy(),
+    ]);
+
+    assert!(result.is_err());
+    assert_eq!(std::fs::read_to_string(&first).unwrap(), "n: 1\n");
+    assert_eq!(std::fs::read_to_string(&second).unwrap(), "n: text\n");
 }